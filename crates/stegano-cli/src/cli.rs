@@ -1,7 +1,12 @@
-use clap::{Parser, Subcommand};
+use std::path::Path;
+
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
 use dialoguer::Password;
+use serde::Serialize;
+use stegano_core::{Algorithm, MediaFormat, SteganoError};
 
 use crate::commands::*;
+use crate::CliResult;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -11,15 +16,131 @@ pub struct CliArgs {
     #[arg(long = "x-color-step-increment", default_value = "1")]
     pub color_step_increment: u8,
 
+    /// Experimental: only every n-th audio sample carries data
+    #[arg(long = "x-audio-sample-step", default_value = "1")]
+    pub audio_sample_step: u32,
+
+    /// Experimental: obfuscates the header of an unencrypted secret, this is no encryption
+    #[arg(long = "x-obfuscate-header")]
+    pub obfuscate_header: bool,
+
+    /// Increases the log output, overrides `RUST_LOG`:
+    /// `-v` shows info, `-vv` and more show all debug and trace messages
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Only errors are logged, overrides `RUST_LOG`
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// How `list`, `capacity` and `unveil` print their results, `json` is meant for scripts
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    pub format: OutputFormat,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
+impl CliArgs {
+    /// The log level the flags ask for, `None` if neither `--quiet` nor `--verbose` was given
+    pub fn log_level(&self) -> Option<log::LevelFilter> {
+        match (self.quiet, self.verbose) {
+            (true, _) => Some(log::LevelFilter::Error),
+            (false, 0) => None,
+            (false, 1) => Some(log::LevelFilter::Info),
+            (false, _) => Some(log::LevelFilter::Trace),
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     Hide(hide::HideArgs),
     Unveil(unveil::UnveilArgs),
     UnveilRaw(unveil_raw::UnveilRawArgs),
+    List(list::ListArgs),
+    Capacity(capacity::CapacityArgs),
+    Inspect(inspect::InspectArgs),
+    LsbPreview(lsb_preview::LsbPreviewArgs),
+}
+
+/// The form in which results are printed to stdout
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// human readable text
+    #[default]
+    Text,
+    /// one JSON object
+    Json,
+}
+
+impl OutputFormat {
+    /// Prints the result of a command, as JSON or as the human readable `text` of it
+    pub fn print<T: Serialize>(self, result: &T, text: impl FnOnce(&T) -> String) {
+        match self {
+            OutputFormat::Text => println!("{}", text(result)),
+            OutputFormat::Json => println!(
+                "{}",
+                serde_json::to_string(result).expect("results are plain data and serialize")
+            ),
+        }
+    }
+}
+
+/// The steganography algorithm, by default it is picked by the carrier format
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum AlgorithmArg {
+    /// least significant bit, for PNG, BMP and WAV
+    Lsb,
+    /// F5, for JPEG, not supported by this build yet
+    F5,
+}
+
+impl From<AlgorithmArg> for Algorithm {
+    fn from(algorithm: AlgorithmArg) -> Self {
+        match algorithm {
+            AlgorithmArg::Lsb => Algorithm::Lsb,
+            AlgorithmArg::F5 => Algorithm::F5,
+        }
+    }
+}
+
+/// Refuses an explicitly chosen algorithm that does not fit the carrier,
+/// unknown formats are left to the loading of the media
+pub fn ensure_algorithm_fits(algorithm: Option<AlgorithmArg>, media: &Path) -> CliResult<()> {
+    match (algorithm, MediaFormat::of_file(media)) {
+        (Some(algorithm), Some(format)) => Algorithm::from(algorithm).ensure_fits(format),
+        _ => Ok(()),
+    }
+}
+
+/// The password of `--password`, `--password-file` or `--password-stdin`, only the first
+/// line of a file or stdin is used. Without any of them it is asked for interactively.
+pub fn resolve_password(
+    password: Option<String>,
+    password_file: Option<&Path>,
+    password_stdin: bool,
+    with_confirmation: bool,
+) -> CliResult<Option<String>> {
+    let line = if let Some(file) = password_file {
+        std::fs::read_to_string(file).map_err(|source| SteganoError::Io {
+            path: file.to_path_buf(),
+            source,
+        })?
+    } else if password_stdin {
+        let mut line = String::new();
+        std::io::stdin()
+            .read_line(&mut line)
+            .map_err(|source| SteganoError::ReadError { source })?;
+        line
+    } else if password.is_some() {
+        return Ok(password);
+    } else {
+        return Ok(ask_for_password(with_confirmation));
+    };
+
+    let password = line.lines().next().unwrap_or_default();
+    Ok((!password.is_empty()).then(|| password.to_string()))
 }
 
 pub fn ask_for_password(with_confirmation: bool) -> Option<String> {
@@ -41,3 +162,31 @@ pub fn ask_for_password(with_confirmation: bool) -> Option<String> {
         Some(password)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> CliArgs {
+        let base = ["stegano", "unveil", "-i", "in.png", "-o", "out"];
+        CliArgs::try_parse_from(base.iter().chain(args)).expect("Failed to parse args")
+    }
+
+    #[test]
+    fn should_map_flags_to_log_levels() {
+        assert_eq!(parse(&[]).log_level(), None);
+        assert_eq!(parse(&["-q"]).log_level(), Some(log::LevelFilter::Error));
+        assert_eq!(parse(&["-v"]).log_level(), Some(log::LevelFilter::Info));
+        assert_eq!(parse(&["-vv"]).log_level(), Some(log::LevelFilter::Trace));
+        assert_eq!(
+            parse(&["--verbose", "--verbose", "--verbose"]).log_level(),
+            Some(log::LevelFilter::Trace)
+        );
+    }
+
+    #[test]
+    fn should_reject_quiet_and_verbose_together() {
+        let args = ["stegano", "-q", "-v", "unveil", "-i", "in.png", "-o", "out"];
+        assert!(CliArgs::try_parse_from(args).is_err());
+    }
+}
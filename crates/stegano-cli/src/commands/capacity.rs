@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use serde::Serialize;
+use stegano_core::CodecOptions;
+
+use crate::cli::OutputFormat;
+use crate::CliResult;
+
+/// Tells how many bytes can be hidden in a media
+#[derive(Args, Debug)]
+pub struct CapacityArgs {
+    /// Media file such as PNG image or WAV audio file
+    #[arg(short = 'i', long = "in", value_name = "media file", required = true)]
+    pub media: PathBuf,
+}
+
+#[derive(Serialize, Debug)]
+struct Capacity {
+    capacity: usize,
+}
+
+impl CapacityArgs {
+    pub fn run(self, options: CodecOptions, format: OutputFormat) -> CliResult<()> {
+        let capacity = stegano_core::api::probe::capacity(&self.media, &options)?;
+
+        format.print(&Capacity { capacity }, |c| format!("{} bytes", c.capacity));
+
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
 use std::path::PathBuf;
 
 use clap::Args;
-use stegano_core::CodecOptions;
+use log::{error, info};
+use stegano_core::api::hide::{BatchOptions, OutputTemplate};
+use stegano_core::{CodecOptions, Message, SteganoError};
 
 use crate::CliResult;
 
@@ -12,11 +14,21 @@ pub struct HideArgs {
     #[arg(long, value_name = "password")]
     pub password: Option<String>,
 
+    /// Reads the password from the first line of this file, so that it does not end up in
+    /// the shell history or the process list
+    #[arg(long, value_name = "file", conflicts_with_all = ["password", "password_stdin"])]
+    pub password_file: Option<PathBuf>,
+
+    /// Reads the password from the first line of stdin
+    #[arg(long, conflicts_with = "password")]
+    pub password_stdin: bool,
+
     /// Media file such as PNG image or WAV audio file, used readonly.
+    /// A directory hides the data into all PNG and WAV files inside of it.
     #[arg(short = 'i', long = "in", value_name = "media file", required = true)]
     pub media: PathBuf,
 
-    /// Final image will be stored as file
+    /// Final image will be stored as file, or into this directory if `--in` is a directory
     #[arg(
         short = 'o',
         long = "out",
@@ -42,15 +54,43 @@ pub struct HideArgs {
         required_unless_present = "data_files"
     )]
     pub message: Option<String>,
+
+    /// Steganography algorithm, by default it is picked by the carrier format.
+    /// F5 requires a JPEG, LSB a lossless format like PNG or WAV
+    #[arg(long, value_enum, value_name = "algorithm")]
+    pub algorithm: Option<crate::cli::AlgorithmArg>,
+
+    /// Names the outputs when `--in` is a directory, `{stem}`, `{ext}` and `{index}` are
+    /// replaced per carrier, e.g. `{stem}-stego.{ext}`. By default the carrier names are kept
+    #[arg(long, value_name = "template")]
+    pub output_template: Option<String>,
+
+    /// Leaves out the metadata of the carrier, like text and EXIF chunks of a PNG,
+    /// so that the output carries no fingerprints of the original camera
+    #[arg(long)]
+    pub strip_metadata: bool,
+
+    /// Accepts an indexed PNG as carrier, the output is written with full colors
+    /// because the palette cannot keep the hidden data
+    #[arg(long)]
+    pub expand_palette: bool,
 }
 
 impl HideArgs {
-    pub fn run(self, options: CodecOptions) -> CliResult<()> {
-        let password = if self.password.is_none() {
-            crate::cli::ask_for_password(true)
-        } else {
-            self.password
-        };
+    pub fn run(mut self, options: CodecOptions) -> CliResult<()> {
+        if !self.media.is_dir() {
+            crate::cli::ensure_algorithm_fits(self.algorithm, &self.media)?;
+        }
+        let password = crate::cli::resolve_password(
+            self.password.take(),
+            self.password_file.as_deref(),
+            self.password_stdin,
+            true,
+        )?;
+
+        if self.media.is_dir() {
+            return self.hide_batch(password.as_deref(), &options);
+        }
 
         stegano_core::api::hide::prepare()
             .with_options(options)
@@ -59,6 +99,74 @@ impl HideArgs {
             .using_password(password)
             .use_files(self.data_files)
             .use_message(self.message)
+            .strip_metadata(self.strip_metadata)
+            .expand_palette(self.expand_palette)
             .execute()
     }
+
+    /// hides the data into all PNG and WAV files of the `--in` directory
+    fn hide_batch(self, password: Option<&str>, options: &CodecOptions) -> CliResult<()> {
+        let mut carriers = self
+            .media
+            .read_dir()
+            .map_err(|source| SteganoError::Io {
+                path: self.media.clone(),
+                source,
+            })?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| {
+                        ext.eq_ignore_ascii_case("png") || ext.eq_ignore_ascii_case("wav")
+                    })
+            })
+            .collect::<Vec<_>>();
+        carriers.sort();
+        for carrier in carriers.iter() {
+            crate::cli::ensure_algorithm_fits(self.algorithm, carrier)?;
+        }
+
+        let mut message = Message::empty();
+        for file in self.data_files.iter().flatten() {
+            message.add_file(file)?;
+        }
+        if let Some(text) = self.message {
+            message.add_file_data("secret-message.txt", text.into_bytes())?;
+        }
+
+        let batch = BatchOptions::default().strip_metadata(self.strip_metadata);
+        let results = match self.output_template {
+            Some(template) => stegano_core::api::hide::hide_batch_as(
+                &carriers,
+                &message,
+                &self.write_to_file,
+                &OutputTemplate::new(template),
+                password,
+                options,
+                &batch,
+            )?,
+            None => stegano_core::api::hide::hide_batch(
+                &carriers,
+                &message,
+                &self.write_to_file,
+                password,
+                options,
+                &batch,
+            )?,
+        };
+
+        let mut first_error = None;
+        for (carrier, result) in carriers.iter().zip(results) {
+            match result {
+                Ok(output) => info!("{} -> {}", carrier.display(), output.display()),
+                Err(e) => {
+                    error!("{}: {e}", carrier.display());
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
 }
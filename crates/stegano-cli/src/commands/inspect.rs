@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use serde::Serialize;
+use stegano_core::CodecOptions;
+
+use crate::cli::OutputFormat;
+use crate::CliResult;
+
+/// Tells what a media is and what secret data it holds, without a password
+#[derive(Args, Debug)]
+pub struct InspectArgs {
+    /// Media file such as PNG image or WAV audio file
+    #[arg(short = 'i', long = "in", value_name = "media file", required = true)]
+    pub media: PathBuf,
+}
+
+#[derive(Serialize, Debug)]
+struct Inspection {
+    format: String,
+    algorithm: String,
+    payload_version: Option<String>,
+    encrypted: bool,
+    file_count: Option<usize>,
+    total_payload_bytes: Option<usize>,
+    raw_capacity: usize,
+}
+
+impl InspectArgs {
+    pub fn run(self, options: CodecOptions, format: OutputFormat) -> CliResult<()> {
+        let inspection = stegano_core::api::probe::inspect(&self.media, &options)?;
+        let inspection = Inspection {
+            format: format!("{:?}", inspection.format),
+            algorithm: format!("{:?}", inspection.algorithm),
+            payload_version: inspection.payload_version.map(|v| format!("{v:?}")),
+            encrypted: inspection.encrypted,
+            file_count: inspection.file_count,
+            total_payload_bytes: inspection.total_payload_bytes,
+            raw_capacity: inspection.raw_capacity,
+        };
+
+        format.print(&inspection, |i| {
+            let unknown = || "-".to_string();
+            [
+                format!("format:        {}", i.format),
+                format!("algorithm:     {}", i.algorithm),
+                format!(
+                    "payload:       {}",
+                    i.payload_version.clone().unwrap_or_else(unknown)
+                ),
+                format!("encrypted:     {}", i.encrypted),
+                format!(
+                    "files:         {}",
+                    i.file_count.map_or_else(unknown, |n| n.to_string())
+                ),
+                format!(
+                    "payload size:  {}",
+                    i.total_payload_bytes
+                        .map_or_else(unknown, |n| format!("{n} bytes"))
+                ),
+                format!("capacity:      {} bytes", i.raw_capacity),
+            ]
+            .join("\n")
+        });
+
+        Ok(())
+    }
+}
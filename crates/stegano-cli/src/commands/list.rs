@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use serde::Serialize;
+use stegano_core::CodecOptions;
+
+use crate::cli::OutputFormat;
+use crate::CliResult;
+
+/// Lists the files hidden in a media without unveiling them
+#[derive(Args, Debug)]
+pub struct ListArgs {
+    /// Password used to encrypt the data
+    #[arg(short, long, value_name = "password")]
+    pub password: Option<String>,
+
+    /// Source media that contains secret data
+    #[arg(
+        short = 'i',
+        long = "in",
+        value_name = "media source file",
+        required = true
+    )]
+    pub media: PathBuf,
+}
+
+#[derive(Serialize, Debug)]
+struct Listing {
+    files: Vec<ListedFile>,
+}
+
+#[derive(Serialize, Debug)]
+struct ListedFile {
+    name: String,
+    size: usize,
+}
+
+impl ListArgs {
+    pub fn run(self, options: CodecOptions, format: OutputFormat) -> CliResult<()> {
+        let password = if self.password.is_none() {
+            crate::cli::ask_for_password(false)
+        } else {
+            self.password
+        };
+
+        let files = stegano_core::api::unveil::list(&self.media, password.as_deref(), &options)?
+            .into_iter()
+            .map(|file| ListedFile {
+                name: file.name,
+                size: file.size,
+            })
+            .collect();
+
+        format.print(&Listing { files }, |listing| {
+            listing
+                .files
+                .iter()
+                .map(|file| format!("{}\t{} bytes", file.name, file.size))
+                .collect::<Vec<_>>()
+                .join("\n")
+        });
+
+        Ok(())
+    }
+}
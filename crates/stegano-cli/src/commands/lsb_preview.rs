@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use serde::Serialize;
+
+use crate::cli::OutputFormat;
+use crate::CliResult;
+
+/// the shades from no set LSB to all set, random data shows as the middle one
+const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+/// Draws the least significant bits of an image as blocks, a sequentially hidden payload
+/// often stands out as an area of its own
+#[derive(Args, Debug)]
+pub struct LsbPreviewArgs {
+    /// Image file such as PNG or BMP
+    #[arg(short = 'i', long = "in", value_name = "image file", required = true)]
+    pub media: PathBuf,
+
+    /// The preview is at most this many characters wide
+    #[arg(long, default_value = "64")]
+    pub width: u32,
+}
+
+#[derive(Serialize, Debug)]
+struct Preview {
+    columns: u32,
+    rows: u32,
+    densities: Vec<f64>,
+}
+
+impl LsbPreviewArgs {
+    pub fn run(self, format: OutputFormat) -> CliResult<()> {
+        let preview = stegano_core::api::probe::lsb_preview(&self.media, self.width)?;
+        let preview = Preview {
+            columns: preview.columns,
+            rows: preview.rows,
+            densities: preview.densities,
+        };
+
+        format.print(&preview, |p| {
+            p.densities
+                .chunks(p.columns as usize)
+                .map(|row| {
+                    row.iter()
+                        .map(|d| SHADES[(d * (SHADES.len() - 1) as f64).round() as usize])
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        });
+
+        Ok(())
+    }
+}
@@ -1,3 +1,7 @@
+pub mod capacity;
 pub mod hide;
+pub mod inspect;
+pub mod list;
+pub mod lsb_preview;
 pub mod unveil;
 pub mod unveil_raw;
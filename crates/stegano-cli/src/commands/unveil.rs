@@ -1,8 +1,10 @@
 use std::path::PathBuf;
 
 use clap::Args;
+use serde::Serialize;
 use stegano_core::CodecOptions;
 
+use crate::cli::OutputFormat;
 use crate::CliResult;
 
 #[derive(Args, Debug)]
@@ -11,6 +13,15 @@ pub struct UnveilArgs {
     #[arg(short, long, value_name = "password")]
     pub password: Option<String>,
 
+    /// Reads the password from the first line of this file, so that it does not end up in
+    /// the shell history or the process list
+    #[arg(long, value_name = "file", conflicts_with_all = ["password", "password_stdin"])]
+    pub password_file: Option<PathBuf>,
+
+    /// Reads the password from the first line of stdin
+    #[arg(long, conflicts_with = "password")]
+    pub password_stdin: bool,
+
     /// Source image that contains secret data
     #[arg(
         short = 'i',
@@ -28,21 +39,84 @@ pub struct UnveilArgs {
         required = true
     )]
     pub output_folder: PathBuf,
+
+    /// Only this file will be unveiled, all others are skipped
+    #[arg(long = "only", value_name = "file name")]
+    pub only_file: Option<String>,
+
+    /// Overwrite files that already exist in the output folder
+    #[arg(long)]
+    pub force: bool,
+
+    /// Steganography algorithm, by default it is picked by the carrier format.
+    /// F5 requires a JPEG, LSB a lossless format like PNG or WAV
+    #[arg(long, value_enum, value_name = "algorithm")]
+    pub algorithm: Option<crate::cli::AlgorithmArg>,
+
+    /// Refuses to unveil more than this many bytes, a safeguard for media of unknown origin
+    /// whose hidden files claim to be huge
+    #[arg(long, value_name = "bytes")]
+    pub max_extract_bytes: Option<usize>,
+
+    /// Skips hidden files that are damaged instead of failing, the intact ones are unveiled
+    #[arg(long)]
+    pub best_effort: bool,
+}
+
+/// The files an unveil wrote
+#[derive(Serialize, Debug)]
+struct Unveiled {
+    files: Vec<PathBuf>,
+    /// the hidden files that were skipped, as they are damaged
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    damaged: Vec<String>,
 }
 
 impl UnveilArgs {
-    pub fn run(self, options: CodecOptions) -> CliResult<()> {
-        let password = if self.password.is_none() {
-            crate::cli::ask_for_password(false)
-        } else {
-            self.password
-        };
+    pub fn run(self, options: CodecOptions, format: OutputFormat) -> CliResult<()> {
+        crate::cli::ensure_algorithm_fits(self.algorithm, &self.media)?;
+        let password = crate::cli::resolve_password(
+            self.password,
+            self.password_file.as_deref(),
+            self.password_stdin,
+            false,
+        )?;
 
-        stegano_core::api::unveil::prepare()
+        let options = CodecOptions {
+            max_extract_bytes: self.max_extract_bytes,
+            best_effort: self.best_effort,
+            ..options
+        };
+        let mut api = stegano_core::api::unveil::prepare()
             .with_options(options)
             .from_secret_file(self.media)
             .into_output_folder(self.output_folder)
-            .using_password(password)
-            .execute()
+            .overwrite(self.force)
+            .using_password(password);
+        if let Some(name) = self.only_file {
+            api = api.only_file(name);
+        }
+
+        let report = api.execute_with_report()?;
+        let unveiled = Unveiled {
+            files: report.written,
+            damaged: report.damaged,
+        };
+        format.print(&unveiled, |unveiled| {
+            unveiled
+                .files
+                .iter()
+                .map(|file| file.display().to_string())
+                .chain(
+                    unveiled
+                        .damaged
+                        .iter()
+                        .map(|name| format!("{name} (damaged, skipped)")),
+                )
+                .collect::<Vec<_>>()
+                .join("\n")
+        });
+
+        Ok(())
     }
 }
@@ -9,9 +9,18 @@ mod commands;
 pub type CliResult<T> = std::result::Result<T, SteganoError>;
 
 fn main() -> Result<()> {
-    env_logger::init();
-
     let args = CliArgs::parse();
+
+    // the flags take precedence over `RUST_LOG`
+    let mut logger = match args.log_level() {
+        Some(level) => {
+            let mut logger = env_logger::Builder::new();
+            logger.filter_level(level);
+            logger
+        }
+        None => env_logger::Builder::from_default_env(),
+    };
+    logger.init();
     if let Err(err) = handle_subcommands(args) {
         eprintln!("{err}");
         std::process::exit(1);
@@ -24,14 +33,20 @@ fn handle_subcommands(args: CliArgs) -> CliResult<()> {
     let options = get_options(&args);
     match args.command {
         Commands::Hide(hide) => hide.run(options),
-        Commands::Unveil(unveil) => unveil.run(options),
+        Commands::Unveil(unveil) => unveil.run(options, args.format),
         Commands::UnveilRaw(unveil_raw) => unveil_raw.run(options),
+        Commands::List(list) => list.run(options, args.format),
+        Commands::Capacity(capacity) => capacity.run(options, args.format),
+        Commands::Inspect(inspect) => inspect.run(options, args.format),
+        Commands::LsbPreview(preview) => preview.run(args.format),
     }
 }
 
 fn get_options(args: &CliArgs) -> CodecOptions {
     CodecOptions {
         color_channel_step_increment: args.color_step_increment as _,
+        audio_sample_step: args.audio_sample_step,
+        obfuscate_header: args.obfuscate_header,
         ..Default::default()
     }
 }
@@ -0,0 +1,68 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn stegano(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_stegano"))
+        .args(args)
+        .output()
+        .expect("Failed to run stegano")
+}
+
+fn hide_with_algorithm(algorithm: &str, carrier: &str) -> Output {
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("algorithm-out.png");
+    stegano(&[
+        "hide",
+        "--algorithm",
+        algorithm,
+        "-i",
+        carrier,
+        "-o",
+        out.to_str().unwrap(),
+        "-m",
+        "Hello World!",
+        "--password",
+        "secret",
+    ])
+}
+
+#[test]
+fn should_refuse_f5_on_a_png() {
+    let output = hide_with_algorithm("f5", "../stegano-core/tests/images/plain/carrier-image.png");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("F5 cannot be used with Png"), "{stderr}");
+}
+
+#[test]
+fn should_refuse_f5_for_a_jpeg_carrier_as_not_supported() {
+    let output = hide_with_algorithm("f5", "../stegano-core/tests/images/NoSecrets.jpg");
+
+    // the algorithm fits the carrier, but there is no F5 implementation to hide with yet
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("cannot be used with"), "{stderr}");
+    assert!(
+        stderr.contains("F5 is not supported by this build"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn should_refuse_lsb_on_a_jpeg_when_unveiling() {
+    let output = stegano(&[
+        "unveil",
+        "--algorithm",
+        "lsb",
+        "-i",
+        "../stegano-core/tests/images/NoSecrets.jpg",
+        "-o",
+        env!("CARGO_TARGET_TMPDIR"),
+        "--password",
+        "secret",
+    ]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Lsb cannot be used with Jpeg"), "{stderr}");
+}
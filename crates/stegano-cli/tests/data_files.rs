@@ -0,0 +1,29 @@
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn should_report_a_missing_data_file_without_panicking() {
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("data-files-out.png");
+    let output = Command::new(env!("CARGO_BIN_EXE_stegano"))
+        .args([
+            "hide",
+            "-i",
+            "../stegano-core/tests/images/plain/carrier-image.png",
+            "-o",
+            out.to_str().unwrap(),
+            "-d",
+            "no-such-file.txt",
+            "--password",
+            "secret",
+        ])
+        .output()
+        .expect("Failed to run stegano");
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Cannot access `no-such-file.txt`"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
@@ -0,0 +1,39 @@
+use std::process::{Command, Output};
+
+fn stegano(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_stegano"))
+        .args(args)
+        .output()
+        .expect("Failed to run stegano")
+}
+
+#[test]
+fn should_draw_the_lsb_plane_of_an_image() {
+    let preview = stegano(&[
+        "lsb-preview",
+        "-i",
+        "../stegano-core/tests/images/plain/carrier-image.png",
+        "--width",
+        "16",
+    ]);
+    assert!(
+        preview.status.success(),
+        "{}",
+        String::from_utf8_lossy(&preview.stderr)
+    );
+
+    let text = String::from_utf8(preview.stdout).unwrap();
+    let lines: Vec<_> = text.lines().collect();
+    assert!(!lines.is_empty());
+    assert!(lines.iter().all(|line| line.chars().count() == 16));
+}
+
+#[test]
+fn should_refuse_to_preview_audio() {
+    let preview = stegano(&[
+        "lsb-preview",
+        "-i",
+        "../stegano-core/tests/audio/plain/carrier-audio.wav",
+    ]);
+    assert!(!preview.status.success());
+}
@@ -0,0 +1,81 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn stegano(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_stegano"))
+        .args(args)
+        .output()
+        .expect("Failed to run stegano")
+}
+
+fn json_of(output: &Output) -> serde_json::Value {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).expect("stdout is no JSON")
+}
+
+#[test]
+fn should_list_the_hidden_files_as_json() {
+    let secret = Path::new(env!("CARGO_TARGET_TMPDIR")).join("output-format-list.png");
+    let secret = secret.to_str().unwrap();
+    let hidden = stegano(&[
+        "hide",
+        "-i",
+        "../stegano-core/tests/images/plain/carrier-image.png",
+        "-o",
+        secret,
+        "-d",
+        "Cargo.toml",
+        "-d",
+        "../../LICENSE",
+        "--password",
+        "secret",
+    ]);
+    assert!(
+        hidden.status.success(),
+        "{}",
+        String::from_utf8_lossy(&hidden.stderr)
+    );
+
+    let listing = json_of(&stegano(&[
+        "list", "--format", "json", "-i", secret, "-p", "secret",
+    ]));
+
+    let files = listing["files"].as_array().expect("files is no array");
+    let names: Vec<_> = files.iter().map(|f| f["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["Cargo.toml", "LICENSE"]);
+    let license_size = std::fs::metadata("../../LICENSE").unwrap().len();
+    assert_eq!(files[1]["size"].as_u64(), Some(license_size));
+}
+
+#[test]
+fn should_print_the_capacity_as_json() {
+    let capacity = json_of(&stegano(&[
+        "capacity",
+        "--format",
+        "json",
+        "-i",
+        "../stegano-core/tests/images/plain/carrier-image.png",
+    ]));
+
+    assert!(capacity["capacity"].as_u64().is_some_and(|c| c > 0));
+}
+
+#[test]
+fn should_print_the_inspection_as_json() {
+    let inspection = json_of(&stegano(&[
+        "inspect",
+        "--format",
+        "json",
+        "-i",
+        "../stegano-core/tests/images/with_attachment/Blah.txt__and__Blah-2.txt.png",
+    ]));
+
+    assert_eq!(inspection["format"], "Png");
+    assert_eq!(inspection["algorithm"], "Lsb");
+    assert_eq!(inspection["encrypted"], false);
+    assert_eq!(inspection["file_count"].as_u64(), Some(2));
+}
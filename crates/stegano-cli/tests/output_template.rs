@@ -0,0 +1,47 @@
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn should_name_the_batch_outputs_by_the_output_template() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("output-template");
+    let carriers = dir.join("carriers");
+    let out = dir.join("out");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&carriers).unwrap();
+    for name in ["first.png", "second.png"] {
+        std::fs::copy(
+            "../stegano-core/tests/images/plain/carrier-image.png",
+            carriers.join(name),
+        )
+        .unwrap();
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stegano"))
+        .args([
+            "hide",
+            "-i",
+            carriers.to_str().unwrap(),
+            "-o",
+            out.to_str().unwrap(),
+            "-m",
+            "batch secret",
+            "--output-template",
+            "{stem}-stego-{index}.{ext}",
+            "--password",
+            "secret",
+        ])
+        .output()
+        .expect("Failed to run stegano");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let mut names: Vec<_> = std::fs::read_dir(&out)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["first-stego-0.png", "second-stego-1.png"]);
+}
@@ -0,0 +1,99 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+fn stegano(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_stegano"))
+        .args(args)
+        .output()
+        .expect("Failed to run stegano")
+}
+
+fn assert_success(output: &Output) {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn should_hide_and_unveil_with_the_password_from_a_file_or_stdin() {
+    let tmp = Path::new(env!("CARGO_TARGET_TMPDIR")).join("password-source");
+    std::fs::create_dir_all(&tmp).unwrap();
+    let password_file = tmp.join("password.txt");
+    std::fs::write(&password_file, "SuperSecret42\n").unwrap();
+    let secret = tmp.join("secret.png");
+    let secret = secret.to_str().unwrap();
+
+    assert_success(&stegano(&[
+        "hide",
+        "-i",
+        "../stegano-core/tests/images/plain/carrier-image.png",
+        "-o",
+        secret,
+        "-m",
+        "Hello from a password file",
+        "--password-file",
+        password_file.to_str().unwrap(),
+    ]));
+
+    let out_dir = tmp.join("out");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    assert_success(&stegano(&[
+        "unveil",
+        "-i",
+        secret,
+        "-o",
+        out_dir.to_str().unwrap(),
+        "--force",
+        "--password-file",
+        password_file.to_str().unwrap(),
+    ]));
+    assert_eq!(
+        std::fs::read_to_string(out_dir.join("secret-message.txt")).unwrap(),
+        "Hello from a password file"
+    );
+
+    let mut unveil = Command::new(env!("CARGO_BIN_EXE_stegano"))
+        .args([
+            "unveil",
+            "-i",
+            secret,
+            "-o",
+            out_dir.to_str().unwrap(),
+            "--force",
+            "--password-stdin",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run stegano");
+    unveil
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"SuperSecret42\n")
+        .unwrap();
+    let child = unveil.wait_with_output().unwrap();
+    assert_success(&child);
+}
+
+#[test]
+fn should_refuse_a_password_together_with_a_password_file() {
+    let output = stegano(&[
+        "unveil",
+        "-i",
+        "in.png",
+        "-o",
+        "out",
+        "-p",
+        "secret",
+        "--password-file",
+        "password.txt",
+    ]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+}
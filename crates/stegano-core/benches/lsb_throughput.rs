@@ -0,0 +1,64 @@
+//! Throughput of hiding and unveiling on a generated carrier, without any filesystem IO.
+//!
+//! Run with `cargo bench -p stegano-core --features test-utils`, add the `bench-timing` feature
+//! and `RUST_LOG=debug` to see the time of the codec loops on their own.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use stegano_core::api::unveil::unveil_to_memory;
+use stegano_core::testing::{generate_carrier, noise};
+use stegano_core::{CodecOptions, LsbStegoCodec, SteganoEncoder, StegoCodec};
+
+const CARRIER_SIZE: u32 = 1024;
+const PAYLOAD_LEN: usize = 256 * 1024;
+
+fn lsb_codec(c: &mut Criterion) {
+    let opts = CodecOptions::default();
+    let carrier = generate_carrier(CARRIER_SIZE, CARRIER_SIZE, 42);
+    let payload: Vec<u8> = noise(7).take(PAYLOAD_LEN).collect();
+
+    let mut group = c.benchmark_group("lsb");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(payload.len() as u64));
+    group.bench_function("embed", |b| {
+        b.iter_batched(
+            || carrier.clone(),
+            |mut media| LsbStegoCodec.embed(&mut media, &payload, &opts).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    let mut stego = carrier.clone();
+    LsbStegoCodec.embed(&mut stego, &payload, &opts).unwrap();
+    // the extraction reads the whole carrier
+    let capacity = stego.capacity_bytes(&opts);
+    group.throughput(Throughput::Bytes(capacity as u64));
+    group.bench_function("extract", |b| {
+        b.iter(|| LsbStegoCodec.extract(&stego, &opts).unwrap())
+    });
+    group.finish();
+}
+
+fn message_roundtrip(c: &mut Criterion) {
+    let opts = CodecOptions::default();
+    let payload: Vec<u8> = noise(7).take(PAYLOAD_LEN).collect();
+
+    let mut encoder = SteganoEncoder::with_options(opts.clone());
+    encoder
+        .with_carrier(generate_carrier(CARRIER_SIZE, CARRIER_SIZE, 42))
+        .add_reader("payload.bin", payload.as_slice())
+        .unwrap();
+
+    let mut group = c.benchmark_group("message");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(payload.len() as u64));
+    group.bench_function("hide_to_vec", |b| b.iter(|| encoder.hide_to_vec().unwrap()));
+
+    let png = encoder.hide_to_vec().unwrap();
+    group.bench_function("unveil_to_memory", |b| {
+        b.iter(|| unveil_to_memory(&png, None, &opts).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, lsb_codec, message_roundtrip);
+criterion_main!(benches);
@@ -1,8 +1,376 @@
 use std::path::{Path, PathBuf};
 
-use crate::{CodecOptions, SteganoEncoder, SteganoError};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::prelude::*;
 
-use super::Password;
+use crate::media::Persist;
+use crate::{CodecOptions, Media, Message, SteganoEncoder, SteganoError};
+
+use super::{Password, SplitPart, SPLIT_HEADER_LEN, SPLIT_PART_FILE};
+
+/// How the outputs of `hide_batch` and `hide_batch_as` are written
+#[derive(Debug, Default, Clone)]
+pub struct BatchOptions {
+    strip_metadata: bool,
+}
+
+impl BatchOptions {
+    /// Leaves out all metadata of the carriers, like text and EXIF chunks
+    pub fn strip_metadata(mut self, strip: bool) -> Self {
+        self.strip_metadata = strip;
+        self
+    }
+}
+
+/// Hides the same message into each of the carriers in parallel, every output is written to
+/// `out_dir` under the file name of its carrier.
+/// A failing carrier does not abort the batch, its error is returned at its position instead.
+pub fn hide_batch(
+    carriers: &[PathBuf],
+    message: &Message,
+    out_dir: &Path,
+    password: Option<&str>,
+    opts: &CodecOptions,
+    batch: &BatchOptions,
+) -> Result<Vec<Result<PathBuf, SteganoError>>, SteganoError> {
+    let outputs = carriers
+        .iter()
+        .map(|carrier| carrier.file_name().map(|name| out_dir.join(name)))
+        .collect();
+
+    hide_batch_into(carriers, outputs, message, out_dir, password, opts, batch)
+}
+
+/// Like `hide_batch`, but the outputs are named by `template`, see [`OutputTemplate`].
+/// Errors with `SteganoError::DuplicateFileName` before anything is written if two carriers
+/// end up with the same name.
+pub fn hide_batch_as(
+    carriers: &[PathBuf],
+    message: &Message,
+    out_dir: &Path,
+    template: &OutputTemplate,
+    password: Option<&str>,
+    opts: &CodecOptions,
+    batch: &BatchOptions,
+) -> Result<Vec<Result<PathBuf, SteganoError>>, SteganoError> {
+    let outputs: Vec<_> = carriers
+        .iter()
+        .enumerate()
+        .map(|(index, carrier)| Some(out_dir.join(template.resolve(carrier, index))))
+        .collect();
+    let mut seen = std::collections::HashSet::new();
+    if let Some(output) = outputs
+        .iter()
+        .flatten()
+        .find(|output| !seen.insert(*output))
+    {
+        return Err(SteganoError::DuplicateFileName {
+            name: output.display().to_string(),
+        });
+    }
+
+    hide_batch_into(carriers, outputs, message, out_dir, password, opts, batch)
+}
+
+/// The name of an output of `hide_batch_as`, the placeholders are resolved per carrier:
+/// - `{stem}` the file name of the carrier without its extension
+/// - `{ext}` the extension of the carrier without the dot
+/// - `{index}` the position of the carrier in the batch, counted from 0
+///
+/// E.g. `{stem}-stego.{ext}` turns `cat.png` into `cat-stego.png`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputTemplate(String);
+
+impl OutputTemplate {
+    pub fn new<S: Into<String>>(template: S) -> Self {
+        Self(template.into())
+    }
+
+    /// the file name of the output for the `index`th carrier
+    pub fn resolve(&self, carrier: &Path, index: usize) -> String {
+        let part = |part: Option<&std::ffi::OsStr>| {
+            part.map(|part| part.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+
+        self.0
+            .replace("{stem}", &part(carrier.file_stem()))
+            .replace("{ext}", &part(carrier.extension()))
+            .replace("{index}", &index.to_string())
+    }
+}
+
+/// hides the message into each carrier and writes it to its output, `None` if it has no name
+fn hide_batch_into(
+    carriers: &[PathBuf],
+    outputs: Vec<Option<PathBuf>>,
+    message: &Message,
+    out_dir: &Path,
+    password: Option<&str>,
+    opts: &CodecOptions,
+    batch: &BatchOptions,
+) -> Result<Vec<Result<PathBuf, SteganoError>>, SteganoError> {
+    std::fs::create_dir_all(out_dir).map_err(|source| SteganoError::WriteError { source })?;
+
+    let mut builder = Message::builder();
+    builder.with_message(message.clone())?;
+
+    Ok(carriers
+        .par_iter()
+        .zip(outputs)
+        .map(|(carrier, output)| {
+            let Some(output) = output else {
+                return Err(SteganoError::TargetNotSet);
+            };
+
+            let mut s = SteganoEncoder::with_options(opts.clone());
+            s.use_media(carrier)?
+                .save_as(&output)
+                .strip_metadata(batch.strip_metadata);
+            s.message = builder.clone();
+            if let Some(password) = password {
+                s.with_encryption(password);
+            }
+            s.hide_and_save()?;
+
+            Ok(output)
+        })
+        .collect())
+}
+
+/// Splits `data` across as many of the `carriers` as needed, each carrier is filled before the
+/// next one is used. Every part is hidden with a small header, so that `unveil::unveil_join` can
+/// put the parts back together in any order. The outputs are written to `out_dir` under the file
+/// names of their carriers and returned in the order of the parts.
+/// WAV carriers work alike, the bitstream then continues from one clip to the next, like in a
+/// playlist. Their sample specs have to match, or it errors with `SteganoError::MismatchedAudioSpecs`.
+/// Errors with `SteganoError::SplitCarriersTooSmall` before anything is written if the carriers
+/// are too small together.
+pub fn hide_split(
+    data: &[u8],
+    carriers: &[PathBuf],
+    out_dir: &Path,
+    password: Option<&str>,
+    opts: &CodecOptions,
+) -> Result<Vec<PathBuf>, SteganoError> {
+    let mut chunks = Vec::new();
+    let mut offset = 0;
+    let mut audio_spec = None;
+    for carrier in carriers {
+        if offset == data.len() && !chunks.is_empty() {
+            break;
+        }
+        let media = Media::from_file(carrier)?;
+        if let Media::Audio((spec, _)) | Media::FloatAudio((spec, _)) = &media {
+            match audio_spec {
+                Some(first) if first != *spec => {
+                    return Err(SteganoError::MismatchedAudioSpecs {
+                        first,
+                        other: *spec,
+                    })
+                }
+                _ => audio_spec = Some(*spec),
+            }
+        }
+        let capacity = media.capacity_bytes(opts);
+        let len = largest_split_chunk(&data[offset..], capacity, password.is_some())?;
+        if len > 0 || data.is_empty() {
+            chunks.push((carrier, offset..offset + len));
+            offset += len;
+        }
+    }
+    if offset < data.len() || chunks.len() > u16::MAX as usize {
+        return Err(SteganoError::SplitCarriersTooSmall {
+            needed: data.len(),
+            available: offset,
+        });
+    }
+
+    std::fs::create_dir_all(out_dir).map_err(|source| SteganoError::WriteError { source })?;
+    let total = chunks.len() as u16;
+    let mut outputs = Vec::with_capacity(chunks.len());
+    for (index, (carrier, range)) in chunks.into_iter().enumerate() {
+        let Some(file_name) = carrier.file_name() else {
+            return Err(SteganoError::TargetNotSet);
+        };
+        let output = out_dir.join(file_name);
+        let part = SplitPart {
+            index: index as u16,
+            total,
+            original_len: data.len() as u64,
+            data: data[range].to_vec(),
+        };
+
+        let mut s = SteganoEncoder::with_options(opts.clone());
+        s.use_media(carrier)?.save_as(&output);
+        if let Some(password) = password {
+            s.with_encryption(password);
+        }
+        s.add_reader(SPLIT_PART_FILE, part.to_bytes().as_slice())?;
+        s.hide_and_save()?;
+
+        outputs.push(output);
+    }
+
+    Ok(outputs)
+}
+
+/// the length of the longest start of `data` whose part fits into `capacity`
+fn largest_split_chunk(
+    data: &[u8],
+    capacity: usize,
+    encrypted: bool,
+) -> Result<usize, SteganoError> {
+    let fits = |len: usize| -> Result<bool, SteganoError> {
+        let mut part = vec![0; SPLIT_HEADER_LEN];
+        part.extend_from_slice(&data[..len]);
+        let mut builder = Message::builder();
+        builder
+            .encrypted(encrypted)
+            .add_file_data(SPLIT_PART_FILE, part)?;
+
+        Ok(builder.would_fit(capacity))
+    };
+    if !fits(0)? {
+        return Ok(0);
+    }
+
+    // the compressed size grows with the length, so the longest fitting one can be searched
+    let (mut fitting, mut too_long) = (0, data.len() + 1);
+    while too_long - fitting > 1 {
+        let len = fitting + (too_long - fitting) / 2;
+        if fits(len)? {
+            fitting = len;
+        } else {
+            too_long = len;
+        }
+    }
+
+    Ok(fitting)
+}
+
+/// Adds `new_files` to the message that is already hidden in `image` and hides the merged
+/// message in place, so that a hidden archive can be built up file by file.
+/// Errors with `SteganoError::CarrierTooSmall` if the merged message does not fit anymore,
+/// the image stays untouched then.
+pub fn append(
+    image: &Path,
+    new_files: &[PathBuf],
+    password: Option<&str>,
+    opts: &CodecOptions,
+) -> Result<(), SteganoError> {
+    let media = Media::from_file(image)?;
+    let mut message = super::unveil::unveil_message(&media, password, opts)?;
+
+    // a text only message cannot be merged with files, so the text becomes a file like on unveil
+    if let Some(text) = message.text.take() {
+        message.add_file_data("secret-message.txt", text.into_bytes())?;
+    }
+    for file in new_files {
+        message.add_file(file)?;
+    }
+
+    let mut s = SteganoEncoder::with_options(opts.clone());
+    s.use_media(image)?.save_as(image);
+    if let Some(password) = password {
+        s.with_encryption(password);
+    }
+    s.message.with_message(message)?;
+    media.ensure_capacity(s.required_capacity()?, opts)?;
+    s.hide_and_save()?;
+
+    Ok(())
+}
+
+/// Changes the password of an encrypted stego image, the result is written to `output`.
+/// The message is decrypted in memory only and hidden again under `new_password`, it never
+/// reaches the disk and is zeroized afterwards. Before, every bit that could carry data is
+/// overwritten with noise, so that no old ciphertext stays behind where the new one does not reach.
+/// Errors like unveiling if `old_password` does not decrypt the message.
+pub fn rekey(
+    image: &Path,
+    old_password: &str,
+    new_password: &str,
+    output: &Path,
+    opts: &CodecOptions,
+) -> Result<(), SteganoError> {
+    let mut media = Media::from_file(image)?;
+    let mut message = super::unveil::unveil_message(&media, Some(old_password), opts)?;
+    // a text only message is hidden as file, like on unveil
+    if let Some(text) = message.text.take() {
+        message.add_file_data("secret-message.txt", text.into_bytes())?;
+    }
+    media.sanitize(opts, &mut StdRng::from_entropy())?;
+
+    let mut s = SteganoEncoder::with_options(opts.clone());
+    // the metadata chunks of the image are kept
+    s.use_media(image)?
+        .save_as(output)
+        .with_encryption(new_password);
+    s.carrier = Some(media);
+    s.message.with_message(message)?;
+    let hidden = s.hide_and_save().map(|_| ());
+    std::mem::take(&mut s.message).build().zeroize();
+
+    hidden
+}
+
+/// Experimental: hides `data` verbatim like `SteganoEncoder::hide_raw` and then, for every color
+/// channel whose bit does not match, borrows the channel of the `donor` image at the same
+/// coordinate if that one carries the bit already. Channels that match keep the value of the
+/// cover, only where neither does the bit is flipped. `unveil_raw` reads the data back.
+///
+/// The borrowed values are taken as they are, so a donor that differs a lot from the cover
+/// makes the stego image look worse. It is meant for a donor that is a near copy of the cover,
+/// e.g. another shot of the same scene. Returns the number of channels that had to be flipped.
+/// Errors with `SteganoError::IncomparableMedia` if the donor is no image of the same kind and size.
+pub fn hide_cover_swap(
+    cover: &Path,
+    donor: &Path,
+    data: &[u8],
+    out: &Path,
+    opts: &CodecOptions,
+) -> Result<usize, SteganoError> {
+    let cover = Media::from_file(cover)?;
+    let donor = Media::from_file(donor)?;
+    let mut stego = cover.clone();
+    stego.hide_data(data.to_vec(), opts)?;
+
+    let flipped = match (&mut stego, &cover, &donor) {
+        (Media::Image(s), Media::Image(c), Media::Image(d)) if c.dimensions() == d.dimensions() => {
+            swap_from_donor(s, c, d, opts)
+        }
+        (Media::GrayImage(s), Media::GrayImage(c), Media::GrayImage(d))
+            if c.dimensions() == d.dimensions() =>
+        {
+            swap_from_donor(s, c, d, opts)
+        }
+        _ => return Err(SteganoError::IncomparableMedia),
+    };
+    stego.save_as(out)?;
+
+    Ok(flipped)
+}
+
+/// replaces the flipped channels of `stego` by the donor ones that carry the same bit,
+/// returns the number of channels that stay flipped
+fn swap_from_donor(stego: &mut [u8], cover: &[u8], donor: &[u8], opts: &CodecOptions) -> usize {
+    let bit = !opts.cover_bits();
+    let mut flipped = 0;
+    for (s, (c, d)) in stego.iter_mut().zip(cover.iter().zip(donor)) {
+        if *s == *c {
+            continue;
+        }
+        if *s & bit == *d & bit {
+            *s = *d;
+        } else {
+            flipped += 1;
+        }
+    }
+
+    flipped
+}
 
 /// Prepares the hide API for further configuration
 pub fn prepare() -> HideApi {
@@ -17,6 +385,8 @@ pub struct HideApi {
     output: Option<PathBuf>,
     password: Password,
     options: CodecOptions,
+    strip_metadata: bool,
+    expand_palette: bool,
 }
 
 impl HideApi {
@@ -84,6 +454,18 @@ impl HideApi {
         self
     }
 
+    /// Leaves out all metadata of the carrier image, like text and EXIF chunks
+    pub fn strip_metadata(mut self, strip: bool) -> Self {
+        self.strip_metadata = strip;
+        self
+    }
+
+    /// Accepts a palette PNG as carrier image, the output is then written with full colors
+    pub fn expand_palette(mut self, expand: bool) -> Self {
+        self.expand_palette = expand;
+        self
+    }
+
     /// Execute the hiding process and blocks until it is finished
     pub fn execute(self) -> Result<(), SteganoError> {
         self.validate()?;
@@ -95,7 +477,10 @@ impl HideApi {
         };
 
         let mut s = SteganoEncoder::with_options(self.options);
-        s.use_media(&image)?.save_as(&output);
+        s.use_media(&image)?
+            .save_as(&output)
+            .strip_metadata(self.strip_metadata)
+            .expand_palette(self.expand_palette);
 
         if let Some(password) = self.password.as_ref() {
             s.with_encryption(password);
@@ -132,6 +517,60 @@ impl HideApi {
 mod tests {
     use tempfile::tempdir;
 
+    #[test]
+    fn should_hide_with_cover_swap_and_flip_fewer_channels_than_plain_lsb() {
+        use crate::{CodecOptions, Media};
+        use std::io::Read;
+
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let cover = std::path::Path::new("tests/images/plain/carrier-image.png");
+        let cover_image = image::open(cover).unwrap().to_rgba8();
+        // a near copy of the cover, every second pixel carries the other bit
+        let mut donor_image = cover_image.clone();
+        donor_image
+            .enumerate_pixels_mut()
+            .filter(|(x, y, _)| (x + y) % 2 == 0)
+            .for_each(|(_, _, p)| p.0[..3].iter_mut().for_each(|c| *c ^= 1));
+        let donor = temp_dir.path().join("donor.png");
+        donor_image.save(&donor).unwrap();
+
+        let opts = CodecOptions::default();
+        let data = b"swapped, not flipped".repeat(20);
+        let out = temp_dir.path().join("stego.png");
+        let flipped = super::hide_cover_swap(cover, &donor, &data, &out, &opts).unwrap();
+
+        let mut unveiled = vec![0; data.len()];
+        Media::from_file(&out)
+            .unwrap()
+            .decoder(&opts)
+            .read_exact(&mut unveiled)
+            .unwrap();
+        assert_eq!(unveiled, data);
+
+        let mut plain = Media::Image(cover_image.clone());
+        plain.hide_data(data.clone(), &opts).unwrap();
+        let Media::Image(plain) = plain else {
+            unreachable!()
+        };
+        let plain_flipped = plain
+            .iter()
+            .zip(cover_image.iter())
+            .filter(|(s, c)| s != c)
+            .count();
+        let stego = image::open(&out).unwrap().to_rgba8();
+        let neither_cover_nor_donor = stego
+            .iter()
+            .zip(cover_image.iter().zip(donor_image.iter()))
+            .filter(|(s, (c, d))| s != c && s != d)
+            .count();
+        assert_eq!(neither_cover_nor_donor, flipped);
+        assert!(flipped > 0);
+        assert!(
+            flipped * 3 < plain_flipped * 2,
+            "{flipped} vs {plain_flipped}"
+        );
+    }
+
     #[test]
     fn illustrate_api_usage() {
         let temp_dir = tempdir().expect("Failed to create temporary directory");
@@ -191,4 +630,308 @@ mod tests {
         let api = api.use_files(None);
         assert!(api.files.as_ref().is_none());
     }
+
+    #[test]
+    fn should_hide_into_a_batch_of_carriers() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let out_dir = temp_dir.path().join("out");
+        let mut carriers: Vec<std::path::PathBuf> = (0..6)
+            .map(|i| {
+                let carrier = temp_dir.path().join(format!("carrier-{i}.png"));
+                image::RgbaImage::from_pixel(32, 32, image::Rgba([i * 40, 128, 64, 255]))
+                    .save(&carrier)
+                    .unwrap();
+                carrier
+            })
+            .collect();
+        carriers.push(temp_dir.path().join("missing.png"));
+
+        let mut message = crate::Message::empty();
+        message
+            .add_file_data("secret.txt", b"batch secret".to_vec())
+            .unwrap();
+
+        let results = super::hide_batch(
+            &carriers,
+            &message,
+            &out_dir,
+            None,
+            &crate::CodecOptions::default(),
+            &super::BatchOptions::default(),
+        )
+        .expect("Failed to run the batch");
+
+        assert_eq!(results.len(), 7);
+        assert!(results[6].is_err());
+        for (i, result) in results[..6].iter().enumerate() {
+            let output = result.as_ref().expect("Failed to hide into carrier");
+            assert_eq!(output, &out_dir.join(format!("carrier-{i}.png")));
+
+            let unveil_dir = temp_dir.path().join(format!("unveiled-{i}"));
+            std::fs::create_dir(&unveil_dir).unwrap();
+            crate::api::unveil::prepare()
+                .from_secret_file(output)
+                .into_output_folder(&unveil_dir)
+                .execute()
+                .expect("Failed to unveil from batch output");
+            assert_eq!(
+                std::fs::read(unveil_dir.join("secret.txt")).unwrap(),
+                b"batch secret"
+            );
+        }
+    }
+
+    #[test]
+    fn should_name_the_batch_outputs_by_the_template() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let out_dir = temp_dir.path().join("out");
+        let carriers: Vec<std::path::PathBuf> = ["cat.png", "dog.png", "bird.png"]
+            .iter()
+            .map(|name| {
+                let carrier = temp_dir.path().join(name);
+                image::RgbaImage::from_pixel(32, 32, image::Rgba([200, 128, 64, 255]))
+                    .save(&carrier)
+                    .unwrap();
+                carrier
+            })
+            .collect();
+        let mut message = crate::Message::empty();
+        message
+            .add_file_data("secret.txt", b"batch secret".to_vec())
+            .unwrap();
+        let opts = crate::CodecOptions::default();
+        let batch = super::BatchOptions::default();
+
+        let template = super::OutputTemplate::new("{index}-{stem}-stego.{ext}");
+        let results = super::hide_batch_as(
+            &carriers, &message, &out_dir, &template, None, &opts, &batch,
+        )
+        .expect("Failed to run the batch");
+        let outputs: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+        assert_eq!(
+            outputs,
+            ["0-cat-stego.png", "1-dog-stego.png", "2-bird-stego.png"].map(|n| out_dir.join(n))
+        );
+        assert!(outputs.iter().all(|output| output.exists()));
+
+        let same_name = super::OutputTemplate::new("stego.{ext}");
+        assert!(matches!(
+            super::hide_batch_as(&carriers, &message, &out_dir, &same_name, None, &opts, &batch),
+            Err(crate::SteganoError::DuplicateFileName { .. })
+        ));
+    }
+
+    #[test]
+    fn should_append_a_file_to_an_image_with_a_secret() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let image = temp_dir.path().join("image-with-secret.png");
+        crate::api::hide::prepare()
+            .with_file("tests/images/secrets/Blah.txt")
+            .with_image("tests/images/plain/carrier-image.png")
+            .using_password("SuperSecret42")
+            .with_output(&image)
+            .execute()
+            .expect("Failed to hide the first file");
+
+        super::append(
+            &image,
+            &["tests/images/secrets/random_1666_byte.bin".into()],
+            Some("SuperSecret42"),
+            &crate::CodecOptions::default(),
+        )
+        .expect("Failed to append the second file");
+
+        let out_dir = temp_dir.path().join("out");
+        std::fs::create_dir(&out_dir).unwrap();
+        crate::api::unveil::prepare()
+            .from_secret_file(&image)
+            .into_output_folder(&out_dir)
+            .using_password("SuperSecret42")
+            .execute()
+            .expect("Failed to unveil both files");
+
+        for name in ["Blah.txt", "random_1666_byte.bin"] {
+            assert_eq!(
+                std::fs::read(out_dir.join(name)).unwrap(),
+                std::fs::read(format!("tests/images/secrets/{name}")).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn should_rekey_an_image_to_a_new_password() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let image = temp_dir.path().join("image-with-secret.png");
+        let rekeyed = temp_dir.path().join("rekeyed.png");
+        crate::api::hide::prepare()
+            .with_file("tests/images/secrets/Blah.txt")
+            .with_image("tests/images/plain/carrier-image.png")
+            .using_password("OldSecret42")
+            .with_output(&image)
+            .execute()
+            .expect("Failed to hide the file");
+
+        super::rekey(
+            &image,
+            "OldSecret42",
+            "NewSecret42",
+            &rekeyed,
+            &crate::CodecOptions::default(),
+        )
+        .expect("Failed to rekey");
+
+        let out_dir = temp_dir.path().join("out");
+        std::fs::create_dir(&out_dir).unwrap();
+        assert!(crate::api::unveil::prepare()
+            .from_secret_file(&rekeyed)
+            .into_output_folder(&out_dir)
+            .using_password("OldSecret42")
+            .execute()
+            .is_err());
+        assert_eq!(std::fs::read_dir(&out_dir).unwrap().count(), 0);
+        crate::api::unveil::prepare()
+            .from_secret_file(&rekeyed)
+            .into_output_folder(&out_dir)
+            .using_password("NewSecret42")
+            .execute()
+            .expect("Failed to unveil with the new password");
+        assert_eq!(
+            std::fs::read(out_dir.join("Blah.txt")).unwrap(),
+            std::fs::read("tests/images/secrets/Blah.txt").unwrap()
+        );
+
+        assert!(super::rekey(
+            &image,
+            "WrongSecret",
+            "NewSecret42",
+            &temp_dir.path().join("never.png"),
+            &crate::CodecOptions::default(),
+        )
+        .is_err());
+        assert!(!temp_dir.path().join("never.png").exists());
+    }
+
+    #[test]
+    fn should_refuse_to_append_beyond_the_capacity() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let carrier = temp_dir.path().join("carrier.png");
+        image::RgbaImage::from_pixel(32, 32, image::Rgba([128, 128, 128, 255]))
+            .save(&carrier)
+            .unwrap();
+        let image = temp_dir.path().join("image-with-secret.png");
+        crate::api::hide::prepare()
+            .with_message("hi")
+            .with_image(&carrier)
+            .with_output(&image)
+            .execute()
+            .expect("Failed to hide the message");
+        let before = std::fs::read(&image).unwrap();
+
+        let result = super::append(
+            &image,
+            &["tests/images/secrets/random_1666_byte.bin".into()],
+            None,
+            &crate::CodecOptions::default(),
+        );
+
+        assert!(matches!(
+            result,
+            Err(crate::SteganoError::CarrierTooSmall { .. })
+        ));
+        assert_eq!(std::fs::read(&image).unwrap(), before);
+    }
+
+    #[test]
+    fn should_split_across_carriers_and_join_again() {
+        use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let out_dir = temp_dir.path().join("out");
+        let carriers: Vec<std::path::PathBuf> = (0..4)
+            .map(|i| {
+                let carrier = temp_dir.path().join(format!("carrier-{i}.png"));
+                std::fs::write(
+                    &carrier,
+                    crate::testing::generate_carrier(32, 32, i)
+                        .to_vec()
+                        .unwrap(),
+                )
+                .unwrap();
+                carrier
+            })
+            .collect();
+        // incompressible, so that it needs three of the carriers
+        let mut secret = vec![0; 600];
+        StdRng::seed_from_u64(5).fill_bytes(&mut secret);
+        let opts = crate::CodecOptions::default();
+
+        let parts = super::hide_split(&secret, &carriers, &out_dir, None, &opts).unwrap();
+        assert_eq!(parts.len(), 3);
+        assert!(!out_dir.join("carrier-3.png").exists());
+
+        // the order of the parts does not matter
+        let joined = temp_dir.path().join("joined.bin");
+        let shuffled = [parts[2].clone(), parts[0].clone(), parts[1].clone()];
+        crate::api::unveil::unveil_join(&shuffled, &joined, None, &opts).unwrap();
+        assert_eq!(std::fs::read(&joined).unwrap(), secret);
+
+        assert!(matches!(
+            crate::api::unveil::unveil_join(&parts[..2], &joined, None, &opts),
+            Err(crate::SteganoError::MissingSplitParts { missing }) if missing == [2]
+        ));
+        assert!(matches!(
+            super::hide_split(
+                &[secret.clone(), secret].concat(),
+                &carriers,
+                &out_dir,
+                None,
+                &opts
+            ),
+            Err(crate::SteganoError::SplitCarriersTooSmall { needed: 1200, .. })
+        ));
+    }
+
+    #[test]
+    fn should_split_across_wav_clips_and_join_again() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let out_dir = temp_dir.path().join("out");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let clip = |name: &str, spec: hound::WavSpec| {
+            let clip = temp_dir.path().join(name);
+            let samples = (0..3000).map(|i| (i * 7 % 2000) - 1000).collect();
+            std::fs::write(
+                &clip,
+                crate::Media::Audio((spec, samples)).to_vec().unwrap(),
+            )
+            .unwrap();
+            clip
+        };
+        let clips = vec![clip("clip-0.wav", spec), clip("clip-1.wav", spec)];
+        let secret: Vec<u8> = (0..400u32).map(|i| (i * 31 % 251) as u8).collect();
+        let opts = crate::CodecOptions::default();
+
+        let parts = super::hide_split(&secret, &clips, &out_dir, None, &opts).unwrap();
+        assert_eq!(parts.len(), 2);
+
+        let joined = temp_dir.path().join("joined.bin");
+        crate::api::unveil::unveil_join(&parts, &joined, None, &opts).unwrap();
+        assert_eq!(std::fs::read(&joined).unwrap(), secret);
+
+        let stereo = clip(
+            "stereo.wav",
+            hound::WavSpec {
+                channels: 2,
+                ..spec
+            },
+        );
+        assert!(matches!(
+            super::hide_split(&secret, &[clips[0].clone(), stereo], &out_dir, None, &opts),
+            Err(crate::SteganoError::MismatchedAudioSpecs { .. })
+        ));
+    }
 }
@@ -1,4 +1,6 @@
 pub mod hide;
+pub mod probe;
+pub mod sanitize;
 pub mod unveil;
 pub mod unveil_raw;
 
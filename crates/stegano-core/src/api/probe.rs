@@ -0,0 +1,443 @@
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use image::{ImageBuffer, Pixel};
+use zip::ZipArchive;
+
+use crate::{
+    media::{
+        payload::{has_crypt_header, FabA, HasFeature, PayloadCodecFactory, PayloadCodecFeatures},
+        Media,
+    },
+    message::MESSAGE_HEADER_LEN,
+    Algorithm, CodecOptions, MediaFormat, PayloadVersion, SteganoError,
+};
+
+use super::{decode_with_password_order, Password};
+
+/// Reads only the header of the secret data and returns the payload version, the body is not decoded
+pub fn detect_version(media: &Path, opts: &CodecOptions) -> Result<PayloadVersion, SteganoError> {
+    let media = Media::from_file(media)?;
+    let mut decoder = media.decoder(opts);
+
+    read_version(&mut decoder)
+}
+
+/// The number of bytes that can be hidden in the media with the given options
+pub fn capacity(media: &Path, opts: &CodecOptions) -> Result<usize, SteganoError> {
+    Ok(Media::from_file(media)?.capacity_bytes(opts))
+}
+
+/// Estimates how detectable LSB embedding in the media is, by the chi-square attack over the
+/// channels the options would use. A clean carrier scores close to 0.0, a carrier whose LSBs
+/// are filled with (encrypted) data close to 1.0.
+pub fn chi_square_score(media: &Path, opts: &CodecOptions) -> Result<f64, SteganoError> {
+    Ok(Media::from_file(media)?.chi_square_score(opts))
+}
+
+/// Everything that can be told about a carrier without a password, see [`inspect`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CarrierInspection {
+    pub format: MediaFormat,
+    /// the algorithm that fits the format
+    pub algorithm: Algorithm,
+    /// `None` if no secret data was found
+    pub payload_version: Option<PayloadVersion>,
+    /// true if the secret data is encrypted
+    pub encrypted: bool,
+    /// the number of hidden files, `None` if they are encrypted or there is no secret data
+    pub file_count: Option<usize>,
+    /// the size of the (compressed or encrypted) payload, `None` if there is no secret data
+    pub total_payload_bytes: Option<usize>,
+    /// the number of bytes the carrier can hold with the given options
+    pub raw_capacity: usize,
+}
+
+/// Inspects a carrier in one go, only headers are read and nothing is decrypted or decompressed.
+/// Secret data that was hidden in the order derived from a password is not found.
+pub fn inspect(media: &Path, opts: &CodecOptions) -> Result<CarrierInspection, SteganoError> {
+    let format = MediaFormat::of_file(media).ok_or(SteganoError::UnsupportedMedia)?;
+    let media = Media::from_file(media)?;
+    let mut inspection = CarrierInspection {
+        format,
+        algorithm: Algorithm::for_format(format),
+        payload_version: None,
+        encrypted: false,
+        file_count: None,
+        total_payload_bytes: None,
+        raw_capacity: media.capacity_bytes(opts),
+    };
+    if inspection.raw_capacity < MESSAGE_HEADER_LEN {
+        return Ok(inspection);
+    }
+
+    let mut decoder = media.decoder(opts);
+    let Ok(version) = read_version(&mut decoder) else {
+        return Ok(inspection);
+    };
+    let features = PayloadCodecFeatures::from(version);
+    let Ok(payload) = FabA
+        .create_codec(features)
+        .and_then(|codec| codec.decode(&mut decoder))
+    else {
+        return Ok(inspection);
+    };
+
+    inspection.payload_version = Some(version);
+    inspection.total_payload_bytes = Some(payload.len());
+    inspection.encrypted =
+        features.has_feature(PayloadCodecFeatures::ChaCrypto) || has_crypt_header(&payload);
+    if !inspection.encrypted {
+        inspection.file_count = if features.has_feature(PayloadCodecFeatures::TextOnly) {
+            Some(0)
+        } else {
+            // the central directory lists the files, none of them is decompressed
+            ZipArchive::new(Cursor::new(&payload))
+                .ok()
+                .map(|zip| zip.len())
+        };
+    }
+
+    Ok(inspection)
+}
+
+/// The share of set least significant bits in the cells of a grid over an image, see [`lsb_preview`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LsbPreview {
+    pub columns: u32,
+    pub rows: u32,
+    /// row by row, `0.0` if no LSB of a cell is set and `1.0` if all are
+    pub densities: Vec<f64>,
+}
+
+impl LsbPreview {
+    /// The density of the cell in column `x` and row `y`
+    pub fn density(&self, x: u32, y: u32) -> f64 {
+        self.densities[(y * self.columns + x) as usize]
+    }
+
+    /// The difference between the densest and the sparsest cell. The LSBs of a clean carrier
+    /// are rather uniform, a payload that fills only a part of it stands out.
+    pub fn spread(&self) -> f64 {
+        let min = self.densities.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = self
+            .densities
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        (max - min).max(0.0)
+    }
+}
+
+/// Maps the LSBs of the color channels of an image onto a grid that is at most `columns` wide.
+/// A cell is twice as high as wide, like a character in a terminal. The data of sequential
+/// LSB embedding often shows as a block of its own, as it is hidden column by column.
+/// Errors with `SteganoError::UnsupportedMedia` for audio.
+pub fn lsb_preview(media: &Path, columns: u32) -> Result<LsbPreview, SteganoError> {
+    match Media::from_file(media)? {
+        Media::Image(i) => Ok(lsb_plane_grid(&i, columns)),
+        Media::GrayImage(i) => Ok(lsb_plane_grid(&i, columns)),
+        Media::Audio(_) | Media::FloatAudio(_) => Err(SteganoError::UnsupportedMedia),
+    }
+}
+
+fn lsb_plane_grid<P: Pixel<Subpixel = u8>>(
+    image: &ImageBuffer<P, Vec<u8>>,
+    columns: u32,
+) -> LsbPreview {
+    let (width, height) = image.dimensions();
+    let columns = columns.clamp(1, width.max(1));
+    let rows = (height as u64 * columns as u64)
+        .div_ceil(2 * width.max(1) as u64)
+        .clamp(1, height.max(1) as u64) as u32;
+    // alpha is left out, like it is by default when hiding
+    let channels = (P::CHANNEL_COUNT as usize).min(3);
+
+    let mut ones = vec![0u64; (columns * rows) as usize];
+    let mut total = vec![0u64; ones.len()];
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let cell = (y as u64 * rows as u64 / height as u64) * columns as u64
+            + x as u64 * columns as u64 / width as u64;
+        for channel in &pixel.channels()[..channels] {
+            ones[cell as usize] += (channel & 1) as u64;
+            total[cell as usize] += 1;
+        }
+    }
+
+    LsbPreview {
+        columns,
+        rows,
+        densities: ones
+            .iter()
+            .zip(&total)
+            .map(|(ones, total)| *ones as f64 / (*total).max(1) as f64)
+            .collect(),
+    }
+}
+
+fn read_version(decoder: &mut dyn Read) -> Result<PayloadVersion, SteganoError> {
+    let mut version = [0];
+    decoder.read_exact(&mut version)?;
+
+    PayloadVersion::try_from(version[0]).map_err(|_| SteganoError::NoSecretData)
+}
+
+/// Prepares the probe API, it inspects a media without the need of a password
+pub fn prepare() -> ProbeApi {
+    ProbeApi::default()
+}
+
+#[derive(Default, Debug)]
+pub struct ProbeApi {
+    secret_media: Option<PathBuf>,
+    options: CodecOptions,
+    password: Password,
+}
+
+/// What could be found out about the secret data inside a media
+#[derive(Debug, PartialEq, Eq)]
+pub struct ProbeReport {
+    /// the version of the payload format
+    pub version: PayloadVersion,
+    /// true if the secret data is encrypted, a password is then needed to unveil it
+    pub encrypted: bool,
+}
+
+impl ProbeApi {
+    /// Use the given codec options
+    pub fn with_options(mut self, options: CodecOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// This is the media that is going to be inspected
+    pub fn from_secret_file(mut self, secret_media: impl AsRef<Path>) -> Self {
+        self.secret_media = Some(secret_media.as_ref().to_path_buf());
+        self
+    }
+
+    /// Images that were hidden with a password have their data placed in an order derived
+    /// from the password, those can only be found when the password is given here.
+    /// The password is not used for decryption.
+    pub fn using_password<P: Into<Password>>(mut self, password: P) -> Self {
+        self.password = password.into();
+        self
+    }
+
+    /// Execute the probing, returns `SteganoError::NoSecretData` if the media contains no secret data
+    pub fn execute(self) -> Result<ProbeReport, SteganoError> {
+        let Some(secret_media) = self.secret_media else {
+            return Err(SteganoError::CarrierNotSet);
+        };
+
+        let media = Media::from_file(&secret_media)?;
+        media.ensure_capacity(MESSAGE_HEADER_LEN, &self.options)?;
+        decode_with_password_order(
+            &media,
+            &self.options,
+            self.password.as_ref().as_deref(),
+            |options| Ok(media.decoder(options)),
+            |decoder, _| {
+                let version = read_version(decoder)?;
+                let features = PayloadCodecFeatures::from(version);
+                let codec = FabA.create_codec(features)?;
+                let payload = codec.decode(decoder)?;
+
+                Ok(ProbeReport {
+                    version,
+                    encrypted: features.has_feature(PayloadCodecFeatures::ChaCrypto)
+                        || has_crypt_header(&payload),
+                })
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn should_inspect_an_encrypted_carrier_with_several_files() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let image_with_secret = temp_dir.path().join("image-with-secret.png");
+        crate::SteganoEncoder::new()
+            .use_media("tests/images/plain/carrier-image.png")
+            .and_then(|e| {
+                e.add_files(&[
+                    "tests/images/secrets/Blah.txt",
+                    "tests/images/secrets/Blah-2.txt",
+                ])
+            })
+            .expect("Failed to add the secrets")
+            .save_as(&image_with_secret)
+            .with_encryption_multi(&["alice".to_string(), "bob".to_string()])
+            .hide_and_save()
+            .expect("Failed to hide the secrets");
+
+        let opts = CodecOptions::default();
+        let inspection = inspect(&image_with_secret, &opts).expect("Failed to inspect image");
+        assert_eq!(inspection.format, MediaFormat::Png);
+        assert_eq!(inspection.algorithm, Algorithm::Lsb);
+        assert!(inspection.payload_version.is_some());
+        assert!(inspection.encrypted);
+        assert_eq!(inspection.file_count, None);
+        assert!(inspection.total_payload_bytes.unwrap() > 0);
+        assert_eq!(
+            inspection.raw_capacity,
+            capacity(&image_with_secret, &opts).unwrap()
+        );
+
+        let plain = inspect(
+            Path::new("tests/images/with_attachment/Blah.txt__and__Blah-2.txt.png"),
+            &opts,
+        )
+        .expect("Failed to inspect image");
+        assert!(!plain.encrypted);
+        assert_eq!(plain.file_count, Some(2));
+    }
+
+    #[test]
+    fn should_report_encryption_of_a_linear_image_without_password() {
+        // hidden before the order was derived from the password
+        let report = prepare()
+            .from_secret_file("tests/images/encrypted/hello_world.png")
+            .execute()
+            .expect("Failed to probe image");
+        assert!(report.encrypted);
+    }
+
+    #[test]
+    fn should_find_shuffled_data_only_with_password() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let image_with_secret = temp_dir.path().join("image-with-secret.png");
+        crate::api::hide::prepare()
+            .with_message("Hello, World!")
+            .with_image("tests/images/plain/carrier-image.png")
+            .using_password("SuperSecret42")
+            .with_output(&image_with_secret)
+            .execute()
+            .expect("Failed to hide message in image");
+
+        assert!(prepare()
+            .from_secret_file(&image_with_secret)
+            .execute()
+            .is_err());
+
+        let report = prepare()
+            .from_secret_file(&image_with_secret)
+            .using_password("SuperSecret42")
+            .execute()
+            .expect("Failed to probe image");
+        assert_eq!(
+            report,
+            ProbeReport {
+                version: PayloadVersion::V4Encrypted,
+                encrypted: true
+            }
+        );
+    }
+
+    #[test]
+    fn should_report_no_encryption() {
+        let report = prepare()
+            .from_secret_file("tests/images/with_attachment/Blah.txt.png")
+            .execute()
+            .expect("Failed to probe image");
+        assert_eq!(
+            report,
+            ProbeReport {
+                version: PayloadVersion::V2,
+                encrypted: false
+            }
+        );
+    }
+
+    #[test]
+    fn should_detect_the_version_of_a_v2_image() {
+        let version = detect_version(
+            "tests/images/with_attachment/Blah.txt.png".as_ref(),
+            &CodecOptions::default(),
+        )
+        .expect("Failed to detect version");
+        assert_eq!(version, PayloadVersion::V2);
+    }
+
+    #[test]
+    fn should_produce_and_detect_legacy_v2_images() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let image_with_secret = temp_dir.path().join("legacy.png");
+
+        crate::SteganoEncoder::new()
+            .with_version(PayloadVersion::V2)
+            .unwrap()
+            .use_media("tests/images/plain/carrier-image.png")
+            .unwrap()
+            .add_file("tests/images/secrets/Blah.txt")
+            .unwrap()
+            .save_as(&image_with_secret)
+            .hide_and_save()
+            .expect("Failed to hide legacy V2 payload");
+
+        let version = detect_version(&image_with_secret, &CodecOptions::default())
+            .expect("Failed to detect version");
+        assert_eq!(version, PayloadVersion::V2);
+
+        crate::api::unveil::prepare()
+            .from_secret_file(&image_with_secret)
+            .into_output_folder(temp_dir.path())
+            .execute()
+            .expect("Failed to unveil legacy V2 payload");
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("Blah.txt")).unwrap(),
+            std::fs::read("tests/images/secrets/Blah.txt").unwrap()
+        );
+    }
+
+    #[test]
+    fn should_preview_a_sequential_embedding_as_a_block() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let clean = temp_dir.path().join("clean.png");
+        let stego = temp_dir.path().join("stego.png");
+        // a smooth gradient, its LSBs follow the colors and not a payload
+        image::RgbImage::from_fn(120, 80, |x, y| {
+            image::Rgb([(x * 2) as u8, (y * 3) as u8, 100])
+        })
+        .save(&clean)
+        .unwrap();
+        crate::api::hide::prepare()
+            .with_file("tests/images/secrets/random_1666_byte.bin")
+            .with_image(&clean)
+            .with_output(&stego)
+            .execute()
+            .expect("Failed to hide the file");
+
+        let clean = lsb_preview(&clean, 12).expect("Failed to preview the clean image");
+        let stego = lsb_preview(&stego, 12).expect("Failed to preview the stego image");
+        assert_eq!((clean.columns, clean.rows), (12, 4));
+        assert_eq!((stego.columns, stego.rows), (12, 4));
+
+        // the payload fills the first columns and leaves the last ones as they were
+        assert!(stego.spread() > clean.spread() + 0.2);
+        for y in 0..stego.rows {
+            assert!((stego.density(2, y) - 0.5).abs() < 0.1);
+            assert_eq!(stego.density(11, y), clean.density(11, y));
+        }
+
+        assert!(matches!(
+            lsb_preview(Path::new("tests/audio/plain/carrier-audio.wav"), 12),
+            Err(SteganoError::UnsupportedMedia)
+        ));
+    }
+
+    #[test]
+    fn should_report_no_secret_data() {
+        let result = prepare()
+            .from_secret_file("tests/images/plain/carrier-image.png")
+            .execute();
+        assert!(matches!(result, Err(SteganoError::NoSecretData)));
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::{Path, PathBuf};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::media::{Media, Persist};
+use crate::{CodecOptions, SteganoError};
+
+/// Prepares the sanitize API, it destroys any data hidden in a media without visible changes
+pub fn prepare() -> SanitizeApi {
+    SanitizeApi::default()
+}
+
+#[derive(Default, Debug)]
+pub struct SanitizeApi {
+    media: Option<PathBuf>,
+    output: Option<PathBuf>,
+    options: CodecOptions,
+    seed: Option<u64>,
+}
+
+impl SanitizeApi {
+    /// Use the given codec options, they decide on the bits and channels that are overwritten
+    pub fn with_options(mut self, options: CodecOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// This is the media that might contain secret data, it's read-only
+    pub fn from_media(mut self, media: impl AsRef<Path>) -> Self {
+        self.media = Some(media.as_ref().to_path_buf());
+        self
+    }
+
+    /// The sanitized media will be stored as this file
+    pub fn with_output(mut self, output: impl AsRef<Path>) -> Self {
+        self.output = Some(output.as_ref().to_path_buf());
+        self
+    }
+
+    /// Makes the noise reproducible, by default it is random
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Execute the sanitizing, every bit that could carry data is overwritten with noise
+    pub fn execute(self) -> Result<(), SteganoError> {
+        let Some(media) = self.media else {
+            return Err(SteganoError::CarrierNotSet);
+        };
+        let Some(output) = self.output else {
+            return Err(SteganoError::TargetNotSet);
+        };
+
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut media = Media::from_file(&media)?;
+        media.sanitize(&self.options, &mut rng)?;
+
+        media.save_as(&output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::SteganoError;
+
+    #[test]
+    fn should_leave_no_secret_data_behind() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let secret = temp_dir.path().join("secret.png");
+        let sanitized = temp_dir.path().join("sanitized.png");
+        crate::api::hide::prepare()
+            .with_message("Hello, World!")
+            .with_image("tests/images/plain/carrier-image.png")
+            .with_output(&secret)
+            .execute()
+            .expect("Failed to hide message in image");
+        assert!(crate::api::probe::prepare()
+            .from_secret_file(&secret)
+            .execute()
+            .is_ok());
+
+        super::prepare()
+            .from_media(&secret)
+            .with_output(&sanitized)
+            .with_seed(42)
+            .execute()
+            .expect("Failed to sanitize");
+
+        assert!(matches!(
+            crate::api::probe::prepare()
+                .from_secret_file(&sanitized)
+                .execute(),
+            Err(SteganoError::NoSecretData)
+        ));
+        assert!(crate::api::unveil::prepare()
+            .from_secret_file(&sanitized)
+            .into_output_folder(temp_dir.path())
+            .execute()
+            .is_err());
+
+        // only the lowest bits changed
+        let before = image::open(&secret).unwrap().to_rgba8();
+        let after = image::open(&sanitized).unwrap().to_rgba8();
+        assert_ne!(before, after);
+        for (before, after) in before.pixels().zip(after.pixels()) {
+            for (before, after) in before.0.iter().zip(after.0.iter()) {
+                assert!(before.abs_diff(*after) <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn should_sanitize_audio() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let secret = temp_dir.path().join("secret.wav");
+        let sanitized = temp_dir.path().join("sanitized.wav");
+        crate::api::hide::prepare()
+            .with_message("Hello, World!")
+            .with_image("tests/audio/plain/carrier-audio.wav")
+            .with_output(&secret)
+            .execute()
+            .expect("Failed to hide message in audio");
+
+        super::prepare()
+            .from_media(&secret)
+            .with_output(&sanitized)
+            .with_seed(42)
+            .execute()
+            .expect("Failed to sanitize");
+
+        assert!(crate::api::unveil::prepare()
+            .from_secret_file(&sanitized)
+            .into_output_folder(temp_dir.path())
+            .execute()
+            .is_err());
+    }
+}
@@ -1,3 +1,7 @@
 mod password;
+mod permutation;
+mod split;
 
 pub use password::*;
+pub(crate) use permutation::*;
+pub(crate) use split::*;
@@ -1,8 +1,36 @@
 use std::fmt::{self, Debug, Formatter};
 
+use zeroize::Zeroize;
+
+/// An optional password, it is scrubbed from memory on drop
 #[derive(Default)]
 pub struct Password(Option<String>);
 
+impl Drop for Password {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Compares in constant time for passwords of equal length, to not leak timing information
+impl PartialEq for Password {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => {
+                a.len() == b.len()
+                    && a.bytes()
+                        .zip(b.bytes())
+                        .fold(0, |acc, (a, b)| acc | (a ^ b))
+                        == 0
+            }
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Password {}
+
 impl Debug for Password {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         if let Some(password) = &self.0 {
@@ -44,6 +72,16 @@ mod tests {
         assert_eq!(password.as_ref(), &Some("password".to_string()));
     }
 
+    #[test]
+    fn test_eq() {
+        let password: Password = "password".into();
+        assert_eq!(password, "password".into());
+        assert_ne!(password, "passwore".into());
+        assert_ne!(password, "password42".into());
+        assert_ne!(password, None.into());
+        assert_eq!(Password::from(None), None.into());
+    }
+
     #[test]
     fn test_debug() {
         let password: Password = None.into();
@@ -0,0 +1,142 @@
+use std::io::{Cursor, Read};
+
+use byteorder::ReadBytesExt;
+use stegano_seasmoke::derive_seed;
+
+use crate::media::payload::{read_length_header, HasFeature, PayloadCodecFeatures, PayloadVersion};
+use crate::media::Media;
+use crate::{CodecOptions, Result};
+
+/// Returns the options with the position seed derived from the password,
+/// unless a seed was already set explicitly
+pub(crate) fn with_password_seed(options: &CodecOptions, password: &str) -> Result<CodecOptions> {
+    let mut options = options.clone();
+    if options.permutation_seed.is_none() {
+        options.permutation_seed = Some(derive_seed(password)?);
+    }
+
+    Ok(options)
+}
+
+/// Decodes with the position order derived from the password first,
+/// images that were hidden before the order was shuffled are decoded in their linear order.
+///
+/// The linear order is only read if the password order shows no header of an encrypted
+/// payload, so the errors after a valid header, like a wrong password, are returned as they are.
+///
+/// `payload` opens the hidden bytes for the given options, `decode` reads them.
+pub(crate) fn decode_with_password_order<'m, T>(
+    media: &Media,
+    options: &CodecOptions,
+    password: Option<&str>,
+    payload: impl Fn(&CodecOptions) -> Result<Box<dyn Read + 'm>>,
+    decode: impl Fn(&mut dyn Read, &CodecOptions) -> Result<T>,
+) -> Result<T> {
+    options.check_bit_plane()?;
+    let Some(password) =
+        password.filter(|_| media.is_image() && options.permutation_seed.is_none())
+    else {
+        return decode(&mut payload(options)?, options);
+    };
+    let mut shuffled = options.clone();
+    shuffled.permutation_seed = Some(derive_seed(password)?);
+
+    if let Some((header, reader)) = encrypted_payload(media, &shuffled, &payload) {
+        return decode(&mut Cursor::new(header).chain(reader), &shuffled);
+    }
+    // hidden before the order was shuffled
+    decode(&mut payload(options)?, options)
+}
+
+/// Opens the payload, if it starts with the header of an encrypted payload. The header is read
+/// already, its bytes are returned along with the reader of the rest.
+fn encrypted_payload<'m>(
+    media: &Media,
+    options: &CodecOptions,
+    payload: impl Fn(&CodecOptions) -> Result<Box<dyn Read + 'm>>,
+) -> Option<(Vec<u8>, Box<dyn Read + 'm>)> {
+    let mut reader = payload(options).ok()?;
+    let header = encrypted_header(&mut reader, media.capacity_bytes(options))?;
+
+    Some((header, reader))
+}
+
+/// Reads the version and the length header, returns their bytes if they belong to an
+/// encrypted payload that fits into the `capacity`
+fn encrypted_header(reader: &mut dyn Read, capacity: usize) -> Option<Vec<u8>> {
+    let version = reader.read_u8().ok()?;
+    if !matches!(
+        PayloadVersion::try_from(version),
+        Ok(PayloadVersion::V4Encrypted | PayloadVersion::V76Encrypted)
+    ) {
+        return None;
+    }
+    let long = version.has_feature(PayloadCodecFeatures::LongLengthHeader);
+    let len = read_length_header(reader, long).ok()?;
+    if len > capacity as u64 {
+        return None;
+    }
+
+    let mut header = vec![version];
+    if long {
+        header.extend_from_slice(&len.to_be_bytes());
+    } else {
+        header.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+
+    Some(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::media::RgbaImage;
+    use crate::SteganoError;
+
+    /// an encrypted V4 header that announces 8 bytes
+    const ENCRYPTED_HEADER: [u8; 5] = [44, 0, 0, 0, 8];
+
+    #[test]
+    fn should_return_the_error_after_a_header_in_the_password_order() {
+        let media = Media::Image(RgbaImage::new(32, 32));
+        let orders = RefCell::new(Vec::new());
+
+        let result: Result<()> = decode_with_password_order(
+            &media,
+            &CodecOptions::default(),
+            Some("secret"),
+            |options| {
+                orders.borrow_mut().push(options.permutation_seed.is_some());
+                Ok(Box::new(Cursor::new(ENCRYPTED_HEADER.to_vec())))
+            },
+            |_, _| Err(SteganoError::WrongPassword),
+        );
+
+        assert!(matches!(result, Err(SteganoError::WrongPassword)));
+        assert_eq!(*orders.borrow(), [true], "the linear order is not read");
+    }
+
+    #[test]
+    fn should_read_the_linear_order_without_a_header_in_the_password_order() {
+        let media = Media::Image(RgbaImage::new(32, 32));
+
+        let result = decode_with_password_order(
+            &media,
+            &CodecOptions::default(),
+            Some("secret"),
+            |options| match options.permutation_seed {
+                Some(_) => Ok(Box::new(Cursor::new(vec![0xff; 5]))),
+                None => Ok(Box::new(Cursor::new(ENCRYPTED_HEADER.to_vec()))),
+            },
+            |payload, options| {
+                let mut header = Vec::new();
+                payload.read_to_end(&mut header)?;
+                Ok((header, options.permutation_seed))
+            },
+        );
+
+        assert_eq!(result.unwrap(), (ENCRYPTED_HEADER.to_vec(), None));
+    }
+}
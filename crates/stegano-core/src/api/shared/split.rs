@@ -0,0 +1,71 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+/// the file inside a message that carries one part of a split secret
+pub(crate) const SPLIT_PART_FILE: &str = "split-part.bin";
+/// marks a part of a split secret, it is not secret and just tells parts apart from other files
+const SPLIT_MAGIC: [u8; 4] = *b"StgP";
+/// the magic, part index, total parts and original length in front of the data of a part
+pub(crate) const SPLIT_HEADER_LEN: usize = SPLIT_MAGIC.len() + 2 + 2 + 8;
+
+/// One part of a secret that is split across several carriers
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct SplitPart {
+    pub index: u16,
+    pub total: u16,
+    /// the length of the whole secret
+    pub original_len: u64,
+    pub data: Vec<u8>,
+}
+
+impl SplitPart {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(SPLIT_HEADER_LEN + self.data.len());
+        bytes.extend_from_slice(&SPLIT_MAGIC);
+        // writing into a Vec cannot fail
+        bytes.write_u16::<BigEndian>(self.index).unwrap();
+        bytes.write_u16::<BigEndian>(self.total).unwrap();
+        bytes.write_u64::<BigEndian>(self.original_len).unwrap();
+        bytes.extend_from_slice(&self.data);
+
+        bytes
+    }
+
+    /// `None` if the bytes are no part of a split secret
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut header = bytes.strip_prefix(&SPLIT_MAGIC[..])?;
+        let index = header.read_u16::<BigEndian>().ok()?;
+        let total = header.read_u16::<BigEndian>().ok()?;
+        let original_len = header.read_u64::<BigEndian>().ok()?;
+        if index >= total {
+            return None;
+        }
+
+        Some(Self {
+            index,
+            total,
+            original_len,
+            data: header.to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_read_back_a_written_part() {
+        let part = SplitPart {
+            index: 1,
+            total: 3,
+            original_len: 1000,
+            data: b"some data".to_vec(),
+        };
+        let bytes = part.to_bytes();
+        assert_eq!(bytes.len(), SPLIT_HEADER_LEN + 9);
+
+        assert_eq!(SplitPart::from_bytes(&bytes), Some(part));
+        assert_eq!(SplitPart::from_bytes(b"StgX whatever"), None);
+        assert_eq!(SplitPart::from_bytes(&bytes[..10]), None);
+    }
+}
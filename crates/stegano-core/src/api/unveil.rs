@@ -1,30 +1,426 @@
 use std::{
+    collections::VecDeque,
     fs::File,
-    io::Write,
+    hash::{Hash, Hasher},
+    io::{Cursor, Read, Write},
     path::{Path, PathBuf},
 };
 
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use byteorder::ReadBytesExt;
+
 use crate::{
     media::{
-        audio, image,
-        payload::{FabA, FabS, PayloadCodecFactory},
-        Media,
+        payload::{
+            read_length_header, FabA, FabS, HasFeature, PayloadCodecFactory, PayloadCodecFeatures,
+        },
+        Media, StegoCodec,
     },
-    CodecOptions, Message, SteganoError,
+    message::MESSAGE_HEADER_LEN,
+    CodecOptions, Manifest, MediaFormat, Message, RawMessage, SteganoError,
 };
 
-use super::Password;
+use super::{decode_with_password_order, Password, SplitPart, SPLIT_PART_FILE};
+
+/// Unveils the message of the media, the password decrypts it and, for images,
+/// determines the order in which the data was hidden
+pub(crate) fn unveil_message(
+    media: &Media,
+    password: Option<&str>,
+    options: &CodecOptions,
+) -> Result<Message, SteganoError> {
+    unveil_message_with(media, password, options, None)
+}
+
+/// Like `unveil_message`, but the payload is extracted by `codec` if one is given
+fn unveil_message_with(
+    media: &Media,
+    password: Option<&str>,
+    options: &CodecOptions,
+    codec: Option<&dyn StegoCodec>,
+) -> Result<Message, SteganoError> {
+    media.ensure_capacity(MESSAGE_HEADER_LEN, options)?;
+    let fab: Box<dyn PayloadCodecFactory> = match password {
+        Some(password) => Box::new(FabS::new(password)),
+        None => Box::new(FabA),
+    };
+
+    crate::media::timed("unveiling", || {
+        decode_with_password_order(
+            media,
+            options,
+            password,
+            |options| match codec {
+                // the built-in decoder is streamed, it stops reading once the payload is complete
+                None => {
+                    media.ensure_unaltered(options)?;
+                    media.ensure_copies_agree(options)?;
+                    Ok(media.decoder(options))
+                }
+                Some(codec) => Ok(Box::new(Cursor::new(codec.extract(media, options)?))),
+            },
+            |payload, options| Message::from_raw_data_with_options(payload, &*fab, options),
+        )
+    })
+}
+
+/// Streams the payload of a stego media, e.g. the zip archive of the hidden files, see [`open_reader`]
+pub struct MediaReader<'m> {
+    inner: Box<dyn Read + 'm>,
+    /// the bytes of the payload that are not read yet
+    remaining: u64,
+}
+
+impl MediaReader<'_> {
+    fn of(content: Vec<u8>) -> Self {
+        Self {
+            remaining: content.len() as u64,
+            inner: Box::new(Cursor::new(content)),
+        }
+    }
+}
+
+impl Read for MediaReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.remaining == 0 {
+            return Ok(0);
+        }
+        let max = usize::try_from(self.remaining).map_or(buf.len(), |r| r.min(buf.len()));
+        let n = self.inner.read(&mut buf[..max])?;
+        if n == 0 && max > 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("the payload misses {} bytes", self.remaining),
+            ));
+        }
+        self.remaining -= n as u64;
+
+        Ok(n)
+    }
+}
+
+/// Opens the payload of the media for reading, without the payload header. A plain payload is
+/// streamed from the media while it is read, so it can be piped into a parser of its own.
+///
+/// An encrypted payload is authenticated as a whole, it is decrypted at once when the reader
+/// is opened. So are legacy payloads that carry no length header.
+pub fn open_reader<'m>(
+    media: &'m Media,
+    password: Option<&str>,
+    options: &CodecOptions,
+) -> Result<MediaReader<'m>, SteganoError> {
+    media.ensure_capacity(MESSAGE_HEADER_LEN, options)?;
+    if let Some(password) = password {
+        let fab = FabS::new(password);
+        let content = decode_with_password_order(
+            media,
+            options,
+            Some(password),
+            |options| Ok(media.decoder(options)),
+            |payload, _| Ok(RawMessage::from_raw_data(payload, &fab)?.content),
+        )?;
+        return Ok(MediaReader::of(content));
+    }
+
+    let mut decoder = media.decoder(options);
+    let features = PayloadCodecFeatures::MixedFeatures(decoder.read_u8()?);
+    if !features.has_feature(PayloadCodecFeatures::LengthHeader)
+        || features.has_feature(PayloadCodecFeatures::ChaCrypto)
+    {
+        let content = FabA.create_codec(features)?.decode(&mut decoder)?;
+        return Ok(MediaReader::of(content));
+    }
+    let remaining = read_length_header(
+        &mut decoder,
+        features.has_feature(PayloadCodecFeatures::LongLengthHeader),
+    )?;
+
+    Ok(MediaReader {
+        inner: decoder,
+        remaining,
+    })
+}
+
+/// A file inside the secret data of a media, as `list` finds it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HiddenFile {
+    pub name: String,
+    /// the size in bytes, once unveiled
+    pub size: usize,
+}
+
+/// Lists the files inside the secret data of the media without writing any of them,
+/// a text message is listed as `secret-message.txt` like it would be unveiled.
+/// The files come in the order they were hidden, the text last.
+pub fn list(
+    media: &Path,
+    password: Option<&str>,
+    options: &CodecOptions,
+) -> Result<Vec<HiddenFile>, SteganoError> {
+    let media = Media::from_file(media)?;
+    let msg = unveil_message(&media, password, options)?;
+
+    let mut files: Vec<_> = msg
+        .files
+        .into_iter()
+        .map(|(name, data)| HiddenFile {
+            name,
+            size: data.len(),
+        })
+        .collect();
+    if let Some(text) = msg.text {
+        files.push(HiddenFile {
+            name: "secret-message.txt".to_owned(),
+            size: text.len(),
+        });
+    }
+
+    Ok(files)
+}
+
+/// Unveils a media whose codec options are not known, by trying the `candidates` in order.
+/// The first candidate whose payload header, zip checksums and, with a password, the
+/// authentication tag hold up wins. An empty message does not count, noise hardly ever
+/// decodes to anything else. The text of the message is returned as `secret-message.txt`.
+///
+/// Errors with the error of the first candidate if none of them fits,
+/// or with `SteganoError::NoSecretData` if there are no candidates.
+pub fn unveil_auto(
+    media: &Path,
+    password: Option<&str>,
+    candidates: &[CodecOptions],
+) -> Result<Vec<(String, Vec<u8>)>, SteganoError> {
+    let media = Media::from_file(media)?;
+    let mut first_error = None;
+    for options in candidates {
+        match unveil_message(&media, password, options) {
+            Ok(msg) if msg.files.is_empty() && msg.text.as_ref().is_none_or(String::is_empty) => {
+                first_error.get_or_insert(SteganoError::NoSecretData);
+            }
+            Ok(msg) => {
+                let mut files = msg.files;
+                if let Some(text) = msg.text {
+                    files.push(("secret-message.txt".to_owned(), text.into_bytes()));
+                }
+                return Ok(files);
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    Err(first_error.unwrap_or(SteganoError::NoSecretData))
+}
+
+/// Reads just the manifest that `SteganoEncoder::with_manifest` hid in front of the files.
+/// Errors with `SteganoError::FileNotFound` if the message has no manifest.
+pub fn read_manifest(
+    media: &Path,
+    password: Option<&str>,
+    options: &CodecOptions,
+) -> Result<Manifest, SteganoError> {
+    let media = Media::from_file(media)?;
+    let msg = unveil_message(&media, password, options)?;
+
+    Manifest::from_message(&msg)
+}
+
+/// Puts a secret back together that `hide::hide_split` split across several carriers and writes
+/// it to `output`. The stego media can be given in any order, but all parts are needed.
+/// Errors with `SteganoError::MissingSplitParts` if some are missing.
+pub fn unveil_join(
+    stego_media: &[PathBuf],
+    output: &Path,
+    password: Option<&str>,
+    options: &CodecOptions,
+) -> Result<(), SteganoError> {
+    let mut parts = Vec::with_capacity(stego_media.len());
+    for media in stego_media {
+        let msg = unveil_message(&Media::from_file(media)?, password, options)?;
+        let part = msg
+            .files
+            .iter()
+            .find(|(name, _)| name == SPLIT_PART_FILE)
+            .and_then(|(_, data)| SplitPart::from_bytes(data))
+            .ok_or(SteganoError::NoSecretData)?;
+        parts.push(part);
+    }
+    parts.sort_by_key(|part| part.index);
+    parts.dedup_by_key(|part| part.index);
+
+    let Some(first) = parts.first() else {
+        return Err(SteganoError::NoSecretData);
+    };
+    let (total, original_len) = (first.total, first.original_len);
+    if parts
+        .iter()
+        .any(|part| part.total != total || part.original_len != original_len)
+    {
+        return Err(SteganoError::MismatchedSplitParts);
+    }
+    let missing: Vec<u16> = (0..total)
+        .filter(|index| {
+            parts
+                .binary_search_by_key(index, |part| part.index)
+                .is_err()
+        })
+        .collect();
+    if !missing.is_empty() {
+        return Err(SteganoError::MissingSplitParts { missing });
+    }
+
+    let data: Vec<u8> = parts.into_iter().flat_map(|part| part.data).collect();
+    if data.len() as u64 != original_len {
+        return Err(SteganoError::MismatchedSplitParts);
+    }
+
+    std::fs::write(output, data).map_err(|source| SteganoError::Io {
+        path: output.to_path_buf(),
+        source,
+    })
+}
+
+/// The key of a message in an `UnveilCache`, a BLAKE2b digest of the media bytes, the options
+/// and the password, so a lookup costs no key derivation.
+pub type UnveilCacheKey = [u8; 32];
+
+/// Storage for unveiled messages, so that the same media is decoded only once.
+pub trait UnveilCache {
+    fn get(&mut self, key: &UnveilCacheKey) -> Option<Message>;
+    fn insert(&mut self, key: UnveilCacheKey, message: Message);
+}
+
+/// In-memory `UnveilCache` that forgets the least recently used message once it is full
+#[derive(Debug)]
+pub struct LruUnveilCache {
+    capacity: usize,
+    entries: VecDeque<(UnveilCacheKey, Message)>,
+    hits: usize,
+}
+
+impl LruUnveilCache {
+    /// a cache that holds up to `capacity` messages
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+            hits: 0,
+        }
+    }
+
+    /// how often a message was served from the cache
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+}
+
+impl UnveilCache for LruUnveilCache {
+    fn get(&mut self, key: &UnveilCacheKey) -> Option<Message> {
+        let position = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(position)?;
+        let message = entry.1.clone();
+        self.entries.push_back(entry);
+        self.hits += 1;
+
+        Some(message)
+    }
+
+    fn insert(&mut self, key: UnveilCacheKey, message: Message) {
+        self.entries.retain(|(k, _)| *k != key);
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((key, message));
+    }
+}
+
+/// Unveils the message of the media bytes like `unveil_message`, but asks `cache` first.
+/// A different password or different options never hit a message cached for others.
+pub fn unveil_cached(
+    media: &[u8],
+    password: Option<&str>,
+    options: &CodecOptions,
+    cache: &mut impl UnveilCache,
+) -> Result<Message, SteganoError> {
+    let key = cache_key(media, password, options);
+    if let Some(message) = cache.get(&key) {
+        return Ok(message);
+    }
+    let message = unveil_to_memory(media, password, options)?;
+    cache.insert(key, message.clone());
+
+    Ok(message)
+}
+
+fn cache_key(media: &[u8], password: Option<&str>, options: &CodecOptions) -> UnveilCacheKey {
+    let mut digest = Blake2b::<U32>::new();
+    digest.update((media.len() as u64).to_be_bytes());
+    digest.update(media);
+    options.hash(&mut DigestHasher(&mut digest));
+    match password {
+        Some(password) => {
+            digest.update([1]);
+            digest.update((password.len() as u64).to_be_bytes());
+            digest.update(password.as_bytes());
+        }
+        None => digest.update([0]),
+    }
+
+    digest.finalize().into()
+}
+
+/// feeds what a `Hash` implementation writes into a digest
+struct DigestHasher<'d, D: Digest>(&'d mut D);
+
+impl<D: Digest> Hasher for DigestHasher<'_, D> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        unreachable!("only the digest is finalized")
+    }
+}
+
+/// Unveils the message of the media bytes, like `SteganoEncoder::hide_to_vec` produces them,
+/// without touching the filesystem
+pub fn unveil_to_memory(
+    media: &[u8],
+    password: Option<&str>,
+    options: &CodecOptions,
+) -> Result<Message, SteganoError> {
+    let format = MediaFormat::detect_from_bytes(media).ok_or(SteganoError::UnsupportedMedia)?;
+
+    unveil_message(&Media::from_bytes(media, format)?, password, options)
+}
 
 pub fn prepare() -> UnveilApi {
     UnveilApi::default()
 }
 
+/// The outcome of an unveil, see `UnveilApi::execute_with_report`
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnveilReport {
+    /// the paths of the files that were written
+    pub written: Vec<PathBuf>,
+    /// the names of the hidden files that were damaged and skipped
+    pub damaged: Vec<String>,
+}
+
 #[derive(Default, Debug)]
 pub struct UnveilApi {
     secret_media: Option<PathBuf>,
+    media: Option<Media>,
     output_folder: Option<PathBuf>,
     password: Password,
     options: CodecOptions,
+    only_file: Option<String>,
+    overwrite: bool,
+    codec: Option<Box<dyn StegoCodec>>,
 }
 
 impl UnveilApi {
@@ -46,12 +442,33 @@ impl UnveilApi {
         self
     }
 
+    /// This is the media that contains the data to be unveiled, already decoded,
+    /// e.g. by `Media::from_bytes`. It takes precedence over a secret file
+    pub fn from_media(mut self, media: Media) -> Self {
+        self.media = Some(media);
+        self
+    }
+
     /// This is the folder where the data will be saved to
     pub fn into_output_folder(mut self, output_folder: impl AsRef<Path>) -> Self {
         self.output_folder = Some(output_folder.as_ref().to_path_buf());
         self
     }
 
+    /// Only the file with this name is written, all other files are skipped.
+    /// The hidden text message can be selected as `secret-message.txt`.
+    pub fn only_file(mut self, name: impl Into<String>) -> Self {
+        self.only_file = Some(name.into());
+        self
+    }
+
+    /// Allows to overwrite files that already exist in the output folder,
+    /// by default unveil refuses with `SteganoError::OutputExists` before anything is written
+    pub fn overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
     /// Set the password used for encrypting all data
     /// If `None` is passed, no password will be used, leads to no de-/encryption used
     pub fn using_password<P: Into<Password>>(mut self, password: P) -> Self {
@@ -59,57 +476,86 @@ impl UnveilApi {
         self
     }
 
-    /// Execute the unveil process and blocks until it is finished
-    pub fn execute(self) -> Result<(), SteganoError> {
-        let Some(secret_media) = self.secret_media else {
-            return Err(SteganoError::CarrierNotSet);
+    /// Extracts the payload with this codec, the one it was hidden with by
+    /// `SteganoEncoder::with_codec`
+    pub fn with_codec(mut self, codec: Box<dyn StegoCodec>) -> Self {
+        self.codec = Some(codec);
+        self
+    }
+
+    /// Execute the unveil process and blocks until it is finished,
+    /// returns the paths of the files that were written
+    pub fn execute(self) -> Result<Vec<PathBuf>, SteganoError> {
+        Ok(self.execute_with_report()?.written)
+    }
+
+    /// Like `execute`, but also tells which files were skipped as damaged,
+    /// see `CodecOptions::best_effort`
+    pub fn execute_with_report(self) -> Result<UnveilReport, SteganoError> {
+        let media = match (self.media, self.secret_media) {
+            (Some(media), _) => media,
+            (None, Some(secret_media)) => Media::from_file(&secret_media)?,
+            (None, None) => return Err(SteganoError::CarrierNotSet),
         };
         let Some(output_folder) = self.output_folder else {
             return Err(SteganoError::TargetNotSet);
         };
 
-        let media = Media::from_file(&secret_media)?;
-        let fab: Box<dyn PayloadCodecFactory> = if let Some(password) = self.password.as_ref() {
-            Box::new(FabS::new(password))
-        } else {
-            Box::new(FabA)
-        };
-
-        let msg = match media {
-            Media::Image(image) => {
-                let mut decoder = image::LsbCodec::decoder(&image, &self.options);
-                Message::from_raw_data(&mut decoder, &*fab)?
-            }
-            Media::Audio(audio) => {
-                let mut decoder = audio::LsbCodec::decoder(&audio.1);
-                Message::from_raw_data(&mut decoder, &*fab)?
-            }
-        };
+        let msg = unveil_message_with(
+            &media,
+            self.password.as_ref().as_deref(),
+            &self.options,
+            self.codec.as_deref(),
+        )?;
 
         let mut files = msg.files;
         if let Some(text) = msg.text {
             files.push(("secret-message.txt".to_owned(), text.as_bytes().to_vec()));
         }
 
-        if files.is_empty() {
+        if files.is_empty() && msg.damaged_files.is_empty() {
             return Err(SteganoError::NoSecretData);
         }
 
-        for (file_name, buf) in files.iter().map(|(file_name, buf)| {
-            let file = Path::new(file_name).file_name().unwrap().to_str().unwrap();
+        if let Some(name) = self.only_file {
+            files.retain(|(file_name, _)| file_name == &name);
+            if files.is_empty() {
+                return Err(SteganoError::FileNotFound { name });
+            }
+        }
+
+        let target_file_of = |name: &str| output_folder.join(Path::new(name).file_name().unwrap());
+        if !self.overwrite {
+            if let Some(path) = files
+                .iter()
+                .map(|(name, _)| target_file_of(name))
+                .find(|path| path.exists())
+            {
+                return Err(SteganoError::OutputExists { path });
+            }
+        }
 
-            (file, buf)
-        }) {
-            let target_file = output_folder.join(file_name);
-            let mut target_file =
-                File::create(target_file).map_err(|source| SteganoError::WriteError { source })?;
+        let mut written = Vec::with_capacity(files.len());
+        for (name, buf) in files.iter() {
+            let target_path = target_file_of(name);
+            let io_error = |source| SteganoError::Io {
+                path: target_path.clone(),
+                source,
+            };
+            let mut target_file = File::create(&target_path).map_err(io_error)?;
 
-            target_file
-                .write_all(buf.as_slice())
-                .map_err(|source| SteganoError::WriteError { source })?;
+            target_file.write_all(buf.as_slice()).map_err(io_error)?;
+            // the file gets back its original modification time, if it was hidden along
+            if let Some(modified) = msg.metadata.get(name).and_then(|m| m.modified_time()) {
+                target_file.set_modified(modified).map_err(io_error)?;
+            }
+            written.push(target_path);
         }
 
-        Ok(())
+        Ok(UnveilReport {
+            written,
+            damaged: msg.damaged_files,
+        })
     }
 }
 
@@ -119,6 +565,224 @@ mod tests {
 
     use tempfile::tempdir;
 
+    #[test]
+    fn should_find_the_step_increment_among_the_candidates() {
+        use crate::CodecOptions;
+
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let secret_media = temp_dir.path().join("stepped.png");
+        // without the last row there is no placement header that tells the step
+        let options = |step| CodecOptions {
+            color_channel_step_increment: step,
+            skip_last_row_and_column: false,
+            ..Default::default()
+        };
+        crate::SteganoEncoder::with_options(options(3))
+            .use_media("tests/images/plain/carrier-image.png")
+            .unwrap()
+            .add_message("stepped over")
+            .unwrap()
+            .save_as(&secret_media)
+            .hide_and_save()
+            .unwrap();
+
+        assert!(super::unveil_auto(&secret_media, None, &[options(1)]).is_err());
+        assert!(super::unveil_auto(&secret_media, None, &[]).is_err());
+        let files = super::unveil_auto(&secret_media, None, &[1, 2, 3, 4].map(options)).unwrap();
+        assert_eq!(
+            files,
+            vec![("secret-message.txt".to_owned(), b"stepped over".to_vec())]
+        );
+    }
+
+    #[test]
+    fn should_stream_the_payload_in_small_chunks() {
+        use crate::media::payload::{FabA, FabS};
+        use crate::{CodecOptions, Media, RawMessage};
+        use std::io::Read;
+
+        let opts = CodecOptions::default();
+        let carrier = Media::from_file("tests/images/plain/carrier-image.png".as_ref()).unwrap();
+        let mut message = crate::Message::empty();
+        message
+            .add_file_data("lorem.txt", b"lorem ipsum dolor sit amet ".repeat(40))
+            .unwrap();
+
+        for password in [None, Some("Secret42")] {
+            let mut media = carrier.clone();
+            let raw = match password {
+                Some(password) => message.to_raw_data(&FabS::new(password)),
+                None => message.to_raw_data(&FabA),
+            };
+            media.hide_data(raw.unwrap(), &opts).unwrap();
+            let one_shot = match password {
+                Some(password) => {
+                    RawMessage::from_raw_data(&mut media.decoder(&opts), &FabS::new(password))
+                }
+                None => RawMessage::from_raw_data(&mut media.decoder(&opts), &FabA),
+            }
+            .unwrap()
+            .content;
+
+            let mut reader = super::open_reader(&media, password, &opts).unwrap();
+            let mut streamed = Vec::new();
+            let mut chunk = [0; 7];
+            loop {
+                match reader.read(&mut chunk).unwrap() {
+                    0 => break,
+                    n => streamed.extend_from_slice(&chunk[..n]),
+                }
+            }
+            assert_eq!(streamed, one_shot, "password: {password:?}");
+        }
+    }
+
+    #[test]
+    fn should_name_the_file_that_a_truncated_media_cut_off() {
+        use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+        let mut message = crate::Message::empty();
+        for (name, len) in [("first.bin", 200), ("second.bin", 600)] {
+            let mut data = vec![0; len];
+            StdRng::seed_from_u64(len as u64).fill_bytes(&mut data);
+            message.add_file_data(name, data).unwrap();
+        }
+        let opts = crate::CodecOptions::default();
+        let mut media = crate::testing::generate_carrier(64, 64, 1);
+        media
+            .hide_data(
+                message.to_raw_data(&crate::media::payload::FabA).unwrap(),
+                &opts,
+            )
+            .unwrap();
+
+        // the columns are traversed one after the other, the right part of the image is lost
+        let crate::Media::Image(image) = media else {
+            unreachable!()
+        };
+        let truncated = image::imageops::crop_imm(&image, 0, 0, 25, 64).to_image();
+        let result = super::unveil_message(&crate::Media::Image(truncated), None, &opts);
+
+        assert!(
+            matches!(
+                &result,
+                Err(crate::SteganoError::TruncatedPayload { file_name, expected, got })
+                    if file_name == "second.bin" && got < expected
+            ),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn should_detect_a_modified_copy_with_redundancy() {
+        use crate::{CodecOptions, Media, SteganoError};
+
+        let mut message = crate::Message::empty();
+        message
+            .add_file_data("hello.txt", b"Hello World!".to_vec())
+            .unwrap();
+        let raw = message.to_raw_data(&crate::media::payload::FabA).unwrap();
+        let opts = CodecOptions {
+            redundancy: 2,
+            ..Default::default()
+        };
+        let mut media = crate::testing::generate_carrier(64, 64, 1);
+        let capacity = media.capacity_bytes(&CodecOptions::default());
+        assert_eq!(media.capacity_bytes(&opts), capacity / 2 - 5);
+        media.hide_data(raw.clone(), &opts).unwrap();
+        let Media::Image(mut image) = media else {
+            unreachable!()
+        };
+
+        let unveiled =
+            super::unveil_message(&Media::Image(image.clone()), None, &CodecOptions::default())
+                .unwrap();
+        assert_eq!(unveiled.files, message.files);
+
+        // the lowest bit of the last byte of the second copy, the columns are 63 pixels high
+        let bit = (2 * (5 + raw.len()) - 1) * 8;
+        let (pixel, channel) = (bit / 3, bit % 3);
+        image
+            .get_pixel_mut((pixel / 63) as u32, (pixel % 63) as u32)
+            .0[channel] ^= 1;
+
+        let result = super::unveil_message(&Media::Image(image), None, &CodecOptions::default());
+        assert!(
+            matches!(result, Err(SteganoError::CopiesDisagree { copies: 2 })),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn should_tell_that_a_resized_carrier_was_altered() {
+        use crate::{CarrierSize, CodecOptions, Media, SteganoError};
+
+        let mut message = crate::Message::empty();
+        message
+            .add_file_data("hello.txt", b"Hello World!".to_vec())
+            .unwrap();
+        let opts = CodecOptions {
+            record_carrier_size: true,
+            ..Default::default()
+        };
+        let mut media = crate::testing::generate_carrier(64, 64, 1);
+        media
+            .hide_data(
+                message.to_raw_data(&crate::media::payload::FabA).unwrap(),
+                &opts,
+            )
+            .unwrap();
+        let Media::Image(image) = media else {
+            unreachable!()
+        };
+
+        // the size is recorded, but the unveil does not need to know about it
+        let unaltered =
+            super::unveil_message(&Media::Image(image.clone()), None, &CodecOptions::default())
+                .unwrap();
+        assert_eq!(unaltered.files, message.files);
+
+        let cropped = image::imageops::crop_imm(&image, 0, 0, 48, 64).to_image();
+        let mut padded = image::RgbaImage::new(64, 80);
+        image::imageops::replace(&mut padded, &image, 0, 0);
+        for (altered, width, height) in [(cropped, 48, 64), (padded, 64, 80)] {
+            let result =
+                super::unveil_message(&Media::Image(altered), None, &CodecOptions::default());
+            assert!(
+                matches!(
+                    result,
+                    Err(SteganoError::CarrierAltered {
+                        expected: CarrierSize::Pixels {
+                            width: 64,
+                            height: 64
+                        },
+                        actual: CarrierSize::Pixels { width: w, height: h },
+                    }) if (w, h) == (width, height)
+                ),
+                "{result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn should_unveil_from_an_already_decoded_media() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let bytes = std::fs::read("tests/images/with_text/hello_world.png").unwrap();
+        let media = crate::Media::from_bytes(&bytes, crate::MediaFormat::Png).unwrap();
+
+        let written = crate::api::unveil::prepare()
+            .from_media(media)
+            .into_output_folder(temp_dir.path())
+            .execute()
+            .expect("Failed to unveil message from media");
+
+        assert_eq!(written, vec![temp_dir.path().join("secret-message.txt")]);
+        assert_eq!(
+            std::fs::read_to_string(&written[0]).unwrap(),
+            "Hello World!"
+        );
+    }
+
     #[test]
     fn illustrate_api_usage() {
         let temp_dir = tempdir().expect("Failed to create temporary directory");
@@ -138,4 +802,200 @@ mod tests {
         .expect("Failed to read file");
         assert_eq!(secret_message, "Hello World");
     }
+
+    #[test]
+    fn should_unveil_only_one_file() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+        crate::api::unveil::prepare()
+            .from_secret_file("tests/images/with_attachment/Blah.txt__and__Blah-2.txt.png")
+            .only_file("Blah-2.txt")
+            .into_output_folder(temp_dir.path())
+            .execute()
+            .expect("Failed to unveil one file from image");
+
+        assert_eq!(temp_dir.path().read_dir().unwrap().count(), 1);
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("Blah-2.txt")).unwrap(),
+            std::fs::read("tests/images/secrets/Blah-2.txt").unwrap()
+        );
+    }
+
+    #[test]
+    fn should_error_if_the_only_file_is_not_there() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+        let result = crate::api::unveil::prepare()
+            .from_secret_file("tests/images/with_attachment/Blah.txt__and__Blah-2.txt.png")
+            .only_file("Blah-3.txt")
+            .into_output_folder(temp_dir.path())
+            .execute();
+
+        assert!(
+            matches!(result, Err(crate::SteganoError::FileNotFound { name }) if name == "Blah-3.txt")
+        );
+        assert_eq!(temp_dir.path().read_dir().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn should_error_if_the_carrier_cannot_hold_a_header() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let blank = temp_dir.path().join("blank.png");
+        image::RgbaImage::new(2, 2).save(&blank).unwrap();
+
+        let result = crate::api::unveil::prepare()
+            .from_secret_file(&blank)
+            .into_output_folder(temp_dir.path())
+            .execute();
+
+        assert!(matches!(
+            result,
+            Err(crate::SteganoError::CarrierTooSmall {
+                needed: 5,
+                available: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn should_report_a_wrong_password() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+        let result = crate::api::unveil::prepare()
+            .from_secret_file("tests/images/encrypted/hello_world.png")
+            .using_password("Secret43")
+            .into_output_folder(temp_dir.path())
+            .execute();
+
+        assert!(matches!(result, Err(crate::SteganoError::WrongPassword)));
+        assert_eq!(temp_dir.path().read_dir().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn should_require_a_password_for_an_encrypted_image() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+        let result = crate::api::unveil::prepare()
+            .from_secret_file("tests/images/encrypted/hello_world.png")
+            .into_output_folder(temp_dir.path())
+            .execute();
+
+        assert!(
+            matches!(result, Err(crate::SteganoError::PasswordRequired)),
+            "{result:?}"
+        );
+        assert_eq!(temp_dir.path().read_dir().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn should_ignore_a_password_for_a_plain_image() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+        crate::api::unveil::prepare()
+            .from_secret_file("tests/images/with_attachment/Blah.txt.png")
+            .using_password("Secret42")
+            .into_output_folder(temp_dir.path())
+            .execute()
+            .expect("the password should be ignored");
+
+        assert!(temp_dir.path().join("Blah.txt").exists());
+    }
+
+    #[test]
+    fn should_restore_the_modification_time() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let data_file = temp_dir.path().join("diary.txt");
+        std::fs::write(&data_file, b"dear diary").unwrap();
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_234_567_890);
+        std::fs::File::options()
+            .write(true)
+            .open(&data_file)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let secret_image = temp_dir.path().join("secret.png");
+        crate::api::hide::prepare()
+            .with_file(&data_file)
+            .with_image("tests/images/plain/carrier-image.png")
+            .with_output(&secret_image)
+            .execute()
+            .expect("Failed to hide the file");
+
+        let out_dir = temp_dir.path().join("out");
+        std::fs::create_dir(&out_dir).unwrap();
+        crate::api::unveil::prepare()
+            .from_secret_file(&secret_image)
+            .into_output_folder(&out_dir)
+            .execute()
+            .expect("Failed to unveil the file");
+
+        let unveiled = std::fs::metadata(out_dir.join("diary.txt")).unwrap();
+        assert_eq!(unveiled.modified().unwrap(), modified);
+    }
+
+    #[test]
+    fn should_not_overwrite_unveiled_files_unless_forced() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let unveil = || {
+            crate::api::unveil::prepare()
+                .from_secret_file("tests/images/with_attachment/Blah.txt.png")
+                .into_output_folder(temp_dir.path())
+        };
+
+        unveil().execute().expect("Failed to unveil the first time");
+        let target = temp_dir.path().join("Blah.txt");
+        std::fs::write(&target, b"precious").unwrap();
+
+        match unveil().execute() {
+            Err(crate::SteganoError::OutputExists { path }) => assert_eq!(path, target),
+            result => panic!("Expected OutputExists, but got {result:?}"),
+        }
+        assert_eq!(std::fs::read(&target).unwrap(), b"precious");
+
+        unveil()
+            .overwrite(true)
+            .execute()
+            .expect("Failed to unveil with overwrite");
+        assert_ne!(std::fs::read(&target).unwrap(), b"precious");
+    }
+
+    #[test]
+    fn should_serve_repeated_unveils_from_the_cache() {
+        use super::{unveil_cached, LruUnveilCache};
+
+        let media = std::fs::read("tests/images/encrypted/hello_world.png").unwrap();
+        let options = crate::CodecOptions::default();
+        let mut cache = LruUnveilCache::new(4);
+
+        let first = unveil_cached(&media, Some("Secret42"), &options, &mut cache).unwrap();
+        assert_eq!(cache.hits(), 0);
+        let second = unveil_cached(&media, Some("Secret42"), &options, &mut cache).unwrap();
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(first, second);
+
+        // neither a different password nor different options are served from the cache
+        assert!(unveil_cached(&media, Some("Secret43"), &options, &mut cache).is_err());
+        let other_options = crate::CodecOptions {
+            color_channel_step_increment: 2,
+            ..Default::default()
+        };
+        assert!(unveil_cached(&media, Some("Secret42"), &other_options, &mut cache).is_err());
+        assert_eq!(cache.hits(), 1);
+    }
+
+    #[test]
+    fn should_forget_the_least_recently_used_message() {
+        use super::{LruUnveilCache, UnveilCache};
+
+        let mut cache = LruUnveilCache::new(2);
+        cache.insert([1; 32], crate::Message::empty());
+        cache.insert([2; 32], crate::Message::empty());
+        assert!(cache.get(&[1; 32]).is_some());
+        cache.insert([3; 32], crate::Message::empty());
+
+        assert!(cache.get(&[2; 32]).is_none());
+        assert!(cache.get(&[1; 32]).is_some());
+        assert!(cache.get(&[3; 32]).is_some());
+    }
 }
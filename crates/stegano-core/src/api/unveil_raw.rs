@@ -6,16 +6,14 @@ use std::{
 
 use crate::{
     media::{
-        audio::wav_iter::AudioWavIter,
-        image::LsbCodec,
         payload::{FabA, FabS, PayloadCodecFactory},
         Media,
     },
-    universal_decoder::{OneBitUnveil, UniversalDecoder},
+    message::MESSAGE_HEADER_LEN,
     CodecOptions, RawMessage, SteganoError,
 };
 
-use super::Password;
+use super::{decode_with_password_order, Password};
 
 pub fn prepare() -> UnveilRawApi {
     UnveilRawApi::default()
@@ -27,6 +25,19 @@ pub struct UnveilRawApi {
     destination_file: Option<PathBuf>,
     password: Password,
     options: CodecOptions,
+    framing: RawFraming,
+}
+
+/// Decides how the raw data inside the carrier is framed
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RawFraming {
+    /// the data carries a message header, as written by `hide`
+    #[default]
+    Message,
+    /// the data is a plain bitstream, as written by `SteganoEncoder::hide_raw`
+    Plain,
+    /// the data is a bitstream with a length header, as written by `SteganoEncoder::hide_raw_sized`
+    Sized,
 }
 
 impl UnveilRawApi {
@@ -54,6 +65,20 @@ impl UnveilRawApi {
         self
     }
 
+    /// Reads the carrier as plain bitstream, without expecting any message header.
+    /// This is the counterpart of `SteganoEncoder::hide_raw`, the password is ignored.
+    pub fn as_plain_bitstream(mut self) -> Self {
+        self.framing = RawFraming::Plain;
+        self
+    }
+
+    /// Reads exactly as many bytes as the length header announces, without the capacity padding.
+    /// This is the counterpart of `SteganoEncoder::hide_raw_sized`, the password is ignored.
+    pub fn as_sized_bitstream(mut self) -> Self {
+        self.framing = RawFraming::Sized;
+        self
+    }
+
     /// Set the password used for encrypting all data
     /// If `None` is passed, no password will be used, leads to no de-/encryption used
     pub fn using_password<P: Into<Password>>(mut self, password: P) -> Self {
@@ -71,23 +96,32 @@ impl UnveilRawApi {
         };
 
         let media = Media::from_file(&secret_media)?;
+        if self.framing == RawFraming::Message {
+            media.ensure_capacity(MESSAGE_HEADER_LEN, &self.options)?;
+        }
         let fab: Box<dyn PayloadCodecFactory> = if let Some(password) = self.password.as_ref() {
             Box::new(FabS::new(password))
         } else {
             Box::new(FabA)
         };
 
-        let msg = match media {
-            Media::Image(image) => {
-                let mut decoder = LsbCodec::decoder(&image, &self.options);
-                RawMessage::from_raw_data(&mut decoder, &*fab)?
-            }
-            Media::Audio(audio) => {
-                let mut decoder =
-                    UniversalDecoder::new(AudioWavIter::new(audio.1.into_iter()), OneBitUnveil);
-                RawMessage::from_raw_data(&mut decoder, &*fab)?
-            }
-        };
+        // only framed messages are placed in an order derived from the password
+        let password = self
+            .password
+            .as_ref()
+            .as_deref()
+            .filter(|_| self.framing == RawFraming::Message);
+        let msg = decode_with_password_order(
+            &media,
+            &self.options,
+            password,
+            |options| Ok(media.decoder(options)),
+            |decoder, _| match self.framing {
+                RawFraming::Message => RawMessage::from_raw_data(decoder, &*fab),
+                RawFraming::Plain => RawMessage::of(decoder),
+                RawFraming::Sized => RawMessage::of_sized(decoder),
+            },
+        )?;
 
         let mut destination_file =
             File::create(destination_file).map_err(|source| SteganoError::WriteError { source })?;
@@ -123,4 +157,59 @@ mod tests {
         .expect("Failed to read file");
         assert_eq!(secret_message, "Hello World");
     }
+
+    #[test]
+    fn should_unveil_raw_what_was_hidden_raw() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let image_with_secret = temp_dir.path().join("raw-secret.png");
+        let raw_file = temp_dir.path().join("raw-secret.bin");
+        let pattern: Vec<u8> = (0..=255).cycle().take(1000).collect();
+
+        crate::SteganoEncoder::new()
+            .use_media("tests/images/plain/carrier-image.png")
+            .unwrap()
+            .save_as(&image_with_secret)
+            .hide_raw(&pattern)
+            .expect("Failed to hide raw data");
+
+        crate::api::unveil_raw::prepare()
+            .from_secret_file(&image_with_secret)
+            .as_plain_bitstream()
+            .into_raw_file(&raw_file)
+            .execute()
+            .expect("Failed to unveil raw data");
+
+        let unveiled = std::fs::read(&raw_file).expect("Failed to read raw file");
+        // a plain bitstream has no length, so it spans the whole carrier capacity
+        let (width, height) = image::image_dimensions("tests/images/plain/carrier-image.png")
+            .expect("Failed to read image dimensions");
+        let capacity = ((width - 1) * (height - 1) * 3 / 8) as usize;
+        assert_eq!(unveiled.len(), capacity);
+        assert_eq!(&unveiled[..pattern.len()], pattern.as_slice());
+    }
+
+    #[test]
+    fn should_unveil_raw_sized_exactly_what_was_hidden() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let image_with_secret = temp_dir.path().join("raw-secret.png");
+        let raw_file = temp_dir.path().join("raw-secret.bin");
+        let pattern: Vec<u8> = (0..=255).cycle().take(1000).collect();
+
+        crate::SteganoEncoder::new()
+            .use_media("tests/images/plain/carrier-image.png")
+            .unwrap()
+            .save_as(&image_with_secret)
+            .hide_raw_sized(&pattern)
+            .expect("Failed to hide raw data");
+
+        crate::api::unveil_raw::prepare()
+            .from_secret_file(&image_with_secret)
+            .as_sized_bitstream()
+            .into_raw_file(&raw_file)
+            .execute()
+            .expect("Failed to unveil raw data");
+
+        let unveiled = std::fs::read(&raw_file).expect("Failed to read raw file");
+        assert_eq!(unveiled, pattern);
+    }
 }
@@ -1,7 +1,10 @@
+use hound::WavSpec;
 use std::string::FromUtf8Error;
 use thiserror::Error;
 use zip::result::ZipError;
 
+use crate::media::CarrierSize;
+
 pub use stegano_seasmoke::SeasmokeError;
 
 #[derive(Error, Debug)]
@@ -10,6 +13,32 @@ pub enum SteganoError {
     #[error("Media format is not supported")]
     UnsupportedMedia,
 
+    /// Represents an output format that cannot losslessly hold the carrier, like WAV for an image
+    #[error("The output format {0:?} does not fit the carrier media")]
+    UnsupportedOutputFormat(crate::MediaFormat),
+
+    /// Represents a file whose content contradicts its extension, like a PNG named `.wav`
+    #[error("The file extension `{extension}` does not match the detected format {detected:?}")]
+    FormatMismatch {
+        extension: String,
+        detected: crate::MediaFormat,
+    },
+
+    /// Represents an algorithm that cannot work with the carrier format, like F5 on a PNG
+    #[error("The algorithm {algorithm:?} cannot be used with {format:?} media")]
+    AlgorithmMismatch {
+        algorithm: crate::Algorithm,
+        format: crate::MediaFormat,
+    },
+
+    /// Represents an algorithm that fits the carrier format, but has no implementation yet
+    #[error("The algorithm {0:?} is not supported by this build")]
+    AlgorithmNotSupported(crate::Algorithm),
+
+    /// Represents two media that cannot be compared, because their kind or dimensions differ
+    #[error("Media cannot be compared, kind or dimensions differ")]
+    IncomparableMedia,
+
     /// Represents an invalid carrier audio media. For example, a broken WAV file
     #[error("Audio media is invalid")]
     InvalidAudioMedia,
@@ -30,22 +59,65 @@ pub enum SteganoError {
     #[error("No secret data found")]
     NoSecretData,
 
+    /// Represents a file that was asked for, but is not contained in the secret data
+    #[error("No file with the name `{name}` found in the secret data")]
+    FileNotFound { name: String },
+
     /// Represents an error caused by an invalid filename, for example not unsupported charset or empty filename
     #[error("A file with an invalid file name was provided")]
     InvalidFileName,
 
+    /// Represents an error caused by adding two files with the same name to one message
+    #[error("A file with the name `{name}` was already added")]
+    DuplicateFileName { name: String },
+
+    /// Represents length prefixed data that ended before the announced length was read
+    #[error("Raw data is truncated, expected {0} bytes but only {1} were available")]
+    TruncatedRawData(usize, usize),
+
+    /// Represents raw data that is too large for the 4 byte length header of a sized bitstream
+    #[error("Raw data of {0} bytes is too large for the 4 byte length header")]
+    RawDataTooLarge(usize),
+
+    /// Represents a manifest that cannot be parsed
+    #[error("The manifest is invalid: {0}")]
+    InvalidManifest(#[from] serde_json::Error),
+
+    /// Represents a hidden file whose data ends early, e.g. because the media was truncated.
+    /// The sizes are the ones of the compressed file.
+    #[error(
+        "The hidden file `{file_name}` is truncated, only {got} of {expected} bytes are there"
+    )]
+    TruncatedPayload {
+        file_name: String,
+        expected: usize,
+        got: usize,
+    },
+
     /// Represents an error when interacting with the document message payload
     #[error("Error during the payload processing for documents")]
     PayloadProcessingError(#[from] ZipError),
 
     /// Represents a failure to read from input.
-    #[error("Read error")]
+    #[error("Read error: {source}")]
     ReadError { source: std::io::Error },
 
+    /// Represents an output file that already exists and must not be overwritten
+    #[error("The output file `{}` already exists", path.display())]
+    OutputExists { path: std::path::PathBuf },
+
     /// Represents a failure to write target file.
-    #[error("Write error")]
+    #[error("Write error: {source}")]
     WriteError { source: std::io::Error },
 
+    /// Represents a failure of the filesystem on a known file, the OS error tells the cause,
+    /// e.g. permission denied or a full disk. Data files that cannot be read fail with it too.
+    #[error("Cannot access `{}`: {source}", path.display())]
+    Io {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+
     /// Represents a failure when encoding an audio file.
     #[error("Audio encoding error")]
     AudioEncodingError,
@@ -58,10 +130,15 @@ pub enum SteganoError {
     #[error("Audio creation error")]
     AudioCreationError,
 
-    /// Represents all other cases of `std::io::Error`.
+    /// Represents all other cases of `std::io::Error`, those without a known file,
+    /// e.g. reading a stream. A failure on a known file is an `Io` error instead.
     #[error(transparent)]
     IoError(#[from] std::io::Error),
 
+    /// Represents a failure to derive a key from the password
+    #[error("Key derivation failed")]
+    KeyDerivationFailed(SeasmokeError),
+
     /// Represents an error when encrypting the data
     #[error("Encryption error")]
     EncryptionError(SeasmokeError),
@@ -70,6 +147,71 @@ pub enum SteganoError {
     #[error("Decryption error")]
     DecryptionError(SeasmokeError),
 
+    /// Represents encrypted data that was unveiled without a password
+    #[error("The data is encrypted, a password is required to unveil it")]
+    PasswordRequired,
+
+    /// Represents data decrypted with a password that failed authentication, most likely the password is wrong.
+    /// Malformed data and a key that does not fit are a `DecryptionError` instead
+    #[error("The data is encrypted, but the password is wrong")]
+    WrongPassword,
+
+    /// Represents an encryption for several passwords with none or more than `MAX_PASSWORDS` of them
+    #[error("Encryption needs between 1 and {max} passwords, but got {0}", max = crate::media::payload::MAX_PASSWORDS)]
+    InvalidPasswordCount(usize),
+
+    /// Represents a key file that does not contain exactly 32 bytes of key material
+    #[error("Key file must contain exactly 32 bytes, but contained {0}")]
+    InvalidKeyFile(usize),
+
+    /// Represents a bit plane beyond the 8 bits of a color channel
+    #[error("The bit plane must be between 0 and 7, but got {0}")]
+    InvalidBitPlane(u8),
+
+    /// Represents a palette PNG carrier, it is only used when the palette may be expanded
+    #[error("The carrier is an indexed PNG, the payload would not survive its palette. Expand the palette to use it anyway")]
+    IndexedCarrierUnsupported,
+
+    /// Represents a message that holds more than `CodecOptions::max_extract_bytes` allows
+    #[error("The message claims {claimed} bytes, but at most {limit} bytes may be extracted")]
+    ExtractionLimitExceeded { claimed: usize, limit: usize },
+
+    /// Represents a carrier that cannot even hold the message header
+    #[error("The carrier is too small, it needs to hold at least {needed} bytes but can only hold {available}")]
+    CarrierTooSmall { needed: usize, available: usize },
+
+    /// Represents a payload that needs more than `CodecOptions::max_fill_fraction` of the capacity
+    #[error("The payload needs {needed} bytes, but the fill fraction allows only {available}")]
+    CapacityExceeded { needed: usize, available: usize },
+
+    /// Represents a split secret that does not fit into all the carriers together
+    #[error("The carriers are too small, together they hold {available} of the {needed} bytes")]
+    SplitCarriersTooSmall { needed: usize, available: usize },
+
+    /// Represents a join of a split secret that lacks some of its parts, they are counted from 0
+    #[error("The parts {missing:?} of the split secret are missing")]
+    MissingSplitParts { missing: Vec<u16> },
+
+    /// Represents a join of parts that do not belong to the same split secret
+    #[error("The parts do not belong to the same split secret")]
+    MismatchedSplitParts,
+
+    /// Represents audio carriers of one split secret whose sample specs differ
+    #[error("The audio carriers differ in their sample spec, {first:?} vs. {other:?}")]
+    MismatchedAudioSpecs { first: WavSpec, other: WavSpec },
+
+    /// Represents redundant copies of the hidden data that differ, the media was modified
+    #[error("The {copies} hidden copies of the data differ, the media was modified after hiding")]
+    CopiesDisagree { copies: u8 },
+
+    /// Represents a carrier whose size differs from the one recorded when the data was hidden,
+    /// e.g. because it was cropped or padded since. The hidden bits moved and cannot be read
+    #[error("The carrier was altered after hiding, it had {expected} but has {actual} now")]
+    CarrierAltered {
+        expected: CarrierSize,
+        actual: CarrierSize,
+    },
+
     #[error("No carrier media set")]
     CarrierNotSet,
 
@@ -88,3 +230,44 @@ pub enum SteganoError {
     #[error("API Error: Missing files")]
     MissingFiles,
 }
+
+impl From<SeasmokeError> for SteganoError {
+    fn from(e: SeasmokeError) -> Self {
+        match e {
+            SeasmokeError::KeyDerivationError(_) | SeasmokeError::KeyDerivationParamEarror(_) => {
+                SteganoError::KeyDerivationFailed(e)
+            }
+            SeasmokeError::EncryptionError(_) => SteganoError::EncryptionError(e),
+            // the authentication tag did not match, the key was derived from the wrong password
+            SeasmokeError::DecryptionError(_) => SteganoError::WrongPassword,
+            SeasmokeError::MalformedCiphertext
+            | SeasmokeError::KeyRequired
+            | SeasmokeError::PasswordRequired => SteganoError::DecryptionError(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_map_seasmoke_errors() {
+        assert!(matches!(
+            SteganoError::from(SeasmokeError::DecryptionError(
+                stegano_seasmoke::error::Chacha20Poly1305Error
+            )),
+            SteganoError::WrongPassword
+        ));
+        assert!(matches!(
+            SteganoError::from(SeasmokeError::MalformedCiphertext),
+            SteganoError::DecryptionError(SeasmokeError::MalformedCiphertext)
+        ));
+        assert!(matches!(
+            SteganoError::from(SeasmokeError::KeyDerivationError(
+                stegano_seasmoke::error::Argon2Error::PwdTooLong
+            )),
+            SteganoError::KeyDerivationFailed(_)
+        ));
+    }
+}
@@ -26,10 +26,11 @@
 
 //! # Stegano Core API
 //!
-//! There are 3 main API entry points:
+//! There are 4 main API entry points:
 //! - `hide`
 //! - `unveil`
 //! - `unveil_raw`
+//! - `probe`
 //!
 //! Each of these APIs offer a builder pattern to configure the operation.
 //!
@@ -71,7 +72,9 @@
 extern crate test;
 
 mod error;
+mod manifest;
 mod message;
+mod overwrite;
 mod raw_message;
 mod result;
 mod universal_decoder;
@@ -80,26 +83,55 @@ mod universal_encoder;
 pub(crate) mod media;
 
 pub mod api;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
 
 pub use crate::error::SteganoError;
-pub use crate::media::image::CodecOptions;
+pub use crate::manifest::{Manifest, ManifestEntry, MANIFEST_FILE};
+pub use crate::media::image::{BitOrder, ChannelMask, CodecOptions, Corner, EmbedDirection, Rect};
+pub use crate::media::payload::PayloadVersion;
+pub use crate::media::{
+    Algorithm, CarrierSize, FecConfig, LsbStegoCodec, Media, MediaFormat, StegoCodec,
+    HEADER_KEYSTREAM,
+};
+pub use crate::message::{FileMetadata, Message, MessageBuilder};
+pub use crate::raw_message::RawMessage;
 pub use crate::result::Result;
 
+use std::borrow::Cow;
 use std::default::Default;
-use std::fs::File;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
-use crate::media::payload::{FabA, FabS, PayloadCodecFactory};
-use crate::media::{Media, Persist};
-use crate::message::Message;
-use crate::raw_message::RawMessage;
+use crate::media::image::{is_indexed_png, PngAncillaryChunks};
+use crate::media::payload::{FabA, FabK, FabM, FabS, FabV, PayloadCodecFactory};
+use crate::media::Persist;
+use crate::message::MESSAGE_HEADER_LEN;
+use zeroize::Zeroizing;
 
 pub struct SteganoEncoder {
     options: CodecOptions,
     codec_factory: Box<dyn PayloadCodecFactory>,
+    stego_codec: Box<dyn StegoCodec>,
     target: Option<PathBuf>,
+    /// the file the carrier was loaded from, it is overwritten when `overwrite_source` is set
+    source: Option<PathBuf>,
+    overwrite_source: bool,
     carrier: Option<Media>,
-    message: Message,
+    carrier_chunks: PngAncillaryChunks,
+    /// the carrier was a palette PNG, decoded with its palette expanded to colors
+    indexed_carrier: bool,
+    expand_palette: bool,
+    strip_metadata: bool,
+    watermark: Option<(String, Corner)>,
+    manifest: bool,
+    message: MessageBuilder,
+    zero_padding: bool,
+    /// the positions in an image carrier are shuffled by a seed derived from this password
+    position_password: Option<Zeroizing<String>>,
+    report_psnr: bool,
+    psnr: Option<f64>,
+    output_format: Option<MediaFormat>,
 }
 
 impl Default for SteganoEncoder {
@@ -107,9 +139,23 @@ impl Default for SteganoEncoder {
         Self {
             options: CodecOptions::default(),
             codec_factory: Box::new(FabA),
+            stego_codec: Box::new(LsbStegoCodec),
             target: None,
+            source: None,
+            overwrite_source: false,
             carrier: None,
-            message: Message::empty(),
+            carrier_chunks: PngAncillaryChunks::default(),
+            indexed_carrier: false,
+            expand_palette: false,
+            strip_metadata: false,
+            watermark: None,
+            manifest: false,
+            message: Message::builder(),
+            zero_padding: false,
+            position_password: None,
+            report_psnr: false,
+            psnr: None,
+            output_format: None,
         }
     }
 }
@@ -128,21 +174,164 @@ impl SteganoEncoder {
 
     pub fn use_media(&mut self, input_file: impl AsRef<Path>) -> Result<&mut Self> {
         let path = input_file.as_ref();
-        self.carrier = Some(Media::from_file(path)?);
+        let media = Media::from_file(path)?;
+        if media.looks_transcoded_from_jpeg() {
+            log::warn!(
+                "The carrier {} shows JPEG compression artifacts, that makes it suspicious",
+                path.display()
+            );
+        }
+        self.carrier = Some(media);
+        self.source = Some(path.to_owned());
+        (self.carrier_chunks, self.indexed_carrier) = if is_png(path) {
+            let png = std::fs::read(path).map_err(|source| SteganoError::Io {
+                path: path.to_path_buf(),
+                source,
+            })?;
+            (
+                PngAncillaryChunks::from_png_bytes(&png),
+                is_indexed_png(&png),
+            )
+        } else {
+            (PngAncillaryChunks::default(), false)
+        };
 
         Ok(self)
     }
 
+    /// Uses an already loaded carrier media, e.g. a clone of a media that is shared by many encoders
+    pub fn with_carrier(&mut self, media: Media) -> &mut Self {
+        self.carrier = Some(media);
+        self.source = None;
+        self.carrier_chunks = PngAncillaryChunks::default();
+        self.indexed_carrier = false;
+        self
+    }
+
     pub fn save_as(&mut self, output_file: impl AsRef<Path>) -> &mut Self {
         self.target = Some(output_file.as_ref().to_owned());
         self
     }
 
+    /// Leaves no residual of the carrier file from `use_media`. When the output goes to the carrier
+    /// file itself, it is written to a temporary file first that replaces the carrier with one
+    /// rename, so the carrier is never seen half written. In any case the former carrier content
+    /// is overwritten with noise, a carrier at a separate path is removed afterwards.
+    pub fn overwrite_source(&mut self, overwrite: bool) -> &mut Self {
+        self.overwrite_source = overwrite;
+        self
+    }
+
+    /// Writes the output in this format, no matter what the extension of the target file is.
+    /// It must fit the carrier, PNG or BMP for images and WAV for audio.
+    pub fn with_output_format(&mut self, format: MediaFormat) -> &mut Self {
+        self.output_format = Some(format);
+        self
+    }
+
+    /// Leaves out all metadata of the carrier, like text and EXIF chunks, so that the output
+    /// carries no fingerprints of the original camera or editor. By default they are kept.
+    pub fn strip_metadata(&mut self, strip: bool) -> &mut Self {
+        self.strip_metadata = strip;
+        self
+    }
+
+    /// Burns `text` visibly into a corner of the carrier image before the message is hidden,
+    /// so the hidden bits are placed in the final pixels. The built-in font knows latin letters,
+    /// digits, some punctuation and `©`. Audio carriers cannot be watermarked.
+    pub fn with_watermark(&mut self, text: &str, position: Corner) -> &mut Self {
+        self.watermark = Some((text.to_owned(), position));
+        self
+    }
+
+    /// Hides a `manifest.json` as first file that describes all the other files,
+    /// `api::unveil::read_manifest` reads it back
+    pub fn with_manifest(&mut self, manifest: bool) -> &mut Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// Places the payload bits with this codec instead of the built-in `LsbStegoCodec`,
+    /// the media has to be unveiled with the same codec
+    pub fn with_codec(&mut self, codec: Box<dyn StegoCodec>) -> &mut Self {
+        self.stego_codec = codec;
+        self
+    }
+
+    /// Accepts palette PNG carriers, the payload is hidden in their colors and the output is
+    /// written with full colors. By default they are rejected with
+    /// `SteganoError::IndexedCarrierUnsupported`, because the output cannot be indexed again
+    /// without losing the payload, so it is much larger than the carrier and stands out.
+    pub fn expand_palette(&mut self, expand: bool) -> &mut Self {
+        self.expand_palette = expand;
+        self
+    }
+
+    /// Encrypts the message with the password, for images the password also decides
+    /// in which order the color channels are used
     pub fn with_encryption<S: Into<String>>(&mut self, password: S) -> &mut Self {
-        self.codec_factory = Box::new(FabS::new(password));
+        let password = Zeroizing::new(password.into());
+        self.codec_factory = Box::new(FabS::new(password.as_str()));
+        self.message.encrypted(true);
+        self.position_password = Some(password);
         self
     }
 
+    /// Encrypts the message so that any one of the passwords decrypts it. A random data key
+    /// encrypts the message and is stored once encrypted with each password. As no single
+    /// password decides the order of the color channels, they are used in their linear order.
+    /// At most `MAX_PASSWORDS` passwords are supported.
+    pub fn with_encryption_multi(&mut self, passwords: &[String]) -> &mut Self {
+        self.codec_factory = Box::new(FabM::new(passwords.iter().map(String::as_str)));
+        self.message.encrypted_for_passwords(passwords.len());
+        self.position_password = None;
+        self
+    }
+
+    /// Measures the distortion of the carrier when hiding, it is available via `psnr` afterwards.
+    /// This keeps a copy of the original carrier while hiding.
+    pub fn with_psnr_report(&mut self) -> &mut Self {
+        self.report_psnr = true;
+        self
+    }
+
+    /// The peak signal-to-noise ratio in dB of the last hide, if `with_psnr_report` was set
+    pub fn psnr(&self) -> Option<f64> {
+        self.psnr
+    }
+
+    /// Produces the payload in the given version, for example `PayloadVersion::V2` for images
+    /// that legacy tools can read. Only unencrypted versions with documents are supported.
+    pub fn with_version(&mut self, version: PayloadVersion) -> Result<&mut Self> {
+        self.codec_factory = Box::new(FabV::new(version)?);
+        self.message.encrypted(false);
+        self.position_password = None;
+        // legacy terminated payloads expect the remaining capacity to be zeros
+        self.zero_padding = version == PayloadVersion::V2;
+
+        Ok(self)
+    }
+
+    /// Encrypts with the raw 256-bit key stored in the given file, no password derivation is involved
+    pub fn with_key_file(&mut self, key_file: impl AsRef<Path>) -> Result<&mut Self> {
+        let key_file = key_file.as_ref();
+        let key = zeroize::Zeroizing::new(std::fs::read(key_file).map_err(|source| {
+            SteganoError::Io {
+                path: key_file.to_path_buf(),
+                source,
+            }
+        })?);
+        let key = key
+            .as_slice()
+            .try_into()
+            .map_err(|_| SteganoError::InvalidKeyFile(key.len()))?;
+        self.codec_factory = Box::new(FabK::new(key));
+        self.message.key_encrypted();
+        self.position_password = None;
+
+        Ok(self)
+    }
+
     pub fn add_message(&mut self, msg: &str) -> Result<&mut Self> {
         self.message
             .add_file_data("secret-message.txt", msg.as_bytes().to_vec())?;
@@ -150,17 +339,38 @@ impl SteganoEncoder {
         Ok(self)
     }
 
+    /// Adds the file to the message, errors with `SteganoError::Io` if it cannot be read
     pub fn add_file<P: AsRef<Path> + ?Sized>(&mut self, input_file: &P) -> Result<&mut Self> {
-        {
-            let _f = File::open(input_file).expect("Data file was not readable.");
-        }
         self.message.add_file(input_file)?;
 
         Ok(self)
     }
 
+    /// Adds everything `reader` provides as file `name`, for sources that are no files,
+    /// like a network or a decompression stream. The reader is read until its end.
+    pub fn add_reader(&mut self, name: &str, mut reader: impl Read) -> Result<&mut Self> {
+        let mut data = Vec::new();
+        reader
+            .read_to_end(&mut data)
+            .map_err(|source| SteganoError::ReadError { source })?;
+        self.message.add_file_data(name, data)?;
+
+        Ok(self)
+    }
+
+    /// Adds the file, but stores it under `stored_name` inside the message
+    pub fn add_file_as<P: AsRef<Path> + ?Sized>(
+        &mut self,
+        input_file: &P,
+        stored_name: &str,
+    ) -> Result<&mut Self> {
+        self.message.add_file_as(input_file, stored_name)?;
+
+        Ok(self)
+    }
+
     pub fn add_files<P: AsRef<Path>>(&mut self, input_files: &[P]) -> Result<&mut Self> {
-        self.message.files = Vec::new();
+        self.message.clear();
         for f in input_files.iter() {
             self.add_file(f)?;
         }
@@ -169,34 +379,217 @@ impl SteganoEncoder {
     }
 
     pub fn hide_and_save(&mut self) -> Result<&mut Self> {
-        {
-            // TODO this hack needs to be implemented as well :(
-            // if self.message.header == ContentVersion::V2 {
-            //     space_to_fill -= buf.len();
-            //
-            //     for _ in 0..space_to_fill {
-            //         dec.write_all(&[0])
-            //             .expect("Failed to terminate version 2 content.");
-            //     }
-            // }
-        }
-        if self.carrier.is_none() {
+        let Some(carrier) = self.carrier.as_ref() else {
             return Err(SteganoError::CarrierNotSet);
-        }
+        };
+        self.ensure_exact_carrier()?;
 
         if self.target.is_none() {
             return Err(SteganoError::TargetNotSet);
         }
 
-        if let (Some(media), Some(target)) = (self.carrier.as_mut(), self.target.as_ref()) {
-            let data = self.message.to_raw_data(&*self.codec_factory)?;
-            media
-                .hide_data(data, &self.options)?
-                .save_as(Path::new(target))?;
+        let data = self.message_data(carrier)?;
+        let options = self.message_options()?;
+        self.hide_data_and_save(data, &options)?;
+
+        Ok(self)
+    }
+
+    /// The raw data of the message, checked to fit into the `carrier`
+    /// and padded as the payload version expects it
+    fn message_data(&self, carrier: &Media) -> Result<Vec<u8>> {
+        carrier.ensure_capacity(MESSAGE_HEADER_LEN, &self.options)?;
+        let message = self.message_to_hide()?;
+        // legacy versions are a bit smaller, they are left to the encoding to check
+        if !self.zero_padding {
+            carrier.ensure_capacity(message.current_size(), &self.options)?;
         }
 
+        let mut data = message.message().to_raw_data(&*self.codec_factory)?;
+        if self.zero_padding {
+            let capacity = carrier.capacity_bytes(&self.options);
+            if data.len() < capacity {
+                data.resize(capacity, 0);
+            }
+        }
+
+        Ok(data)
+    }
+
+    /// Hides `data` verbatim, without any message header or encryption, as plain bitstream.
+    /// This is meant for interop with other LSB tools, `unveil_raw` can read it back.
+    pub fn hide_raw(&mut self, data: &[u8]) -> Result<&mut Self> {
+        let options = self.options.clone();
+        self.hide_data_and_save(data.to_vec(), &options)?;
+
         Ok(self)
     }
+
+    /// Like `hide_raw`, but prefixes `data` with a 4 byte big-endian length header,
+    /// so that the sized raw unveil can read back exactly `data` without the capacity padding.
+    pub fn hide_raw_sized(&mut self, data: &[u8]) -> Result<&mut Self> {
+        let mut buf = Vec::with_capacity(data.len() + 4);
+        RawMessage {
+            content: data.to_vec(),
+        }
+        .write_sized_to(&mut buf)?;
+        let options = self.options.clone();
+        self.hide_data_and_save(buf, &options)?;
+
+        Ok(self)
+    }
+
+    fn hide_data_and_save(&mut self, data: Vec<u8>, options: &CodecOptions) -> Result<()> {
+        self.ensure_exact_carrier()?;
+        let Some(media) = self.carrier.as_mut() else {
+            return Err(SteganoError::CarrierNotSet);
+        };
+        let Some(target) = self.target.clone() else {
+            return Err(SteganoError::TargetNotSet);
+        };
+        // a carrier without any capacity, e.g. a 1x1 image, fails here and not deep in the codec
+        let available = media.capacity_bytes(options);
+        if available == 0 && !data.is_empty() {
+            return Err(SteganoError::CarrierTooSmall {
+                needed: data.len(),
+                available,
+            });
+        }
+
+        if let Some((text, corner)) = &self.watermark {
+            media.draw_watermark(text, *corner)?;
+        }
+        let original = self.report_psnr.then(|| media.clone());
+        self.stego_codec.embed(media, &data, options)?;
+        if let Some(original) = original {
+            self.psnr = Some(media.psnr(&original)?);
+        }
+
+        let source = self.source.clone().filter(|_| self.overwrite_source);
+        match source {
+            Some(source) if overwrite::is_same_file(&source, &target) => {
+                let partial = overwrite::partial_path(&target);
+                if let Err(e) = self.write_stego(&partial) {
+                    let _ = std::fs::remove_file(&partial);
+                    return Err(e);
+                }
+                overwrite::replace(&partial, &target)
+            }
+            Some(source) => {
+                self.write_stego(&target)?;
+                overwrite::scrub(&source)
+            }
+            None => self.write_stego(&target),
+        }
+    }
+
+    /// writes the carrier with the hidden data to `target`
+    fn write_stego(&mut self, target: &Path) -> Result<()> {
+        let Some(media) = self.carrier.as_mut() else {
+            return Err(SteganoError::CarrierNotSet);
+        };
+
+        // the image encoder drops all metadata, so the original chunks are copied over
+        if let Some(format) = self.output_format {
+            let mut data = media.to_vec_as(format)?;
+            if format == MediaFormat::Png {
+                data = self.insert_carrier_chunks(data);
+            }
+            std::fs::write(target, data).map_err(|source| SteganoError::Io {
+                path: target.to_path_buf(),
+                source,
+            })
+        } else if media.is_image() && !self.carrier_chunks.is_empty() && is_png(target) {
+            let png = media.to_vec()?;
+            let png = self.insert_carrier_chunks(png);
+            std::fs::write(target, png).map_err(|source| SteganoError::Io {
+                path: target.to_path_buf(),
+                source,
+            })
+        } else {
+            media.save_as(target)
+        }
+    }
+
+    /// the metadata chunks of the carrier go into the freshly encoded PNG, unless they are stripped
+    fn insert_carrier_chunks(&self, png: Vec<u8>) -> Vec<u8> {
+        if self.strip_metadata {
+            png
+        } else {
+            self.carrier_chunks.insert_into(png)
+        }
+    }
+
+    /// Errors if the carrier cannot keep every bit as it is, see `expand_palette`
+    fn ensure_exact_carrier(&self) -> Result<()> {
+        if self.indexed_carrier && !self.expand_palette {
+            return Err(SteganoError::IndexedCarrierUnsupported);
+        }
+
+        Ok(())
+    }
+
+    /// The message as it is hidden, with the manifest in front if one is wanted
+    fn message_to_hide(&self) -> Result<Cow<'_, MessageBuilder>> {
+        if !self.manifest {
+            return Ok(Cow::Borrowed(&self.message));
+        }
+        let message = self.message.message();
+        let mut builder = self.message.clone();
+        builder.with_message(Manifest::of(message).prepend_to(message))?;
+
+        Ok(Cow::Owned(builder))
+    }
+
+    /// The options for hiding a message, with the positions derived from the password if there is one
+    fn message_options(&self) -> Result<CodecOptions> {
+        match (&self.carrier, &self.position_password) {
+            (Some(media), Some(password)) if media.is_image() => {
+                api::with_password_seed(&self.options, password)
+            }
+            _ => Ok(self.options.clone()),
+        }
+    }
+
+    /// Returns the exact number of bytes the message occupies in a carrier, including
+    /// the payload header, the zipped file entries and the encryption overhead.
+    /// This is meant for sizing a carrier upfront instead of guessing an overhead.
+    pub fn required_capacity(&self) -> Result<usize> {
+        self.message_to_hide()?
+            .message()
+            .serialized_size(&*self.codec_factory)
+    }
+
+    /// Hides the message in a copy of the carrier and returns the encoded media bytes,
+    /// the carrier itself stays untouched
+    pub fn hide_to_vec(&self) -> Result<Vec<u8>> {
+        let Some(carrier) = self.carrier.as_ref() else {
+            return Err(SteganoError::CarrierNotSet);
+        };
+        self.ensure_exact_carrier()?;
+
+        let data = self.message_data(carrier)?;
+        let mut media = carrier.clone();
+        if let Some((text, corner)) = &self.watermark {
+            media.draw_watermark(text, *corner)?;
+        }
+        self.stego_codec
+            .embed(&mut media, &data, &self.message_options()?)?;
+
+        match self.output_format {
+            Some(MediaFormat::Png) | None if media.is_image() => {
+                Ok(self.insert_carrier_chunks(media.to_vec()?))
+            }
+            Some(format) => media.to_vec_as(format),
+            None => media.to_vec(),
+        }
+    }
+}
+
+fn is_png(file: &Path) -> bool {
+    file.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
 }
 
 #[cfg(test)]
@@ -206,23 +599,133 @@ mod e2e_tests {
     use api::unveil;
     use media::MediaPrimitiveMut;
     use std::fs;
-    use std::io::Read;
+    use std::fs::File;
     use tempfile::TempDir;
 
     const BASE_IMAGE: &str = "tests/images/Base.png";
 
     #[test]
-    #[should_panic(expected = "Data file was not readable.")]
-    fn should_panic_on_invalid_data_file() {
-        SteganoEncoder::new().add_file("foofile").unwrap();
+    fn should_error_on_invalid_data_file() {
+        match SteganoEncoder::new().add_file("foofile").err() {
+            Some(SteganoError::Io { path, .. }) => {
+                assert_eq!(path, Path::new("foofile"))
+            }
+            err => panic!("Error was not of type Io, but was of {err:?}"),
+        }
     }
 
     #[test]
-    #[should_panic(expected = "Data file was not readable.")]
-    fn should_panic_on_invalid_data_file_among_valid() {
+    fn should_error_on_invalid_data_file_among_valid() {
+        assert!(matches!(
+            SteganoEncoder::new().add_files(&["Cargo.toml", "foofile"]),
+            Err(SteganoError::Io { .. })
+        ));
+    }
+
+    #[test]
+    fn should_hide_a_payload_from_a_reader() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_media = out_dir.path().join("secret.png");
+        let payload = b"streamed from somewhere".to_vec();
+
         SteganoEncoder::new()
-            .add_files(&["Cargo.toml", "foofile"])
-            .unwrap();
+            .use_media("tests/images/plain/carrier-image.png")?
+            .save_as(&secret_media)
+            .add_reader("streamed.txt", std::io::Cursor::new(payload.clone()))?
+            .hide_and_save()?;
+
+        unveil::prepare()
+            .from_secret_file(&secret_media)
+            .into_output_folder(out_dir.path())
+            .execute()?;
+        assert_eq!(fs::read(out_dir.path().join("streamed.txt"))?, payload);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_and_list_files_in_the_order_they_were_hidden() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_media = out_dir.path().join("secret.png");
+        let names = ["c.txt", "a.txt", "b.txt"];
+
+        let mut encoder = SteganoEncoder::new();
+        encoder
+            .use_media("tests/images/plain/carrier-image.png")?
+            .save_as(&secret_media);
+        for name in names {
+            encoder.add_reader(name, name.as_bytes())?;
+        }
+        encoder.hide_and_save()?;
+
+        let listed = unveil::list(&secret_media, None, &CodecOptions::default())?;
+        assert_eq!(
+            listed.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(),
+            names
+        );
+        let written = unveil::prepare()
+            .from_secret_file(&secret_media)
+            .into_output_folder(out_dir.path())
+            .execute()?;
+        assert_eq!(written, names.map(|name| out_dir.path().join(name)));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn should_replace_the_source_in_one_rename_when_hiding_in_place() -> Result<()> {
+        use std::os::unix::fs::MetadataExt;
+
+        let out_dir = TempDir::new()?;
+        let carrier = out_dir.path().join("carrier.png");
+        fs::copy("tests/images/plain/carrier-image.png", &carrier)?;
+        let original = fs::read(&carrier)?;
+        let inode = fs::metadata(&carrier)?.ino();
+
+        SteganoEncoder::new()
+            .use_media(&carrier)?
+            .overwrite_source(true)
+            .save_as(&carrier)
+            .add_message("in place")?
+            .hide_and_save()?;
+
+        // a rename puts a new file in place, truncating and rewriting would keep the old one
+        assert_ne!(fs::metadata(&carrier)?.ino(), inode);
+        assert_ne!(fs::read(&carrier)?, original);
+        let files: Vec<_> = fs::read_dir(out_dir.path())?.collect();
+        assert_eq!(files.len(), 1, "no partial file is left behind");
+
+        unveil::prepare()
+            .from_secret_file(&carrier)
+            .into_output_folder(out_dir.path())
+            .execute()?;
+        assert_eq!(
+            fs::read_to_string(out_dir.path().join("secret-message.txt"))?,
+            "in place"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_scrub_a_source_at_a_separate_path() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let carrier = out_dir.path().join("carrier.png");
+        let secret_media = out_dir.path().join("secret.png");
+        fs::copy("tests/images/plain/carrier-image.png", &carrier)?;
+
+        SteganoEncoder::new()
+            .use_media(&carrier)?
+            .overwrite_source(true)
+            .save_as(&secret_media)
+            .add_message("moved")?
+            .hide_and_save()?;
+
+        assert!(!carrier.exists());
+        assert!(Media::from_file(&secret_media).is_ok());
+
+        Ok(())
     }
 
     #[test]
@@ -230,11 +733,24 @@ mod e2e_tests {
         let mut encoder = SteganoEncoder::new();
         let result = encoder.use_media("some_random_file.png");
         match result.err() {
-            Some(SteganoError::InvalidImageMedia) => (),
+            Some(SteganoError::Io { path, .. }) => {
+                assert_eq!(path, Path::new("some_random_file.png"))
+            }
             _ => panic!(),
         }
     }
 
+    #[test]
+    fn should_report_the_path_of_a_missing_key_file() {
+        let result = SteganoEncoder::new()
+            .with_key_file("no-such.key")
+            .map(|_| ());
+        match result {
+            Err(SteganoError::Io { path, .. }) => assert_eq!(path, Path::new("no-such.key")),
+            result => panic!("Error was not of type Io, but was {result:?}"),
+        }
+    }
+
     #[test]
     fn should_panic_for_invalid_media_file() {
         let mut encoder = SteganoEncoder::new();
@@ -293,6 +809,116 @@ mod e2e_tests {
         Ok(())
     }
 
+    #[test]
+    fn should_hide_and_unveil_in_8_bit_wav() -> Result<()> {
+        assert_hide_and_unveil_in_wav_with_bit_depth(8)
+    }
+
+    #[test]
+    fn should_reject_carriers_without_any_capacity() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image = out_dir.path().join("1x1.png");
+        image::RgbaImage::new(1, 1).save(&image).unwrap();
+        let audio = out_dir.path().join("empty.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        hound::WavWriter::create(&audio, spec)
+            .unwrap()
+            .finalize()
+            .unwrap();
+
+        for (carrier, target) in [(&image, "out.png"), (&audio, "out.wav")] {
+            let target = out_dir.path().join(target);
+            let result = SteganoEncoder::new()
+                .use_media(carrier)?
+                .save_as(&target)
+                .add_message("Hi")?
+                .hide_and_save()
+                .err();
+            assert!(
+                matches!(
+                    result,
+                    Some(SteganoError::CarrierTooSmall {
+                        needed: MESSAGE_HEADER_LEN,
+                        available: 0
+                    })
+                ),
+                "{result:?}"
+            );
+
+            let result = SteganoEncoder::new()
+                .use_media(carrier)?
+                .save_as(&target)
+                .hide_raw(b"Hi")
+                .err();
+            assert!(
+                matches!(
+                    result,
+                    Some(SteganoError::CarrierTooSmall {
+                        needed: 2,
+                        available: 0
+                    })
+                ),
+                "{result:?}"
+            );
+            assert!(!target.exists());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_and_unveil_in_24_bit_wav() -> Result<()> {
+        assert_hide_and_unveil_in_wav_with_bit_depth(24)
+    }
+
+    fn assert_hide_and_unveil_in_wav_with_bit_depth(bits_per_sample: u16) -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let carrier_p = out_dir.path().join("carrier.wav");
+        let secret_media_p = out_dir.path().join("secret.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let amplitude = (1 << (bits_per_sample - 1)) - 1;
+        {
+            let mut writer = hound::WavWriter::create(&carrier_p, spec).unwrap();
+            for t in 0..80_000 {
+                let sample = (t as f32 / 20.0).sin() * amplitude as f32;
+                writer.write_sample(sample as i32).unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+
+        SteganoEncoder::new()
+            .add_file("Cargo.toml")?
+            .use_media(&carrier_p)?
+            .save_as(&secret_media_p)
+            .hide_and_save()?;
+
+        let reader = hound::WavReader::open(&secret_media_p).unwrap();
+        assert_eq!(reader.spec(), spec, "The original spec was not kept");
+
+        unveil::prepare()
+            .from_secret_file(&secret_media_p)
+            .into_output_folder(&out_dir)
+            .execute()?;
+
+        assert_eq_file_content(
+            &out_dir.path().join("Cargo.toml"),
+            "Cargo.toml".as_ref(),
+            "Unveiled data did not match expected",
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn should_hide_and_unveil_one_text_file() -> Result<()> {
         let out_dir = TempDir::new()?;
@@ -481,6 +1107,744 @@ mod e2e_tests {
         Ok(())
     }
 
+    #[test]
+    fn should_hide_independent_payloads_into_clones_of_one_carrier() -> Result<()> {
+        let carrier = Media::from_file(BASE_IMAGE.as_ref())?;
+
+        let mut first = SteganoEncoder::new();
+        first
+            .with_carrier(carrier.clone())
+            .add_message("first payload")?;
+        let mut second = SteganoEncoder::new();
+        second
+            .with_carrier(carrier.clone())
+            .add_message("second payload")?;
+
+        let first_out = first.hide_to_vec()?;
+        let second_out = second.hide_to_vec()?;
+        assert_ne!(first_out, second_out);
+
+        for (out, expected) in [(first_out, "first payload"), (second_out, "second payload")] {
+            let image = image::load_from_memory(&out)
+                .expect("Output was not a valid image")
+                .to_rgba8();
+            let mut decoder = media::image::LsbCodec::decoder(&image, &CodecOptions::default());
+            let msg = Message::from_raw_data(&mut decoder, &FabA)?;
+            assert_eq!(msg.files.len(), 1);
+            assert_eq!(msg.files[0].1, expected.as_bytes());
+        }
+
+        // the shared carrier was never touched
+        let Media::Image(original) = carrier else {
+            panic!("Carrier was not an image");
+        };
+        let reloaded = image::open(BASE_IMAGE).unwrap().to_rgba8();
+        assert_eq!(original, reloaded);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_the_same_bytes_in_memory_as_into_a_file() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        for version in [PayloadVersion::V2, PayloadVersion::V4] {
+            let image_with_secret = out_dir.path().join(format!("{version:?}.png"));
+            let mut encoder = SteganoEncoder::new();
+            encoder
+                .with_version(version)?
+                // noisy pixels, so that the padding of the unused capacity shows
+                .with_carrier(crate::testing::generate_carrier(64, 64, 42))
+                .save_as(&image_with_secret)
+                .add_message("Hello, World!")?;
+
+            let in_memory = encoder.hide_to_vec()?;
+            encoder.hide_and_save()?;
+
+            assert!(
+                fs::read(&image_with_secret)? == in_memory,
+                "{version:?} differs"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_fit_an_encrypted_payload_into_a_carrier_sized_by_required_capacity() -> Result<()> {
+        // hardly compressible data, so that the zip does not shrink it
+        let noise = |seed| crate::testing::noise(seed).take(700).collect::<Vec<_>>();
+        let mut encoder = SteganoEncoder::new();
+        encoder.with_encryption("SuperSecret42");
+        encoder
+            .message
+            .add_file_data("a.bin", noise(1))?
+            .add_file_data("b.bin", noise(2))?;
+
+        let required = encoder.required_capacity()?;
+        assert!(required > 1400);
+
+        // smallest square image that offers the required capacity
+        let opts = CodecOptions::default();
+        let side = (2..)
+            .find(|&side| {
+                Media::Image(image::RgbaImage::new(side, side)).capacity_bytes(&opts) >= required
+            })
+            .unwrap();
+        encoder.with_carrier(Media::Image(image::RgbaImage::new(side, side)));
+        encoder.hide_to_vec()?;
+
+        encoder.with_carrier(Media::Image(image::RgbaImage::new(side - 1, side - 1)));
+        assert!(matches!(
+            encoder.hide_to_vec(),
+            Err(SteganoError::CarrierTooSmall { needed, .. }) if needed == required
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_place_encrypted_data_in_an_order_derived_from_the_password() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret = out_dir.path().join("image-with-secret.png");
+        SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .save_as(&image_with_secret)
+            .with_encryption("SuperSecret42")
+            .add_message("Hello, shuffled World!")?
+            .hide_and_save()?;
+
+        api::unveil::prepare()
+            .from_secret_file(&image_with_secret)
+            .using_password("SuperSecret42")
+            .into_output_folder(out_dir.path())
+            .execute()?;
+        assert_eq!(
+            fs::read_to_string(out_dir.path().join("secret-message.txt"))?,
+            "Hello, shuffled World!"
+        );
+
+        // the right key, but the linear order, it only finds the plain message the base image had
+        let image = image::open(&image_with_secret).unwrap().to_rgba8();
+        let mut decoder = media::image::LsbCodec::decoder(&image, &CodecOptions::default());
+        let linear = Message::from_raw_data(&mut decoder, &FabS::new("SuperSecret42"));
+        assert!(
+            linear.map_or(true, |m| m.text.as_deref()
+                != Some("Hello, shuffled World!")),
+            "the secret was found in the linear order"
+        );
+
+        // the wrong key finds no payload in its order, so the password is ignored for the plain
+        // message the base image still has in the linear order
+        let wrong_dir = TempDir::new()?;
+        let wrong_password = api::unveil::prepare()
+            .from_secret_file(&image_with_secret)
+            .using_password("SuperSecret43")
+            .into_output_folder(wrong_dir.path())
+            .execute();
+        assert!(wrong_password.is_ok(), "{wrong_password:?}");
+        assert_ne!(
+            fs::read_to_string(wrong_dir.path().join("secret-message.txt"))?,
+            "Hello, shuffled World!"
+        );
+
+        // the right order, but the wrong key
+        let wrong_dir = TempDir::new()?;
+        let right_order = CodecOptions {
+            permutation_seed: Some(stegano_seasmoke::derive_seed("SuperSecret42").unwrap()),
+            ..Default::default()
+        };
+        let wrong_password = api::unveil::prepare()
+            .from_secret_file(&image_with_secret)
+            .with_options(right_order)
+            .using_password("SuperSecret43")
+            .into_output_folder(wrong_dir.path())
+            .execute();
+        assert!(
+            matches!(wrong_password, Err(SteganoError::WrongPassword)),
+            "{wrong_password:?}"
+        );
+        assert_eq!(wrong_dir.path().read_dir()?.count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_a_carrier_that_cannot_hold_a_header() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let mut encoder = SteganoEncoder::new();
+        encoder
+            .with_carrier(Media::Image(image::RgbaImage::new(3, 3)))
+            .save_as(out_dir.path().join("out.png"))
+            .add_message("Hi")?;
+
+        assert!(matches!(
+            encoder.hide_and_save(),
+            Err(SteganoError::CarrierTooSmall {
+                needed: 5,
+                available: 1
+            })
+        ));
+        assert!(matches!(
+            encoder.hide_to_vec(),
+            Err(SteganoError::CarrierTooSmall { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_a_synthetic_v1_text_message_as_file() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret = out_dir.path().join("image-with-secret.png");
+        let mut v1 = vec![media::payload::TEXT_ONLY];
+        v1.extend_from_slice(b"Hello from V1");
+        v1.push(0xff);
+        SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .save_as(&image_with_secret)
+            .hide_raw(&v1)?;
+
+        api::unveil::prepare()
+            .from_secret_file(&image_with_secret)
+            .into_output_folder(out_dir.path())
+            .execute()?;
+
+        assert_eq!(
+            fs::read_to_string(out_dir.path().join("secret-message.txt"))?,
+            "Hello from V1"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_keep_a_grayscale_carrier_grayscale() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let carrier = out_dir.path().join("gray.png");
+        let image_with_secret = out_dir.path().join("gray-with-secret.png");
+        image::GrayImage::from_fn(64, 64, |x, y| image::Luma([(x * 4 + y) as u8]))
+            .save(&carrier)
+            .unwrap();
+
+        SteganoEncoder::new()
+            .use_media(&carrier)?
+            .save_as(&image_with_secret)
+            .add_message("Hello, gray World!")?
+            .hide_and_save()?;
+
+        let output = image::open(&image_with_secret).unwrap();
+        assert_eq!(output.color(), image::ColorType::L8);
+
+        api::unveil::prepare()
+            .from_secret_file(&image_with_secret)
+            .into_output_folder(out_dir.path())
+            .execute()?;
+        assert_eq!(
+            fs::read_to_string(out_dir.path().join("secret-message.txt"))?,
+            "Hello, gray World!"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_two_layered_payloads_in_opposite_directions() -> Result<()> {
+        let mut media = Media::from_file(BASE_IMAGE.as_ref())?;
+        let forward = CodecOptions::default();
+        let reverse = CodecOptions {
+            direction: EmbedDirection::Reverse,
+            ..CodecOptions::default()
+        };
+
+        let mut a = Message::empty();
+        a.add_file_data("a.txt", b"payload A".to_vec())?;
+        let mut b = Message::empty();
+        b.add_file_data("b.txt", b"payload B".to_vec())?;
+        media.hide_data(a.to_raw_data(&FabS::new("password A"))?, &forward)?;
+        media.hide_data(b.to_raw_data(&FabS::new("password B"))?, &reverse)?;
+
+        let unveiled_a =
+            Message::from_raw_data(&mut media.decoder(&forward), &FabS::new("password A"))?;
+        assert_eq!(unveiled_a.files, a.files);
+        let unveiled_b =
+            Message::from_raw_data(&mut media.decoder(&reverse), &FabS::new("password B"))?;
+        assert_eq!(unveiled_b.files, b.files);
+        assert!(
+            Message::from_raw_data(&mut media.decoder(&reverse), &FabS::new("password A")).is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_the_psnr_of_a_hide() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let mut encoder = SteganoEncoder::new();
+        encoder
+            .use_media(BASE_IMAGE)?
+            .save_as(out_dir.path().join("image-with-secret.png"))
+            .add_message("Hello, quality control!")?;
+        encoder.hide_and_save()?;
+        assert_eq!(encoder.psnr(), None);
+
+        encoder.with_psnr_report().hide_and_save()?;
+        let psnr = encoder.psnr().expect("PSNR was not reported");
+        assert!(psnr > 50.0, "PSNR was {psnr}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_save_in_the_explicit_output_format() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret = out_dir.path().join("photo");
+        SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .save_as(&image_with_secret)
+            .with_output_format(MediaFormat::Png)
+            .add_message("Hello, no extension!")?
+            .hide_and_save()?;
+
+        let data = fs::read(&image_with_secret)?;
+        let Media::Image(image) = Media::from_bytes(&data, MediaFormat::Png)? else {
+            panic!("Output was not decoded as image");
+        };
+        let mut decoder = media::image::LsbCodec::decoder(&image, &CodecOptions::default());
+        let msg = Message::from_raw_data(&mut decoder, &FabA)?;
+        assert_eq!(msg.files[0].1, b"Hello, no extension!");
+
+        let mut encoder = SteganoEncoder::new();
+        encoder
+            .use_media(BASE_IMAGE)?
+            .save_as(out_dir.path().join("photo.png"))
+            .with_output_format(MediaFormat::Wav)
+            .add_message("Hello, WAV?")?;
+        assert!(matches!(
+            encoder.hide_and_save(),
+            Err(SteganoError::UnsupportedOutputFormat(MediaFormat::Wav))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_error_without_carrier_or_target() -> Result<()> {
+        let mut encoder = SteganoEncoder::new();
+        encoder.add_message("Hello, nobody!")?;
+        assert!(matches!(
+            encoder.hide_and_save(),
+            Err(SteganoError::CarrierNotSet)
+        ));
+        assert!(matches!(
+            encoder.hide_raw(b"raw"),
+            Err(SteganoError::CarrierNotSet)
+        ));
+        assert!(matches!(
+            encoder.hide_to_vec(),
+            Err(SteganoError::CarrierNotSet)
+        ));
+
+        encoder.use_media(BASE_IMAGE)?;
+        assert!(matches!(
+            encoder.hide_and_save(),
+            Err(SteganoError::TargetNotSet)
+        ));
+        assert!(matches!(
+            encoder.hide_raw_sized(b"raw"),
+            Err(SteganoError::TargetNotSet)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_without_knowing_the_step_increment() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret = out_dir.path().join("secret.png");
+
+        SteganoEncoder::with_options(CodecOptions {
+            color_channel_step_increment: 3,
+            ..Default::default()
+        })
+        .use_media(BASE_IMAGE)?
+        .add_message("Hello, step 3!")?
+        .save_as(&image_with_secret)
+        .hide_and_save()?;
+
+        // the default options, the step increment comes from the placement header
+        unveil::prepare()
+            .from_secret_file(&image_with_secret)
+            .into_output_folder(&out_dir)
+            .execute()?;
+        assert_eq!(
+            fs::read_to_string(out_dir.path().join("secret-message.txt"))?,
+            "Hello, step 3!"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_a_manifest_that_describes_the_files() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret = out_dir.path().join("secret.png");
+
+        SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .add_files(&[
+                "tests/images/secrets/Blah.txt",
+                "tests/images/secrets/random_1666_byte.bin",
+            ])?
+            .save_as(&image_with_secret)
+            .with_encryption("secret")
+            .with_manifest(true)
+            .hide_and_save()?;
+
+        let manifest =
+            unveil::read_manifest(&image_with_secret, Some("secret"), &CodecOptions::default())?;
+        assert_eq!(manifest.tool_version, env!("CARGO_PKG_VERSION"));
+        assert!(manifest.created > 0);
+        let blah = fs::read("tests/images/secrets/Blah.txt")?;
+        assert_eq!(
+            manifest.files,
+            [
+                ManifestEntry {
+                    name: "Blah.txt".to_string(),
+                    size: blah.len() as u64,
+                    crc32: crc32fast::hash(&blah),
+                },
+                ManifestEntry {
+                    name: "random_1666_byte.bin".to_string(),
+                    size: 1666,
+                    crc32: crc32fast::hash(&fs::read("tests/images/secrets/random_1666_byte.bin")?),
+                },
+            ]
+        );
+
+        // the manifest is the first file, the others follow unchanged
+        let files = unveil::list(&image_with_secret, Some("secret"), &CodecOptions::default())?;
+        let names: Vec<_> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, [MANIFEST_FILE, "Blah.txt", "random_1666_byte.bin"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_with_any_of_several_passwords() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret = out_dir.path().join("secret.png");
+
+        SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .add_message("Hello, Alice and Bob!")?
+            .save_as(&image_with_secret)
+            .with_encryption_multi(&["alice".to_string(), "bob".to_string()])
+            .hide_and_save()?;
+
+        for password in ["alice", "bob"] {
+            let recipient_dir = out_dir.path().join(password);
+            fs::create_dir(&recipient_dir)?;
+            unveil::prepare()
+                .from_secret_file(&image_with_secret)
+                .using_password(password)
+                .into_output_folder(&recipient_dir)
+                .execute()?;
+            assert_eq!(
+                fs::read_to_string(recipient_dir.join("secret-message.txt"))?,
+                "Hello, Alice and Bob!"
+            );
+        }
+
+        let result = unveil::prepare()
+            .from_secret_file(&image_with_secret)
+            .using_password("eve")
+            .into_output_folder(&out_dir)
+            .execute();
+        assert!(
+            matches!(result, Err(SteganoError::WrongPassword)),
+            "{result:?}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_indexed_carriers_unless_the_palette_is_expanded() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let indexed_carrier = out_dir.path().join("indexed.png");
+        let image_with_secret = out_dir.path().join("secret.png");
+
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, 64, 64);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_palette(
+            (0..=255u8)
+                .flat_map(|i| [i, 255 - i, i / 2])
+                .collect::<Vec<_>>(),
+        );
+        let pixels: Vec<u8> = (0..64 * 64).map(|i| (i * 7 % 256) as u8).collect();
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&pixels))
+            .unwrap();
+        fs::write(&indexed_carrier, png)?;
+
+        let mut encoder = SteganoEncoder::new();
+        encoder
+            .use_media(&indexed_carrier)?
+            .add_message("Hello, palette!")?
+            .save_as(&image_with_secret);
+        assert!(matches!(
+            encoder.hide_and_save(),
+            Err(SteganoError::IndexedCarrierUnsupported)
+        ));
+        assert!(!image_with_secret.exists());
+
+        encoder.expand_palette(true).hide_and_save()?;
+        unveil::prepare()
+            .from_secret_file(&image_with_secret)
+            .into_output_folder(&out_dir)
+            .execute()?;
+        assert_eq!(
+            fs::read_to_string(out_dir.path().join("secret-message.txt"))?,
+            "Hello, palette!"
+        );
+
+        Ok(())
+    }
+
+    /// hides the bits in the red channel of every other pixel only
+    struct EveryOtherPixel;
+
+    impl StegoCodec for EveryOtherPixel {
+        fn embed(&self, media: &mut Media, data: &[u8], _opts: &CodecOptions) -> Result<()> {
+            let Media::Image(image) = media else {
+                return Err(SteganoError::UnsupportedMedia);
+            };
+            let available = image.pixels().len() / 2 / 8;
+            if data.len() > available {
+                return Err(SteganoError::CarrierTooSmall {
+                    needed: data.len(),
+                    available,
+                });
+            }
+            let bits = data
+                .iter()
+                .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1));
+            for (pixel, bit) in image.pixels_mut().step_by(2).zip(bits) {
+                pixel.0[0] = (pixel.0[0] & !1) | bit;
+            }
+
+            Ok(())
+        }
+
+        fn extract(&self, media: &Media, _opts: &CodecOptions) -> Result<Vec<u8>> {
+            let Media::Image(image) = media else {
+                return Err(SteganoError::UnsupportedMedia);
+            };
+            let bits: Vec<u8> = image.pixels().step_by(2).map(|p| p.0[0] & 1).collect();
+
+            Ok(bits
+                .chunks_exact(8)
+                .map(|bits| bits.iter().fold(0, |byte, bit| byte << 1 | bit))
+                .collect())
+        }
+    }
+
+    #[test]
+    fn should_hide_and_unveil_with_a_custom_codec() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret = out_dir.path().join("secret.png");
+
+        SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .add_message("Hello, every other pixel!")?
+            .save_as(&image_with_secret)
+            .with_codec(Box::new(EveryOtherPixel))
+            .hide_and_save()?;
+
+        // the odd pixels stay untouched
+        let carrier = image::open(BASE_IMAGE).unwrap().to_rgba8();
+        let stego = image::open(&image_with_secret).unwrap().to_rgba8();
+        assert!(carrier
+            .pixels()
+            .zip(stego.pixels())
+            .skip(1)
+            .step_by(2)
+            .all(|(a, b)| a == b));
+
+        unveil::prepare()
+            .from_secret_file(&image_with_secret)
+            .into_output_folder(&out_dir)
+            .with_codec(Box::new(EveryOtherPixel))
+            .execute()?;
+        assert_eq!(
+            fs::read_to_string(out_dir.path().join("secret-message.txt"))?,
+            "Hello, every other pixel!"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_burn_in_a_watermark_that_survives_the_hidden_message() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret = out_dir.path().join("secret.png");
+
+        SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .add_message("Hello, watermark!")?
+            .save_as(&image_with_secret)
+            .with_watermark("© Stegano", Corner::BottomRight)
+            .hide_and_save()?;
+
+        let original = image::open(BASE_IMAGE).unwrap().to_rgba8();
+        let mut watermarked = original.clone();
+        media::image::draw_watermark(&mut watermarked, "© Stegano", Corner::BottomRight);
+        let stego = image::open(&image_with_secret).unwrap().to_rgba8();
+
+        let mut watermark_pixels = 0;
+        for (x, y, expected) in watermarked.enumerate_pixels() {
+            if expected == original.get_pixel(x, y) {
+                continue;
+            }
+            watermark_pixels += 1;
+            // at most the hidden bit differs, that is no visible difference
+            let given = stego.get_pixel(x, y);
+            assert!(
+                expected
+                    .0
+                    .iter()
+                    .zip(given.0)
+                    .all(|(e, g)| e.abs_diff(g) <= 1),
+                "the watermark at ({x}, {y}) changed from {expected:?} to {given:?}"
+            );
+        }
+        assert!(
+            watermark_pixels > 50,
+            "only {watermark_pixels} watermark pixels"
+        );
+
+        unveil::prepare()
+            .from_secret_file(&image_with_secret)
+            .into_output_folder(&out_dir)
+            .execute()?;
+        assert_eq!(
+            fs::read_to_string(out_dir.path().join("secret-message.txt"))?,
+            "Hello, watermark!"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_keep_the_os_error_when_saving_fails() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let unwritable = out_dir.path().join("missing-dir").join("secret.png");
+
+        let result = SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .add_message("Hello, nobody!")?
+            .save_as(&unwritable)
+            .hide_and_save()
+            .map(|_| ());
+
+        match result {
+            Err(e @ SteganoError::Io { .. }) => {
+                assert!(e.to_string().contains("missing-dir"), "{e}");
+                let SteganoError::Io { path, source } = e else {
+                    unreachable!()
+                };
+                assert_eq!(path, unwritable);
+                assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+            }
+            other => panic!("expected an Io error, got {other:?}"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_strip_text_and_exif_chunks_of_the_carrier() -> Result<()> {
+        use crate::media::image::PngAncillaryChunks;
+
+        let out_dir = TempDir::new()?;
+        let carrier_with_metadata = out_dir.path().join("carrier-with-metadata.png");
+        let image_with_secret = out_dir.path().join("secret.png");
+
+        let base = fs::read(BASE_IMAGE)?;
+        let mut with_metadata = base[..33].to_vec();
+        with_metadata.extend(crate::test_utils::png_chunk(b"tEXt", b"Camera\0Model X"));
+        with_metadata.extend(crate::test_utils::png_chunk(
+            b"eXIf",
+            b"MM\0*\0\0\0\x08\0\0",
+        ));
+        with_metadata.extend_from_slice(&base[33..]);
+        fs::write(&carrier_with_metadata, with_metadata)?;
+
+        SteganoEncoder::new()
+            .use_media(&carrier_with_metadata)?
+            .add_message("Hello, nobody!")?
+            .save_as(&image_with_secret)
+            .strip_metadata(true)
+            .hide_and_save()?;
+
+        let output = fs::read(&image_with_secret)?;
+        assert!(PngAncillaryChunks::from_png_bytes(&output).is_empty());
+        assert!(!output.windows(4).any(|w| w == b"tEXt" || w == b"eXIf"));
+
+        unveil::prepare()
+            .from_secret_file(&image_with_secret)
+            .into_output_folder(&out_dir)
+            .execute()?;
+        assert_eq!(
+            fs::read_to_string(out_dir.path().join("secret-message.txt"))?,
+            "Hello, nobody!"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_preserve_exif_chunk_of_the_carrier() -> Result<()> {
+        use crate::media::image::PngAncillaryChunks;
+
+        let out_dir = TempDir::new()?;
+        let carrier_with_exif = out_dir.path().join("carrier-with-exif.png");
+        let image_with_secret = out_dir.path().join("secret.png");
+
+        let exif = crate::test_utils::png_chunk(b"eXIf", b"MM\0*\0\0\0\x08\0\0");
+        let base = fs::read(BASE_IMAGE)?;
+        let mut with_exif = base[..33].to_vec();
+        with_exif.extend_from_slice(&exif);
+        with_exif.extend_from_slice(&base[33..]);
+        fs::write(&carrier_with_exif, with_exif)?;
+
+        SteganoEncoder::new()
+            .use_media(&carrier_with_exif)?
+            .add_message("Hello, EXIF!")?
+            .save_as(&image_with_secret)
+            .hide_and_save()?;
+
+        let output = fs::read(&image_with_secret)?;
+        assert_eq!(
+            PngAncillaryChunks::from_png_bytes(&output),
+            PngAncillaryChunks::from_png_bytes(&fs::read(&carrier_with_exif)?)
+        );
+        assert!(output.windows(exif.len()).any(|w| w == exif));
+
+        unveil::prepare()
+            .from_secret_file(&image_with_secret)
+            .into_output_folder(&out_dir)
+            .execute()?;
+        assert_eq!(
+            fs::read_to_string(out_dir.path().join("secret-message.txt"))?,
+            "Hello, EXIF!"
+        );
+
+        Ok(())
+    }
+
     // TODO test for hide_message
 
     fn assert_eq_file_content(file1: &Path, file2: &Path, msg: &str) {
@@ -513,6 +1877,17 @@ mod test_utils {
 
     pub const HELLO_WORLD_PNG: &str = "tests/images/with_text/hello_world.png";
 
+    /// builds a valid PNG chunk including its crc
+    pub fn png_chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = Vec::with_capacity(data.len() + 12);
+        chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        chunk.extend_from_slice(chunk_type);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&crc32fast::hash(&chunk[4..]).to_be_bytes());
+
+        chunk
+    }
+
     /// This image has some traits:
     /// --------------y-------------
     /// | 0,0 -> (0, 1, 2, 3 ) | 0,1 -> (4, 5, 6, 7 ) | ...
@@ -0,0 +1,84 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::result::Result;
+use crate::{Message, SteganoError};
+
+/// the well-known name of the manifest, it is the first file of a message
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// A self-describing summary of the files of a message, hidden as `manifest.json` in front
+/// of them, so that a reader can tell what the message holds without guessing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// the version of stegano-core that hid the message
+    pub tool_version: String,
+    /// the time of hiding, in seconds since the unix epoch
+    pub created: u64,
+    pub files: Vec<ManifestEntry>,
+}
+
+/// One file as the manifest describes it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    /// the size in bytes
+    pub size: u64,
+    /// the CRC-32 of the content
+    pub crc32: u32,
+}
+
+impl Manifest {
+    /// Describes the files of `message`, a manifest that is already among them is left out
+    pub fn of(message: &Message) -> Self {
+        Self {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            files: message
+                .files
+                .iter()
+                .filter(|(name, _)| name != MANIFEST_FILE)
+                .map(|(name, data)| ManifestEntry {
+                    name: name.clone(),
+                    size: data.len() as u64,
+                    crc32: crc32fast::hash(data),
+                })
+                .collect(),
+        }
+    }
+
+    /// Finds and parses the manifest among the files of `message`
+    pub fn from_message(message: &Message) -> Result<Self> {
+        let (_, data) = message
+            .files
+            .iter()
+            .find(|(name, _)| name == MANIFEST_FILE)
+            .ok_or_else(|| SteganoError::FileNotFound {
+                name: MANIFEST_FILE.to_string(),
+            })?;
+
+        Ok(serde_json::from_slice(data)?)
+    }
+
+    pub fn to_json(&self) -> Vec<u8> {
+        // a struct of strings and numbers always serializes
+        serde_json::to_vec_pretty(self).expect("manifest is serializable")
+    }
+
+    /// A copy of `message` with this manifest as the first file, an older manifest is replaced
+    pub(crate) fn prepend_to(&self, message: &Message) -> Message {
+        let mut with_manifest = message.clone();
+        with_manifest
+            .files
+            .retain(|(name, _)| name != MANIFEST_FILE);
+        with_manifest
+            .files
+            .insert(0, (MANIFEST_FILE.to_string(), self.to_json()));
+
+        with_manifest
+    }
+}
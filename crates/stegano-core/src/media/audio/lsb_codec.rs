@@ -1,38 +1,78 @@
 use std::io::{Read, Write};
 
-use super::wav_iter::AudioWavIterMut;
-use crate::media::MediaPrimitive;
+use super::wav_iter::{AudioWavIter, AudioWavIterMut};
+use crate::media::WavSample;
 use crate::universal_decoder::{OneBitUnveil, UniversalDecoder};
 use crate::universal_encoder::{OneBitHide, UniversalEncoder};
+use crate::ChannelMask;
 
 /// Factory for decoder and encoder
 pub struct LsbCodec;
 
 impl LsbCodec {
-    /// builds a LSB Audio Decoder that implements Read
-    pub fn decoder<'i>(input: &'i [i16]) -> Box<dyn Read + 'i> {
+    /// builds a LSB Audio Decoder that implements Read, it reads only every `step`th sample of
+    /// the channels in `mask`, `channels` is the number of interleaved channels
+    pub fn decoder<'i, S: WavSample>(
+        input: &'i [S],
+        channels: u16,
+        mask: ChannelMask,
+        step: u32,
+    ) -> Box<dyn Read + 'i> {
         Box::new(UniversalDecoder::new(
-            input
-                .iter()
-                .map(Clone::clone)
-                .map(MediaPrimitive::AudioSample),
+            AudioWavIter::new(carrying::<_, S>(
+                input.iter().copied(),
+                channels,
+                mask,
+                step,
+            )),
             OneBitUnveil,
         ))
     }
 
-    /// builds a LSB Audio Encoder that implements Write
-    pub fn encoder<'i>(input: &'i mut [i16]) -> Box<dyn Write + 'i> {
+    /// builds a LSB Audio Encoder that implements Write, it uses only every `step`th sample of
+    /// the channels in `mask`, `channels` is the number of interleaved channels
+    pub fn encoder<'i, S: WavSample>(
+        input: &'i mut [S],
+        channels: u16,
+        mask: ChannelMask,
+        step: u32,
+    ) -> Box<dyn Write + 'i> {
         Box::new(UniversalEncoder::new(
-            AudioWavIterMut::new(input.iter_mut()),
+            AudioWavIterMut::new(carrying::<_, S>(input.iter_mut(), channels, mask, step)),
             OneBitHide,
         ))
     }
 }
 
+/// keeps only the interleaved samples of the channels in `mask`, and of those every `step`th
+pub(crate) fn selected<T>(
+    samples: impl Iterator<Item = T>,
+    channels: u16,
+    mask: ChannelMask,
+    step: u32,
+) -> impl Iterator<Item = T> {
+    let channels = channels.max(1) as usize;
+    samples
+        .enumerate()
+        .filter(move |(i, _)| mask.contains(i % channels))
+        .map(|(_, sample)| sample)
+        .step_by(step.max(1) as usize)
+}
+
+/// the selected samples that can carry a bit, see `WavSample::can_carry`
+pub(crate) fn carrying<T: std::borrow::Borrow<S>, S: WavSample>(
+    samples: impl Iterator<Item = T>,
+    channels: u16,
+    mask: ChannelMask,
+    step: u32,
+) -> impl Iterator<Item = T> {
+    selected(samples, channels, mask, step).filter(|sample| sample.borrow().can_carry())
+}
+
 #[cfg(feature = "benchmarks")]
 #[allow(unused_imports)] // clippy false positive, on nightly when `cargo bench` is called
 mod benchmarks {
-    use super::LsbCodec;
+    use super::{ChannelMask, LsbCodec};
     use crate::media::WavReader;
 
     /// Benchmark for audio decoding
@@ -40,11 +80,11 @@ mod benchmarks {
     pub fn audio_decoding(b: &mut test::Bencher) {
         let mut reader = WavReader::open("tests/audio/secrets/audio-with-secrets.wav")
             .expect("Cannot create reader");
-        let samples = reader.samples().map(|s| s.unwrap()).collect::<Vec<i16>>();
+        let samples = reader.samples().map(|s| s.unwrap()).collect::<Vec<i32>>();
         let mut buf = [0; 12];
 
         b.iter(|| {
-            LsbCodec::decoder(&samples)
+            LsbCodec::decoder(&samples, 1, ChannelMask::ALL, 1)
                 .read_exact(&mut buf)
                 .expect("Cannot read 12 bytes from decoder");
         })
@@ -55,11 +95,11 @@ mod benchmarks {
     pub fn audio_encoding(b: &mut test::Bencher) {
         let mut reader =
             WavReader::open("tests/audio/plain/carrier-audio.wav").expect("Cannot create reader");
-        let mut samples = reader.samples().map(|s| s.unwrap()).collect::<Vec<i16>>();
+        let mut samples = reader.samples().map(|s| s.unwrap()).collect::<Vec<i32>>();
         let secret_message = b"Hello World!";
 
         b.iter(|| {
-            LsbCodec::encoder(&mut samples)
+            LsbCodec::encoder(&mut samples, 1, ChannelMask::ALL, 1)
                 .write_all(&secret_message[..])
                 .expect("Cannot write to codec");
         })
@@ -77,7 +117,7 @@ mod tests {
     const SOME_WAV: &str = "tests/audio/plain/carrier-audio.wav";
 
     /// convenient wrapper for `WavReader::open`
-    fn read_samples(file: &Path) -> (Vec<i16>, WavSpec) {
+    fn read_samples(file: &Path) -> (Vec<i32>, WavSpec) {
         let mut reader = WavReader::open(file).expect("Cannot create reader");
         (
             reader.samples().map(|s| s.unwrap()).collect(),
@@ -95,7 +135,7 @@ mod tests {
         let secret_to_hide = secret_to_hide_origin.clone();
         let (mut samples, spec) = read_samples(SOME_WAV.as_ref());
         {
-            let mut codec = LsbCodec::encoder(&mut samples);
+            let mut codec = LsbCodec::encoder(&mut samples, 1, ChannelMask::ALL, 1);
             let half_the_buffer = secret_to_hide.len() / 2;
             codec
                 .write_all(&secret_to_hide[..half_the_buffer])
@@ -115,8 +155,8 @@ mod tests {
 
         let mut reader =
             WavReader::open(audio_with_secret).expect("carrier audio file was not readable");
-        let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
-        let mut codec = LsbCodec::decoder(&samples);
+        let samples: Vec<i32> = reader.samples().map(|s| s.unwrap()).collect();
+        let mut codec = LsbCodec::decoder(&samples, 1, ChannelMask::ALL, 1);
         let mut unveiled_secret = Vec::new();
         let total_read = codec
             .read_to_end(&mut unveiled_secret)
@@ -131,4 +171,81 @@ mod tests {
             String::from_utf8(unveiled_secret)
         );
     }
+
+    #[test]
+    fn should_only_hide_in_the_selected_channel() {
+        // interleaved stereo, left and right channel alternate
+        let original: Vec<i32> = (0..2000).map(|i| (i * 37 % 1000) - 500).collect();
+        let mut samples = original.clone();
+        let left = ChannelMask::only(0);
+
+        LsbCodec::encoder(&mut samples, 2, left, 1)
+            .write_all(b"Hello World!")
+            .expect("Cannot write to codec");
+
+        for (sample, original) in samples.iter().zip(&original).skip(1).step_by(2) {
+            assert_eq!(sample, original, "the right channel must stay untouched");
+        }
+        assert_ne!(samples, original);
+
+        let mut buf = vec![0; 12];
+        LsbCodec::decoder(&samples, 2, left, 1)
+            .read_exact(&mut buf)
+            .expect("Cannot read 12 bytes from codec");
+        assert_eq!(buf, b"Hello World!");
+
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let opts = crate::CodecOptions {
+            audio_channels: left,
+            ..Default::default()
+        };
+        let media = crate::media::Media::Audio((spec, samples));
+        assert_eq!(media.capacity_bytes(&opts), 1000 / 8);
+        assert_eq!(media.capacity_bytes(&Default::default()), 2000 / 8);
+    }
+
+    #[test]
+    fn should_only_hide_in_every_nth_sample() {
+        let original: Vec<i32> = (0..2000).map(|i| (i * 37 % 1000) - 500).collect();
+        let mut samples = original.clone();
+
+        LsbCodec::encoder(&mut samples, 1, ChannelMask::ALL, 4)
+            .write_all(b"Hello World!")
+            .expect("Cannot write to codec");
+
+        for (i, (sample, original)) in samples.iter().zip(&original).enumerate() {
+            if i % 4 != 0 {
+                assert_eq!(sample, original, "sample {i} must stay untouched");
+            }
+        }
+        assert_ne!(samples, original);
+
+        let mut buf = vec![0; 12];
+        LsbCodec::decoder(&samples, 1, ChannelMask::ALL, 4)
+            .read_exact(&mut buf)
+            .expect("Cannot read 12 bytes from codec");
+        assert_eq!(buf, b"Hello World!");
+
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let opts = crate::CodecOptions {
+            audio_sample_step: 4,
+            ..Default::default()
+        };
+        let mut media = crate::media::Media::Audio((spec, original));
+        assert_eq!(media.capacity_bytes(&opts), 500 / 8);
+        media.hide_data(b"Hello World!".to_vec(), &opts).unwrap();
+        let mut buf = vec![0; 12];
+        media.decoder(&opts).read_exact(&mut buf).unwrap();
+        assert_eq!(buf, b"Hello World!");
+    }
 }
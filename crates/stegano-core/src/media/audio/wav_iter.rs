@@ -1,6 +1,4 @@
-use std::slice::IterMut;
-
-use crate::media::{MediaPrimitive, MediaPrimitiveMut};
+use crate::media::{MediaPrimitive, MediaPrimitiveMut, WavSample};
 
 /// Iterating wav audio samples, based on `WavReader` by `hound` crate
 pub struct AudioWavIter<T> {
@@ -9,7 +7,8 @@ pub struct AudioWavIter<T> {
 
 impl<T> AudioWavIter<T>
 where
-    T: Iterator<Item = i16>,
+    T: Iterator,
+    T::Item: WavSample,
 {
     pub fn new(samples: T) -> Self {
         Self { samples }
@@ -19,32 +18,41 @@ where
 /// Audio samples iterator that yields immutable MediaPrimitives `MediaPrimitive`
 impl<T> Iterator for AudioWavIter<T>
 where
-    T: Iterator<Item = i16>,
+    T: Iterator,
+    T::Item: WavSample,
 {
     type Item = MediaPrimitive;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.samples.next().map(MediaPrimitive::AudioSample)
+        self.samples.next().map(WavSample::primitive)
     }
 }
 
 /// Iterating mutable wav audio samples, based on `WavReader` by `hound` crate
-pub struct AudioWavIterMut<'a, T> {
-    samples: IterMut<'a, T>,
+pub struct AudioWavIterMut<T> {
+    samples: T,
 }
 
-impl<'a> AudioWavIterMut<'a, i16> {
-    pub fn new(samples: IterMut<'a, i16>) -> Self {
+impl<'a, T, S> AudioWavIterMut<T>
+where
+    T: Iterator<Item = &'a mut S>,
+    S: WavSample + 'a,
+{
+    pub fn new(samples: T) -> Self {
         Self { samples }
     }
 }
 
 /// Audio samples iterator that yields mutable MediaPrimitives `MediaPrimitiveMut`
-impl<'a> Iterator for AudioWavIterMut<'a, i16> {
+impl<'a, T, S> Iterator for AudioWavIterMut<T>
+where
+    T: Iterator<Item = &'a mut S>,
+    S: WavSample + 'a,
+{
     type Item = MediaPrimitiveMut<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.samples.next().map(MediaPrimitiveMut::AudioSample)
+        self.samples.next().map(WavSample::primitive_mut)
     }
 }
 
@@ -58,7 +66,7 @@ mod tests {
     use super::*;
 
     /// convenient wrapper for `WavReader::open`
-    fn read_samples(file: &Path) -> (Vec<i16>, WavSpec) {
+    fn read_samples(file: &Path) -> (Vec<i32>, WavSpec) {
         let mut reader = WavReader::open(file).expect("Cannot create reader");
         (
             reader.samples().map(|s| s.unwrap()).collect(),
@@ -75,7 +83,7 @@ mod tests {
 
         // create a `Decoder` based on a Iterator
         UniversalDecoder::new(
-            AudioWavIter::new(reader.samples::<i16>().map(|s| s.unwrap())),
+            AudioWavIter::new(reader.samples::<i32>().map(|s| s.unwrap())),
             OneBitUnveil,
         )
         .read_exact(&mut secret)
@@ -90,7 +98,7 @@ mod tests {
         let secret_file = Path::new("tests/audio/secrets/audio-with-secrets.wav");
         let mut secret = vec![0; 12];
 
-        // collect all samples in a Vec<i16>
+        // collect all samples in a Vec<i32>
         let (samples, _) = read_samples(secret_file);
 
         UniversalDecoder::new(AudioWavIter::new(samples.into_iter()), OneBitUnveil)
@@ -0,0 +1,131 @@
+//! Records the size of the carrier in front of the hidden bits, see [`crate::CodecOptions::record_carrier_size`].
+//!
+//! The frame is a flag byte with only the upper bit set, no payload version and no FEC frame
+//! starts like that, followed by a short magic and the size as big endian:
+//! - for images the width and the height as `u32`
+//! - for audio the number of samples as `u64`
+//!
+//! The frame lies in the first color channels, column by column they are the top of the first
+//! column. Those survive a crop at the right or at the bottom, so unveiling can tell that the
+//! carrier was altered. A scaled carrier loses the frame like everything else.
+
+use byteorder::{BigEndian, ByteOrder};
+use std::fmt::{Display, Formatter};
+use std::io::{Cursor, Read};
+
+const SIZE_FLAG: u8 = 0b1000_0000;
+const SIZE_MAGIC: [u8; 2] = *b"Sz";
+const PREFIX_LEN: usize = 1 + SIZE_MAGIC.len();
+/// the bytes the frame takes in front of the payload
+pub(crate) const HEADER_LEN: usize = PREFIX_LEN + 8;
+
+/// The size of a carrier, as the frame records it
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CarrierSize {
+    Pixels { width: u32, height: u32 },
+    Samples(u64),
+}
+
+impl Display for CarrierSize {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CarrierSize::Pixels { width, height } => write!(f, "{width}x{height} pixels"),
+            CarrierSize::Samples(samples) => write!(f, "{samples} samples"),
+        }
+    }
+}
+
+impl CarrierSize {
+    /// the 8 bytes of the frame that hold the size
+    fn to_bytes(self) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        match self {
+            CarrierSize::Pixels { width, height } => {
+                BigEndian::write_u32(&mut bytes[..4], width);
+                BigEndian::write_u32(&mut bytes[4..], height);
+            }
+            CarrierSize::Samples(samples) => BigEndian::write_u64(&mut bytes, samples),
+        }
+
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8], like: CarrierSize) -> Self {
+        match like {
+            CarrierSize::Pixels { .. } => CarrierSize::Pixels {
+                width: BigEndian::read_u32(&bytes[..4]),
+                height: BigEndian::read_u32(&bytes[4..]),
+            },
+            CarrierSize::Samples(_) => CarrierSize::Samples(BigEndian::read_u64(bytes)),
+        }
+    }
+}
+
+/// Puts the frame with the size of the carrier in front of the data
+pub(crate) fn encode(size: CarrierSize, data: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(HEADER_LEN + data.len());
+    frame.push(SIZE_FLAG);
+    frame.extend_from_slice(&SIZE_MAGIC);
+    frame.extend_from_slice(&size.to_bytes());
+    frame.extend_from_slice(data);
+
+    frame
+}
+
+/// The size the frame at the start of `raw` recorded, `None` if there is no frame.
+/// `like` is the size of the carrier now, it tells if pixels or samples were recorded.
+pub(crate) fn recorded_size(raw: &mut dyn Read, like: CarrierSize) -> Option<CarrierSize> {
+    let mut header = [0; HEADER_LEN];
+    raw.read_exact(&mut header).ok()?;
+    if header[0] != SIZE_FLAG || header[1..PREFIX_LEN] != SIZE_MAGIC {
+        return None;
+    }
+
+    Some(CarrierSize::from_bytes(&header[PREFIX_LEN..], like))
+}
+
+/// Skips the frame if there is one, any other data is passed on untouched
+pub(crate) fn decoder<'a>(mut raw: Box<dyn Read + 'a>) -> Box<dyn Read + 'a> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    if raw
+        .by_ref()
+        .take(HEADER_LEN as u64)
+        .read_to_end(&mut header)
+        .is_err()
+    {
+        return raw;
+    }
+    if header.len() == HEADER_LEN && header[0] == SIZE_FLAG && header[1..PREFIX_LEN] == SIZE_MAGIC {
+        return raw;
+    }
+
+    Box::new(Cursor::new(header).chain(raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_skip_the_frame_and_pass_anything_else_on() {
+        let size = CarrierSize::Pixels {
+            width: 640,
+            height: 480,
+        };
+        let framed = encode(size, b"payload");
+        assert_eq!(recorded_size(&mut framed.as_slice(), size), Some(size));
+
+        let mut data = Vec::new();
+        decoder(Box::new(Cursor::new(framed)))
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"payload");
+
+        let mut data = Vec::new();
+        decoder(Box::new(Cursor::new(b"\x80 no frame".to_vec())))
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"\x80 no frame");
+        assert_eq!(recorded_size(&mut &b"\x80 no frame"[..], size), None);
+    }
+}
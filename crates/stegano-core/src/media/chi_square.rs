@@ -0,0 +1,145 @@
+//! The chi-square attack on LSB pairs by Westfeld and Pfitzmann.
+//!
+//! Hiding random bits in the LSBs evens out how often the values `2i` and `2i + 1` occur,
+//! in a clean carrier they usually differ. The closer the pairs are to their mean, the more
+//! likely it is that the LSBs carry data.
+
+use std::collections::HashMap;
+
+/// a category is only taken into account if at least that many values are expected in it
+const MIN_EXPECTED: f64 = 5.0;
+
+/// The probability in `0.0..=1.0` that the LSBs of `values` carry embedded data
+pub(crate) fn embedding_probability(values: impl Iterator<Item = i64>) -> f64 {
+    let mut pairs: HashMap<i64, [u64; 2]> = HashMap::new();
+    for value in values {
+        pairs.entry(value >> 1).or_default()[(value & 1) as usize] += 1;
+    }
+
+    let mut chi_square = 0.0;
+    let mut categories = 0;
+    for [even, odd] in pairs.into_values() {
+        let expected = (even + odd) as f64 / 2.0;
+        if expected < MIN_EXPECTED {
+            continue;
+        }
+        chi_square += (even as f64 - expected).powi(2) / expected;
+        categories += 1;
+    }
+    if categories < 2 {
+        // nothing to tell from
+        return 0.0;
+    }
+    let degrees_of_freedom = (categories - 1) as f64;
+
+    gamma_q(degrees_of_freedom / 2.0, chi_square / 2.0).clamp(0.0, 1.0)
+}
+
+/// the regularized upper incomplete gamma function `Q(a, x)`, that is `1 - P(a, x)`
+fn gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        1.0
+    } else if x < a + 1.0 {
+        1.0 - gamma_p_series(a, x)
+    } else {
+        gamma_q_continued_fraction(a, x)
+    }
+}
+
+const MAX_ITERATIONS: usize = 1000;
+const EPSILON: f64 = 1e-14;
+
+/// `P(a, x)` by its series, converges quickly for `x < a + 1`
+fn gamma_p_series(a: f64, x: f64) -> f64 {
+    let mut n = a;
+    let mut term = 1.0 / a;
+    let mut sum = term;
+    for _ in 0..MAX_ITERATIONS {
+        n += 1.0;
+        term *= x / n;
+        sum += term;
+        if term.abs() < sum.abs() * EPSILON {
+            break;
+        }
+    }
+
+    sum * (-x + a * x.ln() - ln_gamma(a)).exp()
+}
+
+/// `Q(a, x)` by its continued fraction (modified Lentz), converges quickly for `x >= a + 1`
+fn gamma_q_continued_fraction(a: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..MAX_ITERATIONS {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = b + an / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    (-x + a * x.ln() - ln_gamma(a)).exp() * h
+}
+
+/// `ln(Γ(x))` for `x > 0` by the Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut y = x;
+    let series = COEFFICIENTS.iter().fold(1.000000000190015, |sum, c| {
+        y += 1.0;
+        sum + c / y
+    });
+
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_compute_known_gamma_values() {
+        // Γ(5) = 24 and Γ(0.5) = √π
+        assert!((ln_gamma(5.0) - 24f64.ln()).abs() < 1e-10);
+        assert!((ln_gamma(0.5) - std::f64::consts::PI.sqrt().ln()).abs() < 1e-10);
+        // for 2 degrees of freedom the chi-square tail is exp(-x / 2)
+        for chi_square in [0.5, 2.0, 7.0, 30.0] {
+            let expected = (-chi_square / 2.0f64).exp();
+            assert!((gamma_q(1.0, chi_square / 2.0) - expected).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn should_score_balanced_pairs_high_and_unbalanced_pairs_low() {
+        let balanced = (0..100_000).map(|i| i % 256);
+        assert!(embedding_probability(balanced) > 0.99);
+
+        let only_even = (0..100_000).map(|i| (i % 128) * 2);
+        assert!(embedding_probability(only_even) < 0.01);
+
+        assert_eq!(embedding_probability(std::iter::empty()), 0.0);
+    }
+}
@@ -0,0 +1,244 @@
+//! Forward error correction for the hidden bits, a repetition code.
+//!
+//! The payload is hidden several times in a row and every bit is restored by a majority vote
+//! over its copies. The copies lie far apart in the carrier, so a damaged region only hits one
+//! of them. Reed-Solomon erasure codes need to know which parts are lost, flipped LSBs are not
+//! known though, so the plain majority vote is what helps here.
+//!
+//! The frame starts with a header that is repeated three times:
+//! - a flag byte, the two upper bits are set, the lower bits hold the number of repetitions.
+//!   No payload version uses the upper bit, the long length header uses the one below it,
+//!   so only both bits together tell a protected payload apart.
+//! - the length of the payload as big endian `u32`
+//!
+//! then the payload follows, once per repetition.
+
+use byteorder::{BigEndian, ByteOrder};
+use std::io::{Cursor, Read};
+
+const FEC_FLAG: u8 = 0b1100_0000;
+const HEADER_COPIES: usize = 3;
+const HEADER_LEN: usize = HEADER_COPIES * (1 + 4);
+
+/// Configures the forward error correction, see [`crate::CodecOptions::fec`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct FecConfig {
+    repetitions: u8,
+}
+
+impl FecConfig {
+    pub const MAX_REPETITIONS: u8 = !FEC_FLAG;
+
+    /// Every bit is hidden `repetitions` times, at most [`Self::MAX_REPETITIONS`].
+    /// Odd numbers make sense, 3 copies survive one flipped copy of a bit, 5 copies two.
+    pub fn repetition(repetitions: u8) -> Self {
+        Self {
+            repetitions: repetitions.clamp(1, Self::MAX_REPETITIONS),
+        }
+    }
+
+    pub fn repetitions(&self) -> u8 {
+        self.repetitions
+    }
+
+    /// The number of payload bytes that fit into `capacity` raw bytes
+    pub(crate) fn payload_capacity(&self, capacity: usize) -> usize {
+        capacity.saturating_sub(HEADER_LEN) / self.repetitions as usize
+    }
+
+    /// Wraps the payload into a frame that can be corrected on extraction
+    pub(crate) fn encode(&self, payload: &[u8]) -> Vec<u8> {
+        let mut header = [FEC_FLAG | self.repetitions, 0, 0, 0, 0];
+        BigEndian::write_u32(&mut header[1..], payload.len() as u32);
+
+        let mut frame = Vec::with_capacity(HEADER_LEN + payload.len() * self.repetitions as usize);
+        for _ in 0..HEADER_COPIES {
+            frame.extend_from_slice(&header);
+        }
+        for _ in 0..self.repetitions {
+            frame.extend_from_slice(payload);
+        }
+
+        frame
+    }
+}
+
+impl Default for FecConfig {
+    fn default() -> Self {
+        Self::repetition(3)
+    }
+}
+
+/// Corrects a protected payload if the flag says so, any other payload is passed on untouched
+pub(crate) fn decoder<'a>(mut raw: Box<dyn Read + 'a>) -> Box<dyn Read + 'a> {
+    let mut first = Vec::with_capacity(1);
+    if raw.by_ref().take(1).read_to_end(&mut first).is_err() || first.is_empty() {
+        return raw;
+    }
+    // a payload version sets at most the long length header bit, a byte without both is no flag,
+    // whether both are set is decided by the majority of the header copies
+    if first[0] & FEC_FLAG == 0 {
+        return Box::new(Cursor::new(first).chain(raw));
+    }
+
+    let mut raw_header = first;
+    let complete = raw
+        .by_ref()
+        .take(HEADER_LEN as u64 - 1)
+        .read_to_end(&mut raw_header)
+        .is_ok_and(|_| raw_header.len() == HEADER_LEN);
+    if !complete {
+        // a short payload, the bytes read so far are all of it
+        return Box::new(Cursor::new(raw_header).chain(raw));
+    }
+    let header = majority(&raw_header, HEADER_COPIES);
+    let repetitions = (header[0] & !FEC_FLAG) as usize;
+    let len = BigEndian::read_u32(&header[1..]) as usize;
+
+    let body_len = len
+        .checked_mul(repetitions)
+        .filter(|_| header[0] & FEC_FLAG == FEC_FLAG && repetitions > 0);
+    let Some(body_len) = body_len else {
+        return Box::new(Cursor::new(raw_header).chain(raw));
+    };
+
+    let mut copies = Vec::new();
+    let complete = raw
+        .by_ref()
+        .take(body_len as u64)
+        .read_to_end(&mut copies)
+        .is_ok_and(|n| n == body_len);
+    if !complete {
+        // no frame after all, e.g. noise, the bytes are passed on as they were
+        raw_header.append(&mut copies);
+        return Box::new(Cursor::new(raw_header).chain(raw));
+    }
+
+    Box::new(Cursor::new(majority(&copies, repetitions)))
+}
+
+/// Restores every bit by the majority of the consecutive `copies` in `data`
+fn majority(data: &[u8], copies: usize) -> Vec<u8> {
+    let len = data.len() / copies;
+
+    (0..len)
+        .map(|i| {
+            (0..8).fold(0, |byte, bit| {
+                let set = (0..copies)
+                    .filter(|c| data[c * len + i] & (1 << bit) != 0)
+                    .count();
+                if set * 2 > copies {
+                    byte | (1 << bit)
+                } else {
+                    byte
+                }
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::payload::{FabA, LENGTH_HEADER, LONG_LENGTH_HEADER, TEXT_AND_DOCUMENTS};
+    use crate::media::Media;
+    use crate::testing::generate_carrier;
+    use crate::{CodecOptions, Message};
+    use image::Pixel;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn should_correct_single_flipped_copies() {
+        let fec = FecConfig::repetition(3);
+        let mut frame = fec.encode(b"Hello World!");
+        // flip one bit in two of the header copies and in each copy of the payload
+        frame[0] ^= 0b0100_0000;
+        frame[7] ^= 0b0000_0001;
+        for (copy, byte) in [(0, 0), (1, 5), (2, 11)] {
+            frame[HEADER_LEN + copy * 12 + byte] ^= 0b0001_0000;
+        }
+
+        let mut decoded = Vec::new();
+        decoder(Box::new(Cursor::new(frame)))
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, b"Hello World!");
+    }
+
+    #[test]
+    fn should_pass_unprotected_payloads_through() {
+        let payload = vec![0x04, 0, 0, 0, 2, 0xff, 0xc0];
+
+        let mut decoded = Vec::new();
+        decoder(Box::new(Cursor::new(payload.clone())))
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, payload);
+    }
+
+    #[test]
+    fn should_tell_a_long_length_header_from_the_flag() {
+        let long = LONG_LENGTH_HEADER | LENGTH_HEADER | TEXT_AND_DOCUMENTS;
+        let payload = [vec![long, 0, 0, 0, 0, 0, 0, 0, 2], vec![0xc0; 2]].concat();
+
+        let mut passed = Vec::new();
+        decoder(Box::new(Cursor::new(payload.clone())))
+            .read_to_end(&mut passed)
+            .unwrap();
+        assert_eq!(passed, payload);
+
+        let mut corrected = Vec::new();
+        decoder(Box::new(Cursor::new(FecConfig::default().encode(&payload))))
+            .read_to_end(&mut corrected)
+            .unwrap();
+        assert_eq!(corrected, payload);
+    }
+
+    #[test]
+    fn should_pass_a_half_flag_through_without_reading_the_announced_body() {
+        // only the bit below the flag bit is set, with the longest length
+        let header = [0b0100_0011, 0xff, 0xff, 0xff, 0xff].repeat(HEADER_COPIES);
+        let endless = Cursor::new(header.clone()).chain(std::io::repeat(0));
+
+        let mut decoded = vec![0; HEADER_LEN + 4];
+        decoder(Box::new(endless)).read_exact(&mut decoded).unwrap();
+        assert_eq!(decoded[..HEADER_LEN], header);
+        assert_eq!(decoded[HEADER_LEN..], [0; 4]);
+    }
+
+    #[test]
+    fn should_recover_a_protected_payload_with_flipped_lsbs() {
+        let mut secret = Message::empty();
+        secret
+            .add_file_data("secret.txt", b"the eagle has landed at dawn".repeat(20))
+            .unwrap();
+        let payload = secret.to_raw_data(&FabA).unwrap();
+
+        let protected = CodecOptions {
+            fec: Some(FecConfig::repetition(5)),
+            ..Default::default()
+        };
+        let unprotected = CodecOptions::default();
+
+        for (opts, should_recover) in [(protected, true), (unprotected, false)] {
+            let mut media = generate_carrier(128, 128, 7);
+            media.hide_data(payload.clone(), &opts).unwrap();
+            let Media::Image(image) = &mut media else {
+                unreachable!()
+            };
+            // flip the LSB of 0.5% of the color channels
+            let mut rng = StdRng::seed_from_u64(42);
+            for pixel in image.pixels_mut() {
+                for channel in pixel.channels_mut()[..3].iter_mut() {
+                    if rng.gen_bool(0.005) {
+                        *channel ^= 1;
+                    }
+                }
+            }
+
+            let unveiled = Message::from_raw_data(&mut media.decoder(&opts), &FabA);
+            let recovered = unveiled.is_ok_and(|m| m.files == secret.files);
+            assert_eq!(recovered, should_recover, "with fec {:?}", opts.fec);
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Detects the 8x8 block grid that JPEG compression leaves behind.
+//!
+//! JPEG quantizes every 8x8 block on its own, so neighbouring pixels differ more across block
+//! borders than within a block. A lossless image that was decoded from a JPEG keeps that grid.
+
+/// JPEG blocks are 8x8 pixels
+const BLOCK_SIZE: u32 = 8;
+/// the differences across block borders exceed the others by this factor in JPEG transcodes
+const TRANSCODED_BLOCKINESS: f64 = 1.5;
+
+/// The mean difference of neighbouring luma values across block borders, relative to the one
+/// within blocks. It is about 1 for natural images and grows with the JPEG compression.
+pub(crate) fn blockiness(width: u32, height: u32, luma: impl Fn(u32, u32) -> f64) -> f64 {
+    let (mut border, mut border_count) = (0.0, 0usize);
+    let (mut inner, mut inner_count) = (0.0, 0usize);
+    let mut add = |at_border: bool, diff: f64| {
+        if at_border {
+            border += diff;
+            border_count += 1;
+        } else {
+            inner += diff;
+            inner_count += 1;
+        }
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            if x + 1 < width {
+                add(
+                    (x + 1) % BLOCK_SIZE == 0,
+                    (luma(x, y) - luma(x + 1, y)).abs(),
+                );
+            }
+            if y + 1 < height {
+                add(
+                    (y + 1) % BLOCK_SIZE == 0,
+                    (luma(x, y) - luma(x, y + 1)).abs(),
+                );
+            }
+        }
+    }
+    if border_count == 0 || inner_count == 0 {
+        return 1.0;
+    }
+    let border = border / border_count as f64;
+    let inner = inner / inner_count as f64;
+    if inner == 0.0 {
+        // flat blocks, like in graphics, with edges exactly on the block borders
+        return if border > 0.0 { f64::INFINITY } else { 1.0 };
+    }
+
+    border / inner
+}
+
+/// true if the image shows the block grid of a JPEG compression
+pub(crate) fn looks_transcoded_from_jpeg(
+    width: u32,
+    height: u32,
+    luma: impl Fn(u32, u32) -> f64,
+) -> bool {
+    width >= 2 * BLOCK_SIZE
+        && height >= 2 * BLOCK_SIZE
+        && blockiness(width, height, luma) > TRANSCODED_BLOCKINESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_tell_a_block_grid_from_noise() {
+        let noise = |x: u32, y: u32| ((x * 7919 + y * 104_729) % 251) as f64;
+        assert!(!looks_transcoded_from_jpeg(64, 64, noise));
+
+        // smooth gradients within the blocks, steps at the borders
+        let blocky = |x: u32, y: u32| ((x / 8 * 31 + y / 8 * 17) % 200 + x % 8 + y % 8) as f64;
+        assert!(looks_transcoded_from_jpeg(64, 64, blocky));
+    }
+}
@@ -1,32 +1,39 @@
-use super::iterators::{ColorIter, Transpose};
+use super::iterators::{masked, ordered, ColorIter, Transpose};
 use super::lsb_codec::CodecOptions;
 use crate::media::MediaPrimitive;
 
-use image::{Rgba, RgbaImage};
+use image::{ImageBuffer, Pixel};
 
 /// stegano source for image files, based on `RgbaImage` by `image` crate
 pub struct ImageRgbaColor<'i> {
     i: usize,
     steps: usize,
-    pixel: ColorIter<'i, Rgba<u8>>,
+    pixel: Box<dyn Iterator<Item = &'i u8> + 'i>,
 }
 
 impl<'i> ImageRgbaColor<'i> {
     /// constructor for a given `RgbaImage` that lives somewhere
     #[cfg(test)]
-    pub fn new(input: &'i RgbaImage) -> Self {
+    pub fn new(input: &'i image::RgbaImage) -> Self {
         Self::new_with_options(input, &CodecOptions::default())
     }
 
-    pub fn new_with_options(input: &'i RgbaImage, options: &CodecOptions) -> Self {
+    /// works for any 8 bit pixel type, like RGBA or grayscale
+    pub fn new_with_options<P: Pixel<Subpixel = u8> + 'i>(
+        input: &'i ImageBuffer<P, Vec<u8>>,
+        options: &CodecOptions,
+    ) -> Self {
         let w = input.width();
+        let usable = options.usable_pixels(input, options.skip_last_row_and_column);
+        let colors = ColorIter::<P>::from_transpose(
+            Transpose::from_rows(input.rows(), w, options.skip_last_row_and_column),
+            options.skip_alpha_channel,
+        );
+        let colors = masked(colors, usable, options.channels_per_pixel::<P>());
         Self {
             i: 0,
             steps: options.get_color_channel_step_increment(),
-            pixel: ColorIter::from_transpose(
-                Transpose::from_rows(input.rows(), w, true),
-                options.skip_alpha_channel,
-            ),
+            pixel: ordered(colors, options),
         }
     }
 }
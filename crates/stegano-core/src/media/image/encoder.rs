@@ -1,6 +1,6 @@
-use image::{Rgba, RgbaImage};
+use image::{ImageBuffer, Pixel};
 
-use super::iterators::{ColorIterMut, TransposeMut};
+use super::iterators::{masked, ordered, ColorIterMut, TransposeMut};
 use super::lsb_codec::CodecOptions;
 use crate::media::MediaPrimitiveMut;
 
@@ -8,25 +8,32 @@ use crate::media::MediaPrimitiveMut;
 pub struct ImageRgbaColorMut<'a> {
     i: usize,
     steps: usize,
-    pixel: ColorIterMut<'a, Rgba<u8>>,
+    pixel: Box<dyn Iterator<Item = &'a mut u8> + 'a>,
 }
 
 impl<'a> ImageRgbaColorMut<'a> {
     /// constructor for a given `RgbaImage` that lives somewhere
     #[cfg(test)]
-    pub fn new(input: &'a mut RgbaImage) -> Self {
+    pub fn new(input: &'a mut image::RgbaImage) -> Self {
         Self::new_with_options(input, &CodecOptions::default())
     }
 
-    pub fn new_with_options(input: &'a mut RgbaImage, options: &CodecOptions) -> Self {
+    /// works for any 8 bit pixel type, like RGBA or grayscale
+    pub fn new_with_options<P: Pixel<Subpixel = u8> + 'a>(
+        input: &'a mut ImageBuffer<P, Vec<u8>>,
+        options: &CodecOptions,
+    ) -> Self {
         let w = input.width();
+        let usable = options.usable_pixels(input, options.skip_last_row_and_column);
+        let colors = ColorIterMut::<P>::from_transpose(
+            TransposeMut::from_rows_mut(input.rows_mut(), w, options.skip_last_row_and_column),
+            options.skip_alpha_channel,
+        );
+        let colors = masked(colors, usable, options.channels_per_pixel::<P>());
         Self {
             i: 0,
             steps: options.color_channel_step_increment,
-            pixel: ColorIterMut::from_transpose(
-                TransposeMut::from_rows_mut(input.rows_mut(), w, options.skip_last_row_and_column),
-                options.skip_alpha_channel,
-            ),
+            pixel: ordered(colors, options),
         }
     }
 }
@@ -1,9 +1,31 @@
 use image::buffer::{Pixels, PixelsMut, Rows, RowsMut};
-use image::Pixel;
+use image::{ImageBuffer, Pixel};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
 use std::iter::Take;
-use std::ops::Sub;
 use std::slice::{Iter, IterMut};
 
+use super::lsb_codec::{CodecOptions, EmbedDirection, Rect};
+
+/// The width and height of the pixel area that is traversed, the same on hiding and unveiling.
+///
+/// With `skip_last_row_and_column` the last column (`x == width - 1`) and the last row
+/// (`y == height - 1`) are left out, so a `w`x`h` image yields `(w - 1) * (h - 1)` pixels.
+/// The pixels are visited column by column, each column from top to bottom:
+/// `(0, 0), (0, 1), .., (0, h' - 1), (1, 0), ..` where `h'` is the traversed height.
+pub(crate) fn traversed_area(
+    width: u32,
+    height: u32,
+    skip_last_row_and_column: bool,
+) -> (u32, u32) {
+    if skip_last_row_and_column {
+        (width.saturating_sub(1), height.saturating_sub(1))
+    } else {
+        (width, height)
+    }
+}
+
 /// Allows transposed mutable access to pixel, like column based
 pub(crate) struct TransposeMut<'a, P: Pixel + 'a> {
     i: usize,
@@ -20,18 +42,15 @@ impl<'a, P: Pixel + 'a> TransposeMut<'a, P> {
         width: u32,
         skip_last_row_and_column: bool,
     ) -> Self {
-        let (height, width) = if skip_last_row_and_column {
-            (rows_mut.len() - 1, width.sub(1) as usize)
-        } else {
-            (rows_mut.len(), width as usize)
-        };
+        let (width, height) =
+            traversed_area(width, rows_mut.len() as u32, skip_last_row_and_column);
 
         Self {
             i: 0,
-            i_max: height * width,
-            use_max_rows: height as _,
-            rows_mut: rows_mut.take(height),
-            rows_buffer: Vec::with_capacity(height),
+            i_max: height as usize * width as usize,
+            use_max_rows: height,
+            rows_mut: rows_mut.take(height as usize),
+            rows_buffer: Vec::with_capacity(height as usize),
         }
     }
 }
@@ -70,18 +89,14 @@ pub(crate) struct Transpose<'a, P: Pixel + 'a> {
 impl<'a, P: Pixel + 'a> Transpose<'a, P> {
     /// utilizes Rows to give column based readonly access to pixel
     pub fn from_rows(rows: Rows<'a, P>, width: u32, skip_last_row_and_column: bool) -> Self {
-        let (height, width) = if skip_last_row_and_column {
-            (rows.len() - 1, width.sub(1) as usize)
-        } else {
-            (rows.len(), width as usize)
-        };
+        let (width, height) = traversed_area(width, rows.len() as u32, skip_last_row_and_column);
 
         Self {
             i: 0,
-            i_max: height * width,
-            use_max_rows: height as _,
-            rows: rows.take(height),
-            rows_buffer: Vec::with_capacity(height),
+            i_max: height as usize * width as usize,
+            use_max_rows: height,
+            rows: rows.take(height as usize),
+            rows_buffer: Vec::with_capacity(height as usize),
         }
     }
 }
@@ -118,12 +133,11 @@ pub(crate) struct ColorIterMut<'a, P: Pixel + 'a> {
 impl<'a, P: Pixel + 'a> ColorIterMut<'a, P> {
     pub fn from_transpose(mut t: TransposeMut<'a, P>, skip_alpha: bool) -> Self {
         let take: u8 = if skip_alpha { 3 } else { 4 };
-        let i = t
-            .next()
-            .unwrap()
-            .channels_mut()
-            .iter_mut()
-            .take(take as usize);
+        // an image without any traversed pixel yields no colors
+        let i = match t.next() {
+            Some(pixel) => pixel.channels_mut().iter_mut().take(take as usize),
+            None => [].iter_mut().take(0),
+        };
         Self {
             pixel: t,
             colors: i,
@@ -154,7 +168,11 @@ pub(crate) struct ColorIter<'a, P: Pixel + 'a> {
 impl<'a, P: Pixel + 'a> ColorIter<'a, P> {
     pub fn from_transpose(mut t: Transpose<'a, P>, skip_alpha: bool) -> Self {
         let take: u8 = if skip_alpha { 3 } else { 4 };
-        let i = t.next().unwrap().channels().iter().take(take as usize);
+        // an image without any traversed pixel yields no colors
+        let i = match t.next() {
+            Some(pixel) => pixel.channels().iter().take(take as usize),
+            None => [].iter().take(0),
+        };
         Self {
             pixel: t,
             colors: i,
@@ -176,6 +194,100 @@ impl<'a, P: Pixel + 'a> Iterator for ColorIter<'a, P> {
     }
 }
 
+/// brings the color channels into the order the options ask for, by default they stay as they are
+pub(crate) fn ordered<'a, T: 'a>(
+    colors: impl Iterator<Item = T> + 'a,
+    options: &CodecOptions,
+) -> Box<dyn Iterator<Item = T> + 'a> {
+    if options.permutation_seed.is_none() && options.direction == EmbedDirection::Forward {
+        return Box::new(colors);
+    }
+
+    let mut colors: Vec<T> = colors.collect();
+    if let Some(seed) = options.permutation_seed {
+        shuffle(&mut colors, seed);
+    }
+    if options.direction == EmbedDirection::Reverse {
+        colors.reverse();
+    }
+
+    Box::new(colors.into_iter())
+}
+
+/// keeps only the color channels of pixels marked as usable in `mask`, `mask` holds one entry per pixel
+/// in the transposed order and `per_pixel` is the number of color channels each pixel yields
+pub(crate) fn masked<'a, T: 'a>(
+    colors: impl Iterator<Item = T> + 'a,
+    mask: Option<Vec<bool>>,
+    per_pixel: usize,
+) -> impl Iterator<Item = T> + 'a {
+    let mut mask = mask.map(|m| {
+        m.into_iter()
+            .flat_map(move |usable| std::iter::repeat_n(usable, per_pixel))
+    });
+    colors.filter(move |_| mask.as_mut().is_none_or(|m| m.next().unwrap_or(false)))
+}
+
+/// marks the pixels whose local gradient to the right and bottom neighbour exceeds `threshold`,
+/// in the same column based order as `Transpose`.
+///
+/// Only the bits of `cover_bits` are looked at, so hiding data does not change the outcome.
+pub(crate) fn edge_mask<P: Pixel<Subpixel = u8>>(
+    image: &ImageBuffer<P, Vec<u8>>,
+    skip_last_row_and_column: bool,
+    threshold: u8,
+    cover_bits: u8,
+) -> Vec<bool> {
+    let (width, height) = image.dimensions();
+    let (used_width, used_height) = traversed_area(width, height, skip_last_row_and_column);
+    // alpha is never looked at, it does not tell anything about the image content
+    let color_channels = (P::CHANNEL_COUNT as usize).min(3);
+    let gradient = |a: &P, b: &P| {
+        a.channels()
+            .iter()
+            .zip(b.channels())
+            .take(color_channels)
+            .map(|(a, b)| (a & cover_bits).abs_diff(b & cover_bits))
+            .max()
+            .unwrap_or(0)
+    };
+
+    let mut mask = Vec::with_capacity(used_width as usize * used_height as usize);
+    for x in 0..used_width {
+        for y in 0..used_height {
+            let pixel = image.get_pixel(x, y);
+            let right = image.get_pixel((x + 1).min(width - 1), y);
+            let below = image.get_pixel(x, (y + 1).min(height - 1));
+            mask.push(gradient(pixel, right).max(gradient(pixel, below)) > threshold);
+        }
+    }
+
+    mask
+}
+
+/// marks the pixels inside `region`, in the same column based order as `Transpose`
+pub(crate) fn region_mask(
+    width: u32,
+    height: u32,
+    skip_last_row_and_column: bool,
+    region: Rect,
+) -> Vec<bool> {
+    let (used_width, used_height) = traversed_area(width, height, skip_last_row_and_column);
+
+    (0..used_width)
+        .flat_map(|x| (0..used_height).map(move |y| region.contains(x, y)))
+        .collect()
+}
+
+/// deterministic Fisher-Yates shuffle driven by ChaCha20, the same seed always leads to the same order.
+///
+/// Without the seed the order cannot be predicted, but it is only as strong as the seed and that
+/// has 64 bits. A seed that stems from a password must come out of a KDF, like the argon2 based
+/// `derive_seed`, a plain hash of a password would allow cheap guessing.
+pub(crate) fn shuffle<T>(items: &mut [T], seed: u64) {
+    items.shuffle(&mut ChaCha20Rng::seed_from_u64(seed));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -191,7 +303,7 @@ mod tests {
         let width = img.width();
         assert_eq!(width, 4);
         assert_eq!(img.height(), 6);
-        assert_eq!(img.rows().len(), img.height() as _);
+        assert_eq!(img.rows().len(), img.height() as usize);
         let iter = Transpose::from_rows(img.rows(), width, false);
         let color_iter = ColorIter::from_transpose(iter, true);
         for (i, c) in color_iter.enumerate() {
@@ -318,4 +430,105 @@ mod tests {
         let given_last_color = color_iter.last();
         assert_eq!(last_pixel.0.get(2), given_last_color);
     }
+
+    #[test]
+    fn should_traverse_the_same_pixels_for_reading_and_writing() {
+        // every pixel knows its own coordinates
+        let mut img = image::RgbaImage::from_fn(4, 6, |x, y| image::Rgba([x as u8, y as u8, 0, 0]));
+        let (width, height) = img.dimensions();
+
+        for skip in [true, false] {
+            let (used_width, used_height) = traversed_area(width, height, skip);
+            let expected: Vec<(u8, u8)> = (0..used_width)
+                .flat_map(|x| (0..used_height).map(move |y| (x as u8, y as u8)))
+                .collect();
+            assert_eq!(expected.len(), if skip { 3 * 5 } else { 4 * 6 });
+
+            let read: Vec<(u8, u8)> = Transpose::from_rows(img.rows(), width, skip)
+                .map(|p| (p.0[0], p.0[1]))
+                .collect();
+            let written: Vec<(u8, u8)> = TransposeMut::from_rows_mut(img.rows_mut(), width, skip)
+                .map(|p| (p.0[0], p.0[1]))
+                .collect();
+            assert_eq!(read, expected, "skip: {skip}");
+            assert_eq!(written, expected, "skip: {skip}");
+        }
+    }
+
+    #[test]
+    fn should_traverse_no_pixel_of_a_single_row_image_when_skipping() {
+        let mut img = image::RgbaImage::new(5, 1);
+        assert_eq!(traversed_area(5, 1, true), (4, 0));
+
+        let iter = Transpose::from_rows(img.rows(), 5, true);
+        assert_eq!(ColorIter::from_transpose(iter, true).count(), 0);
+        let iter = TransposeMut::from_rows_mut(img.rows_mut(), 5, true);
+        assert_eq!(ColorIterMut::from_transpose(iter, true).count(), 0);
+    }
+
+    #[test]
+    fn should_shuffle_deterministically_by_seed() {
+        let mut a: Vec<u32> = (0..100).collect();
+        let mut b = a.clone();
+        let mut c = a.clone();
+        shuffle(&mut a, 42);
+        shuffle(&mut b, 42);
+        shuffle(&mut c, 43);
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, (0..100).collect::<Vec<_>>());
+        a.sort();
+        assert_eq!(a, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn should_shuffle_into_a_bijection() {
+        for len in [0, 1, 2, 3, 255, 1000] {
+            for seed in [0, 1, u64::MAX] {
+                let mut items: Vec<usize> = (0..len).collect();
+                shuffle(&mut items, seed);
+                let mut seen = vec![false; len];
+                for i in items {
+                    assert!(!seen[i], "{i} appeared twice");
+                    seen[i] = true;
+                }
+                assert!(seen.into_iter().all(|s| s));
+            }
+        }
+    }
+
+    #[test]
+    fn should_shuffle_uncorrelated_for_different_seeds() {
+        let len = 10_000;
+        let order = |seed| {
+            let mut items: Vec<usize> = (0..len).collect();
+            shuffle(&mut items, seed);
+            items
+        };
+        // pearson correlation of the positions, it is about 1 / sqrt(len) for random orders
+        let correlation = |a: &[usize], b: &[usize]| {
+            let mean = (len - 1) as f64 / 2.0;
+            let (mut ab, mut aa, mut bb) = (0.0, 0.0, 0.0);
+            for (&a, &b) in a.iter().zip(b) {
+                let (a, b) = (a as f64 - mean, b as f64 - mean);
+                ab += a * b;
+                aa += a * a;
+                bb += b * b;
+            }
+            ab / (aa * bb).sqrt()
+        };
+
+        let identity: Vec<usize> = (0..len).collect();
+        let orders: Vec<_> = [1, 2, 3, 1 << 32, u64::MAX].map(order).into();
+        for (i, a) in orders.iter().enumerate() {
+            assert!(correlation(a, &identity).abs() < 0.05);
+            for b in &orders[i + 1..] {
+                assert!(correlation(a, b).abs() < 0.05);
+                // neighbouring seeds must not share any noticeable part of the order either
+                let fixed_points = a.iter().zip(b).filter(|(a, b)| a == b).count();
+                assert!(fixed_points < 10, "{fixed_points} equal positions");
+            }
+        }
+    }
 }
@@ -1,14 +1,19 @@
 use super::decoder::ImageRgbaColor;
 use super::encoder::ImageRgbaColorMut;
-use crate::universal_decoder::{OneBitUnveil, UniversalDecoder};
+use super::iterators::{edge_mask, region_mask};
+use crate::media::FecConfig;
+use crate::universal_decoder::{BitPlaneUnveil, OneBitUnveil, UniversalDecoder, UnveilAlgorithms};
 use crate::universal_encoder::{
-    HideAlgorithms, OneBitHide, OneBitInLowFrequencyHide, UniversalEncoder,
+    BitPlaneHide, HideAlgorithms, OneBitHide, OneBitInLowFrequencyHide, UniversalEncoder,
 };
+use crate::{Result, SteganoError};
 
-use image::RgbaImage;
+use bitstream_io::{BigEndian, LittleEndian};
+use image::{ImageBuffer, Pixel};
+use std::hash::{Hash, Hasher};
 use std::io::{Read, Write};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CodecOptions {
     /// determines the step with when iterating over the color channels.
     /// For example `2` would move from (R)GBA to RG(B)A.
@@ -29,10 +34,230 @@ pub struct CodecOptions {
     pub concealer: Concealer,
 
     /// This limits all iterations to skip the least column and row, in fact it reduces width and height of the image by 1
+    ///
+    /// Note: the last row records the step increment, the alpha channel and the bit order of
+    /// an image, so that unveiling configures itself. Without this option, or with a step
+    /// increment beyond 255, they are not recorded and unveiling needs the same options.
     pub skip_last_row_and_column: bool,
+
+    /// If set, the color channels are visited in a pseudo random order derived from this seed,
+    /// instead of column by column. Encoder and decoder need the same seed.
+    ///
+    /// The order comes from ChaCha20 and is as hard to guess as the seed. Use all 64 bits of it,
+    /// e.g. from a KDF like the password based order does, a small number is quickly found.
+    pub permutation_seed: Option<u64>,
+
+    /// The direction in which the color channels are used. Two payloads hidden in opposite
+    /// directions do not collide as long as they fit into the carrier together.
+    /// Note: in an order derived from a password both payloads spread over the whole carrier.
+    pub direction: EmbedDirection,
+
+    /// If set, only pixels whose local gradient exceeds this threshold carry data, flat areas
+    /// like a clear sky stay untouched. The gradient ignores the bits the concealer writes to,
+    /// so the decoder finds the very same pixels.
+    ///
+    /// Note this reduces the capacity depending on the image content, a flat image has none.
+    pub adaptive_threshold: Option<u8>,
+
+    /// If set, only the pixels inside this rectangle carry data, e.g. the quadrant another tool
+    /// embedded into. Parts of the rectangle outside the traversed area are left out.
+    ///
+    /// Note this reduces the capacity to the region, the decoder needs the same region.
+    pub region: Option<Rect>,
+
+    /// The bit of a color channel that carries the data, `0` is the least significant bit,
+    /// `1` the next one and so on. Payloads in different planes do not interfere, e.g. a decoy
+    /// in plane 0 and the real secret in plane 1. The decoder needs the same plane.
+    ///
+    /// Note: planes beyond 7 are rejected with `SteganoError::InvalidBitPlane`, only the `LeastSignificantBit` concealer honors the plane.
+    pub bit_plane: u8,
+
+    /// The order in which the bits of a byte are hidden, other tools often start with the
+    /// most significant bit. The decoder needs the same order.
+    pub bit_order: BitOrder,
+
+    /// The channels of an audio carrier that carry data, by default all of them.
+    /// E.g. only the left channel of a stereo carrier keeps the right one pristine.
+    ///
+    /// Note this reduces the capacity by the share of the channels left out.
+    pub audio_channels: ChannelMask,
+
+    /// Only every `n`th sample of the audio channels carries data, like the step increment does
+    /// for color channels. Fewer touched samples mean less audible noise.
+    ///
+    /// Note this divides the capacity by the step, the decoder needs the same step.
+    pub audio_sample_step: u32,
+
+    /// If set, the payload is hidden several times and corrected on extraction, so that it
+    /// survives a few flipped bits, e.g. from a slight edit of the carrier.
+    /// The extraction detects a protected payload on its own.
+    ///
+    /// Note this divides the capacity by the number of repetitions.
+    pub fec: Option<FecConfig>,
+
+    /// The number of copies of the payload that are hidden one after the other, `1` hides it
+    /// once. Unveiling compares the copies on its own and fails with
+    /// `SteganoError::CopiesDisagree` if they differ, the media was modified then.
+    /// Unlike `fec` nothing is corrected, at most 63 copies are hidden.
+    ///
+    /// Note this divides the capacity by the number of copies.
+    pub redundancy: u8,
+
+    /// If set, unveiling refuses messages whose files and text together exceed this many bytes,
+    /// with `SteganoError::ExtractionLimitExceeded`. The sizes the files claim are checked
+    /// before anything is allocated, so a crafted media cannot exhaust the memory.
+    /// Hiding ignores it.
+    pub max_extract_bytes: Option<usize>,
+
+    /// If true, unveiling skips hidden files that are damaged, e.g. by a slightly corrupted
+    /// media, instead of failing as a whole. The skipped files are logged and listed in
+    /// `Message::damaged_files`. Hiding ignores it.
+    pub best_effort: bool,
+
+    /// If true, the version byte and the length header are XORed with the public constant
+    /// `HEADER_KEYSTREAM`, so that an unencrypted payload does not show its header in the LSBs.
+    /// The decoder needs the same flag.
+    ///
+    /// Note this is no encryption, anyone who knows the constant reads the message.
+    /// Use `SteganoEncoder::with_encryption` to protect the content.
+    pub obfuscate_header: bool,
+
+    /// If true, the size of the carrier is hidden in front of the payload, so that unveiling
+    /// tells a cropped or padded carrier apart with `SteganoError::CarrierAltered`, instead of
+    /// failing somewhere in the payload. The extraction detects a recorded size on its own.
+    ///
+    /// Note this takes 11 bytes of the capacity and only helps with the column by column order.
+    pub record_carrier_size: bool,
+
+    /// If true, the capacity that is left after the payload is filled with random bits, so that
+    /// the LSBs look the same all over the carrier and do not show where the payload ends.
+    /// The payload tells its own length, so unveiling ignores the random tail.
+    pub randomize_unused: bool,
+
+    /// If set, hiding uses at most this fraction (0.0 to 1.0) of the capacity, so that fewer
+    /// LSBs change than the payload would allow. A payload that needs more fails with
+    /// `SteganoError::CapacityExceeded`, citing the reduced capacity. Unveiling ignores it.
+    ///
+    /// Note `randomize_unused` still fills the whole rest of the carrier.
+    pub max_fill_fraction: Option<f32>,
 }
 
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
+impl Hash for CodecOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // destructured, so that a new option is not forgotten here
+        let Self {
+            color_channel_step_increment,
+            skip_alpha_channel,
+            concealer,
+            skip_last_row_and_column,
+            permutation_seed,
+            direction,
+            adaptive_threshold,
+            region,
+            bit_plane,
+            bit_order,
+            audio_channels,
+            audio_sample_step,
+            fec,
+            redundancy,
+            max_extract_bytes,
+            best_effort,
+            obfuscate_header,
+            record_carrier_size,
+            randomize_unused,
+            max_fill_fraction,
+        } = self;
+        color_channel_step_increment.hash(state);
+        skip_alpha_channel.hash(state);
+        concealer.hash(state);
+        skip_last_row_and_column.hash(state);
+        permutation_seed.hash(state);
+        direction.hash(state);
+        adaptive_threshold.hash(state);
+        region.hash(state);
+        bit_plane.hash(state);
+        bit_order.hash(state);
+        audio_channels.hash(state);
+        audio_sample_step.hash(state);
+        fec.hash(state);
+        redundancy.hash(state);
+        max_extract_bytes.hash(state);
+        best_effort.hash(state);
+        obfuscate_header.hash(state);
+        record_carrier_size.hash(state);
+        randomize_unused.hash(state);
+        max_fill_fraction.map(f32::to_bits).hash(state);
+    }
+}
+
+/// Decides if the data is embedded from the first or from the last color channel on
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum EmbedDirection {
+    #[default]
+    Forward,
+    Reverse,
+}
+
+/// Decides if the bits of a byte are hidden starting with the least or the most significant one
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum BitOrder {
+    #[default]
+    Lsb,
+    Msb,
+}
+
+/// Selects channels of an audio carrier, bit `n` stands for channel `n`,
+/// e.g. `ChannelMask(0b01)` is the left channel of a stereo carrier
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct ChannelMask(pub u32);
+
+impl ChannelMask {
+    pub const ALL: Self = Self(u32::MAX);
+
+    /// only the given channel
+    pub fn only(channel: u16) -> Self {
+        Self(1_u32.checked_shl(channel as u32).unwrap_or(0))
+    }
+
+    /// true if the channel is selected
+    pub fn contains(self, channel: usize) -> bool {
+        channel < u32::BITS as usize && self.0 & (1 << channel) != 0
+    }
+}
+
+impl Default for ChannelMask {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// A rectangle of pixels, `x` and `y` are the top left corner
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// true if the pixel at `x`, `y` lies inside
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x.checked_sub(self.x).is_some_and(|dx| dx < self.width)
+            && y.checked_sub(self.y).is_some_and(|dy| dy < self.height)
+    }
+}
+
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum Concealer {
     LeastSignificantBit,
     LowFrequencies,
@@ -46,6 +271,22 @@ impl Default for CodecOptions {
             skip_alpha_channel: true,
             concealer: Concealer::LeastSignificantBit,
             skip_last_row_and_column: true,
+            permutation_seed: None,
+            direction: EmbedDirection::Forward,
+            adaptive_threshold: None,
+            region: None,
+            bit_plane: 0,
+            bit_order: BitOrder::Lsb,
+            audio_channels: ChannelMask::ALL,
+            audio_sample_step: 1,
+            fec: None,
+            redundancy: 1,
+            max_extract_bytes: None,
+            best_effort: false,
+            obfuscate_header: false,
+            record_carrier_size: false,
+            randomize_unused: false,
+            max_fill_fraction: None,
         }
     }
 }
@@ -58,33 +299,103 @@ impl CodecOptions {
     pub fn get_skip_alpha_channel(&self) -> bool {
         self.skip_alpha_channel
     }
+
+    /// the bits of a color channel the concealer leaves untouched
+    pub(crate) fn cover_bits(&self) -> u8 {
+        match self.concealer {
+            Concealer::LeastSignificantBit => 1u8
+                .checked_shl(self.bit_plane.into())
+                .map_or(u8::MAX, |bit| !bit),
+            Concealer::LowFrequencies => 0b11110000,
+        }
+    }
+
+    /// the pixels that may carry data, `None` if all of them may
+    pub(crate) fn usable_pixels<P: Pixel<Subpixel = u8>>(
+        &self,
+        image: &ImageBuffer<P, Vec<u8>>,
+        skip_last_row_and_column: bool,
+    ) -> Option<Vec<bool>> {
+        let edges = self.adaptive_threshold.map(|threshold| {
+            edge_mask(
+                image,
+                skip_last_row_and_column,
+                threshold,
+                self.cover_bits(),
+            )
+        });
+        let region = self.region.map(|region| {
+            let (width, height) = image.dimensions();
+            region_mask(width, height, skip_last_row_and_column, region)
+        });
+        match (edges, region) {
+            (Some(edges), Some(region)) => {
+                Some(edges.iter().zip(region).map(|(a, b)| *a && b).collect())
+            }
+            (edges, region) => edges.or(region),
+        }
+    }
+
+    /// fails for a bit plane that is not within the 8 bits of a color channel
+    pub(crate) fn check_bit_plane(&self) -> Result<()> {
+        if self.bit_plane >= u8::BITS as u8 {
+            return Err(SteganoError::InvalidBitPlane(self.bit_plane));
+        }
+
+        Ok(())
+    }
+
+    /// the number of color channels that are used per pixel
+    pub(crate) fn channels_per_pixel<P: Pixel>(&self) -> usize {
+        let channels = if self.skip_alpha_channel { 3 } else { 4 };
+        channels.min(P::CHANNEL_COUNT as usize)
+    }
 }
 
 /// Factory for decoder and encoder
 pub struct LsbCodec;
 
 impl LsbCodec {
-    /// builds a LSB Image Decoder that implements Read
-    pub fn decoder<'i>(input: &'i RgbaImage, opts: &CodecOptions) -> Box<dyn Read + 'i> {
-        Box::new(UniversalDecoder::new(
-            ImageRgbaColor::new_with_options(input, opts),
-            match opts.concealer {
-                Concealer::LeastSignificantBit => OneBitUnveil,
-                Concealer::LowFrequencies => OneBitUnveil,
-            },
-        ))
-    }
-
-    /// builds a LSB Image Encoder that implements Write
-    pub fn encoder<'i>(carrier: &'i mut RgbaImage, opts: &CodecOptions) -> Box<dyn Write + 'i> {
-        let algorithm: HideAlgorithms = match opts.concealer {
-            Concealer::LeastSignificantBit => OneBitHide.into(),
-            Concealer::LowFrequencies => OneBitInLowFrequencyHide.into(),
+    /// builds a LSB Image Decoder that implements Read, for RGBA and grayscale images
+    pub fn decoder<'i, P: Pixel<Subpixel = u8> + 'i>(
+        input: &'i ImageBuffer<P, Vec<u8>>,
+        opts: &CodecOptions,
+    ) -> Box<dyn Read + 'i> {
+        let colors = ImageRgbaColor::new_with_options(input, opts);
+        let algorithm: UnveilAlgorithms = match (&opts.concealer, opts.bit_plane) {
+            (Concealer::LeastSignificantBit, 0) => OneBitUnveil.into(),
+            (Concealer::LeastSignificantBit, plane) => BitPlaneUnveil(plane).into(),
+            (Concealer::LowFrequencies, _) => OneBitUnveil.into(),
+        };
+        match opts.bit_order {
+            BitOrder::Lsb => Box::new(UniversalDecoder::<_, _, LittleEndian>::with_bit_order(
+                colors, algorithm,
+            )),
+            BitOrder::Msb => Box::new(UniversalDecoder::<_, _, BigEndian>::with_bit_order(
+                colors, algorithm,
+            )),
+        }
+    }
+
+    /// builds a LSB Image Encoder that implements Write, for RGBA and grayscale images
+    pub fn encoder<'i, P: Pixel<Subpixel = u8> + 'i>(
+        carrier: &'i mut ImageBuffer<P, Vec<u8>>,
+        opts: &CodecOptions,
+    ) -> Box<dyn Write + 'i> {
+        let algorithm: HideAlgorithms = match (&opts.concealer, opts.bit_plane) {
+            (Concealer::LeastSignificantBit, 0) => OneBitHide.into(),
+            (Concealer::LeastSignificantBit, plane) => BitPlaneHide(plane).into(),
+            (Concealer::LowFrequencies, _) => OneBitInLowFrequencyHide.into(),
         };
-        Box::new(UniversalEncoder::new(
-            ImageRgbaColorMut::new_with_options(carrier, opts),
-            algorithm,
-        ))
+        let colors = ImageRgbaColorMut::new_with_options(carrier, opts);
+        match opts.bit_order {
+            BitOrder::Lsb => Box::new(UniversalEncoder::<_, _, LittleEndian>::with_bit_order(
+                colors, algorithm,
+            )),
+            BitOrder::Msb => Box::new(UniversalEncoder::<_, _, BigEndian>::with_bit_order(
+                colors, algorithm,
+            )),
+        }
     }
 }
 
@@ -128,6 +439,7 @@ mod benchmarks {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Media;
 
     #[test]
     fn should_decode() {
@@ -164,4 +476,206 @@ mod tests {
         let msg = String::from_utf8(buf).expect("Cannot convert result to string");
         assert_eq!(msg, "Hello World!");
     }
+
+    #[test]
+    fn should_hide_only_in_busy_regions_when_adaptive() {
+        // left half is flat gray, right half is noise
+        let mut noise = crate::testing::noise(0x2545f4914f6cdd1d);
+        let mut image = image::RgbaImage::from_fn(64, 64, |x, _| {
+            if x < 32 {
+                image::Rgba([128, 128, 128, 255])
+            } else {
+                let mut next = || noise.next().unwrap();
+                image::Rgba([next(), next(), next(), 255])
+            }
+        });
+        let original = image.clone();
+        let opts = CodecOptions {
+            adaptive_threshold: Some(16),
+            ..Default::default()
+        };
+        let secret_message = [0xaa_u8; 128];
+
+        let full_capacity =
+            crate::media::Media::Image(image.clone()).capacity_bytes(&Default::default());
+        let adaptive_capacity = crate::media::Media::Image(image.clone()).capacity_bytes(&opts);
+        assert!(adaptive_capacity < full_capacity * 2 / 3);
+        assert!(adaptive_capacity >= secret_message.len());
+
+        LsbCodec::encoder(&mut image, &opts)
+            .write_all(&secret_message)
+            .expect("Cannot write to codec");
+
+        // the last flat column borders on the noise, so it counts as busy
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if x < 31 {
+                assert_eq!(
+                    pixel,
+                    original.get_pixel(x, y),
+                    "flat pixel {x}x{y} changed"
+                );
+            }
+        }
+        assert_ne!(image, original);
+
+        let mut buf = vec![0; secret_message.len()];
+        LsbCodec::decoder(&image, &opts)
+            .read_exact(&mut buf[..])
+            .expect("Cannot read from codec");
+        assert_eq!(buf, secret_message);
+    }
+
+    #[test]
+    fn should_keep_payloads_in_different_bit_planes_apart() {
+        let mut image = image::open("tests/images/plain/carrier-image.png")
+            .expect("Cannot open carrier image")
+            .to_rgba8();
+        let decoy = CodecOptions::default();
+        let secret = CodecOptions {
+            bit_plane: 1,
+            ..Default::default()
+        };
+
+        LsbCodec::encoder(&mut image, &decoy)
+            .write_all(b"nothing to see")
+            .expect("Cannot write decoy");
+        LsbCodec::encoder(&mut image, &secret)
+            .write_all(b"the real secret")
+            .expect("Cannot write secret");
+
+        let mut buf = vec![0; 14];
+        LsbCodec::decoder(&image, &decoy)
+            .read_exact(&mut buf)
+            .expect("Cannot read decoy");
+        assert_eq!(buf, b"nothing to see");
+
+        let mut buf = vec![0; 15];
+        LsbCodec::decoder(&image, &secret)
+            .read_exact(&mut buf)
+            .expect("Cannot read secret");
+        assert_eq!(buf, b"the real secret");
+    }
+
+    #[test]
+    fn should_reject_a_bit_plane_beyond_the_color_channel() {
+        let image = image::open("tests/images/plain/carrier-image.png")
+            .expect("Cannot open carrier image")
+            .to_rgba8();
+        let pristine = image.clone();
+        let mut media = Media::Image(image);
+        let opts = CodecOptions {
+            bit_plane: 8,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            media.hide_data(b"the real secret".to_vec(), &opts),
+            Err(SteganoError::InvalidBitPlane(8))
+        ));
+        let Media::Image(image) = &media else {
+            unreachable!()
+        };
+        assert_eq!(image, &pristine);
+        assert_eq!(opts.cover_bits(), u8::MAX);
+        let mut buf = vec![0; 4];
+        LsbCodec::decoder(image, &opts)
+            .read_exact(&mut buf)
+            .expect("Cannot read");
+        assert_eq!(buf, [0; 4]);
+    }
+
+    #[test]
+    fn should_hide_and_unveil_with_msb_first_bit_order() {
+        let mut image = image::open("tests/images/plain/carrier-image.png")
+            .expect("Cannot open carrier image")
+            .to_rgba8();
+        let msb = CodecOptions {
+            bit_order: BitOrder::Msb,
+            ..Default::default()
+        };
+
+        LsbCodec::encoder(&mut image, &msb)
+            .write_all(b"Hello World!")
+            .expect("Cannot write to codec");
+
+        let mut buf = vec![0; 12];
+        LsbCodec::decoder(&image, &msb)
+            .read_exact(&mut buf)
+            .expect("Cannot read 12 bytes from codec");
+        assert_eq!(buf, b"Hello World!");
+
+        LsbCodec::decoder(&image, &CodecOptions::default())
+            .read_exact(&mut buf)
+            .expect("Cannot read 12 bytes from codec");
+        assert_ne!(buf, b"Hello World!");
+        // every byte comes out mirrored
+        assert_eq!(buf[0], b'H'.reverse_bits());
+    }
+
+    #[test]
+    fn should_round_trip_on_a_non_square_image_with_and_without_the_last_row_and_column() {
+        for skip in [true, false] {
+            let original = image::RgbaImage::from_fn(4, 6, |x, y| {
+                image::Rgba([(x * 40) as u8, (y * 30) as u8, 128, 255])
+            });
+            let mut image = original.clone();
+            let opts = CodecOptions {
+                skip_last_row_and_column: skip,
+                ..Default::default()
+            };
+            // the traversed color channels hold exactly this many bytes
+            let secret: &[u8] = if skip { b"4x6px" } else { b"4x6pixels" };
+
+            LsbCodec::encoder(&mut image, &opts)
+                .write_all(secret)
+                .expect("Cannot write to codec");
+            let mut buf = vec![0; secret.len()];
+            LsbCodec::decoder(&image, &opts)
+                .read_exact(&mut buf)
+                .expect("Cannot read from codec");
+            assert_eq!(buf, secret, "skip: {skip}");
+
+            let last_row_or_column_changed = image
+                .enumerate_pixels()
+                .filter(|(x, y, _)| *x == 3 || *y == 5)
+                .any(|(x, y, p)| p != original.get_pixel(x, y));
+            assert_eq!(last_row_or_column_changed, !skip, "skip: {skip}");
+        }
+    }
+
+    #[test]
+    fn should_only_touch_the_pixels_of_the_region() {
+        let original = image::RgbaImage::from_fn(128, 96, |x, y| {
+            image::Rgba([(x * 2) as u8, (y * 2) as u8, 128, 255])
+        });
+        let mut image = original.clone();
+        let region = Rect::new(64, 32, 32, 32);
+        let opts = CodecOptions {
+            region: Some(region),
+            ..Default::default()
+        };
+        // 3 color channels of the 32x32 pixels, one bit each, all of it filled
+        let secret: Vec<u8> = (0..32 * 32 * 3 / 8).map(|i| !(i as u8)).collect();
+        assert_eq!(
+            crate::Media::Image(original.clone()).capacity_bytes(&opts),
+            secret.len()
+        );
+
+        LsbCodec::encoder(&mut image, &opts)
+            .write_all(&secret)
+            .expect("Cannot write to codec");
+        let mut buf = vec![0; secret.len()];
+        LsbCodec::decoder(&image, &opts)
+            .read_exact(&mut buf)
+            .expect("Cannot read from codec");
+        assert_eq!(buf, secret);
+
+        assert!(image
+            .enumerate_pixels()
+            .filter(|(x, y, _)| !region.contains(*x, *y))
+            .all(|(x, y, p)| p == original.get_pixel(x, y)));
+        assert!(image
+            .enumerate_pixels()
+            .any(|(x, y, p)| p != original.get_pixel(x, y)));
+    }
 }
@@ -1,6 +1,17 @@
+mod blockiness;
 pub mod decoder;
 pub mod encoder;
 mod iterators;
 pub mod lsb_codec;
+mod placement;
+mod png_chunks;
+mod watermark;
 
-pub use lsb_codec::{CodecOptions, LsbCodec};
+pub(crate) use blockiness::looks_transcoded_from_jpeg;
+pub(crate) use iterators::traversed_area;
+pub use lsb_codec::{BitOrder, ChannelMask, CodecOptions, EmbedDirection, LsbCodec, Rect};
+pub(crate) use placement::{read_placement_header, write_placement_header};
+pub(crate) use png_chunks::is_indexed_png;
+pub use png_chunks::PngAncillaryChunks;
+pub(crate) use watermark::draw_watermark;
+pub use watermark::Corner;
@@ -0,0 +1,213 @@
+//! Keeps the codec options that decide where the bits go in the last row of an image.
+//!
+//! The last row is never traversed when `skip_last_row_and_column` is set, so the header does
+//! not collide with the data and can always be read with fixed positions, one bit in the least
+//! significant bit of each color channel from the left. Unveiling then configures itself.
+
+use image::{ImageBuffer, Pixel};
+use log::warn;
+
+use super::{BitOrder, CodecOptions};
+
+const PLACEMENT_MAGIC: [u8; 2] = *b"Sp";
+/// the magic, the step increment and the flags
+const PLACEMENT_HEADER_LEN: usize = PLACEMENT_MAGIC.len() + 2;
+/// the alpha channel carries data too
+const FLAG_WITH_ALPHA: u8 = 0b01;
+/// the bits of a byte are hidden starting with the most significant one
+const FLAG_MSB_FIRST: u8 = 0b10;
+
+/// Writes the placement options into the last row, or clears a header of an earlier hiding
+/// if the options are the default ones, those need no header.
+///
+/// Without `skip_last_row_and_column` or with a step beyond 255 the placement cannot be
+/// recorded, that is only warned about, as unveiling with the same options still works.
+pub(crate) fn write_placement_header<P: Pixel<Subpixel = u8>>(
+    image: &mut ImageBuffer<P, Vec<u8>>,
+    opts: &CodecOptions,
+) {
+    if is_default_placement(opts) {
+        if read_placement_header(image, opts).is_some() {
+            // one flipped bit of the magic is enough to invalidate the old header
+            let mut bits = [false; 1];
+            read_bits(image, &mut bits);
+            write_bits(image, &[!bits[0]]);
+        }
+        return;
+    }
+    let Some(header) = header_of(opts).filter(|_| opts.skip_last_row_and_column) else {
+        warn!(
+            "The placement options are not recorded in the image, unveiling needs the same options"
+        );
+        return;
+    };
+    let bits: Vec<bool> = header
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1 == 1))
+        .collect();
+    write_bits(image, &bits);
+}
+
+/// The options with the placement taken from the header in the last row,
+/// `None` if there is no header
+pub(crate) fn read_placement_header<P: Pixel<Subpixel = u8>>(
+    image: &ImageBuffer<P, Vec<u8>>,
+    opts: &CodecOptions,
+) -> Option<CodecOptions> {
+    if !opts.skip_last_row_and_column {
+        return None;
+    }
+    let mut bits = [false; PLACEMENT_HEADER_LEN * 8];
+    if !read_bits(image, &mut bits) {
+        return None;
+    }
+    let mut header = [0_u8; PLACEMENT_HEADER_LEN];
+    for (byte, bits) in header.iter_mut().zip(bits.chunks(8)) {
+        *byte = bits.iter().fold(0, |byte, bit| byte << 1 | *bit as u8);
+    }
+    let [m0, m1, step, flags] = header;
+    if [m0, m1] != PLACEMENT_MAGIC || step == 0 || flags & !(FLAG_WITH_ALPHA | FLAG_MSB_FIRST) != 0
+    {
+        return None;
+    }
+
+    Some(CodecOptions {
+        color_channel_step_increment: step as usize,
+        skip_alpha_channel: flags & FLAG_WITH_ALPHA == 0,
+        bit_order: if flags & FLAG_MSB_FIRST == 0 {
+            BitOrder::Lsb
+        } else {
+            BitOrder::Msb
+        },
+        ..opts.clone()
+    })
+}
+
+/// the default placement needs no header
+fn is_default_placement(opts: &CodecOptions) -> bool {
+    let default = CodecOptions::default();
+
+    opts.color_channel_step_increment == default.color_channel_step_increment
+        && opts.skip_alpha_channel == default.skip_alpha_channel
+        && opts.bit_order == default.bit_order
+}
+
+/// the header bytes, `None` for a step that does not fit a byte
+fn header_of(opts: &CodecOptions) -> Option<[u8; PLACEMENT_HEADER_LEN]> {
+    let step = u8::try_from(opts.color_channel_step_increment)
+        .ok()
+        .filter(|step| *step > 0)?;
+    let mut flags = 0;
+    if !opts.skip_alpha_channel {
+        flags |= FLAG_WITH_ALPHA;
+    }
+    if opts.bit_order == BitOrder::Msb {
+        flags |= FLAG_MSB_FIRST;
+    }
+
+    Some([PLACEMENT_MAGIC[0], PLACEMENT_MAGIC[1], step, flags])
+}
+
+/// the color channels of the last row that hold the header, from the left
+fn slots(width: u32, channels: usize) -> impl Iterator<Item = (u32, usize)> {
+    let colors = if channels >= 3 { 3 } else { 1 };
+    (0..width).flat_map(move |x| (0..colors).map(move |c| (x, c)))
+}
+
+/// false if the last row is too short for the bits
+fn read_bits<P: Pixel<Subpixel = u8>>(image: &ImageBuffer<P, Vec<u8>>, bits: &mut [bool]) -> bool {
+    let (width, height) = image.dimensions();
+    let slots: Vec<_> = slots(width, P::CHANNEL_COUNT as usize)
+        .take(bits.len())
+        .collect();
+    if height == 0 || slots.len() < bits.len() {
+        return false;
+    }
+    for (bit, (x, c)) in bits.iter_mut().zip(slots) {
+        *bit = image.get_pixel(x, height - 1).channels()[c] & 1 == 1;
+    }
+
+    true
+}
+
+fn write_bits<P: Pixel<Subpixel = u8>>(image: &mut ImageBuffer<P, Vec<u8>>, bits: &[bool]) {
+    let (width, height) = image.dimensions();
+    if height == 0 {
+        return;
+    }
+    for (bit, (x, c)) in bits.iter().zip(slots(width, P::CHANNEL_COUNT as usize)) {
+        let channel = &mut image.get_pixel_mut(x, height - 1).channels_mut()[c];
+        *channel = (*channel & !1) | *bit as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    #[test]
+    fn should_read_back_the_placement_and_clear_it_again() {
+        let mut image = RgbaImage::from_pixel(16, 4, Rgba([100, 101, 102, 255]));
+        let opts = CodecOptions {
+            color_channel_step_increment: 3,
+            bit_order: BitOrder::Msb,
+            ..Default::default()
+        };
+        assert!(read_placement_header(&image, &CodecOptions::default()).is_none());
+
+        write_placement_header(&mut image, &opts);
+        let read = read_placement_header(&image, &CodecOptions::default())
+            .expect("The header was not found");
+        assert_eq!(read.color_channel_step_increment, 3);
+        assert_eq!(read.bit_order, BitOrder::Msb);
+        assert!(read.skip_alpha_channel);
+        // only the last row is touched
+        assert!(image
+            .rows()
+            .take(3)
+            .flatten()
+            .all(|p| *p == Rgba([100, 101, 102, 255])));
+
+        write_placement_header(&mut image, &CodecOptions::default());
+        assert!(read_placement_header(&image, &CodecOptions::default()).is_none());
+    }
+
+    #[test]
+    fn should_need_no_header_for_the_default_placement() {
+        let mut image = RgbaImage::from_pixel(16, 4, Rgba([100, 101, 102, 255]));
+        let before = image.clone();
+        write_placement_header(&mut image, &CodecOptions::default());
+        assert_eq!(image, before);
+
+        // a row too short for the header
+        let mut narrow = RgbaImage::from_pixel(4, 4, Rgba([100, 101, 102, 255]));
+        let opts = CodecOptions {
+            color_channel_step_increment: 2,
+            ..Default::default()
+        };
+        write_placement_header(&mut narrow, &opts);
+        assert!(read_placement_header(&narrow, &opts).is_none());
+    }
+
+    #[test]
+    fn should_not_record_what_does_not_fit_the_last_row() {
+        let image = RgbaImage::from_pixel(16, 4, Rgba([100, 101, 102, 255]));
+        for opts in [
+            CodecOptions {
+                color_channel_step_increment: 3,
+                skip_last_row_and_column: false,
+                ..Default::default()
+            },
+            CodecOptions {
+                color_channel_step_increment: 256,
+                ..Default::default()
+            },
+        ] {
+            let mut written = image.clone();
+            write_placement_header(&mut written, &opts);
+            assert_eq!(written, image);
+        }
+    }
+}
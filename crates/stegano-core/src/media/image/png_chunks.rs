@@ -0,0 +1,108 @@
+use byteorder::{BigEndian, ByteOrder};
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+/// ancillary chunk types that carry metadata and are preserved across a hide operation
+const PRESERVED_CHUNK_TYPES: [&[u8; 4]; 4] = [b"tEXt", b"zTXt", b"iTXt", b"eXIf"];
+
+/// Ancillary PNG chunks (text and EXIF) of a carrier image, kept verbatim including their CRC
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PngAncillaryChunks {
+    chunks: Vec<u8>,
+}
+
+impl PngAncillaryChunks {
+    /// collects all preserved chunks of a PNG file, an invalid PNG just leads to no chunks
+    pub fn from_png_bytes(png: &[u8]) -> Self {
+        let mut chunks = Vec::new();
+        for (chunk_type, chunk) in iter_chunks(png) {
+            if PRESERVED_CHUNK_TYPES.contains(&chunk_type) {
+                chunks.extend_from_slice(chunk);
+            }
+        }
+
+        Self { chunks }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// inserts the chunks into a freshly encoded PNG, right after the `IHDR` chunk
+    pub fn insert_into(&self, png: Vec<u8>) -> Vec<u8> {
+        let Some((b"IHDR", ihdr)) = iter_chunks(&png).next() else {
+            return png;
+        };
+        let split_at = PNG_SIGNATURE.len() + ihdr.len();
+
+        let mut out = Vec::with_capacity(png.len() + self.chunks.len());
+        out.extend_from_slice(&png[..split_at]);
+        out.extend_from_slice(&self.chunks);
+        out.extend_from_slice(&png[split_at..]);
+
+        out
+    }
+}
+
+/// true if the PNG stores palette indices instead of colors, the color type in `IHDR` is 3
+pub(crate) fn is_indexed_png(png: &[u8]) -> bool {
+    const COLOR_TYPE_OFFSET: usize = 8 + 9;
+    const COLOR_TYPE_INDEXED: u8 = 3;
+
+    match iter_chunks(png).next() {
+        Some((b"IHDR", ihdr)) => ihdr.get(COLOR_TYPE_OFFSET) == Some(&COLOR_TYPE_INDEXED),
+        _ => false,
+    }
+}
+
+/// iterates over the chunks of a PNG, yields the chunk type and the whole chunk (length, type, data and crc)
+fn iter_chunks(png: &[u8]) -> impl Iterator<Item = (&[u8; 4], &[u8])> {
+    let mut rest = png.strip_prefix(&PNG_SIGNATURE[..]).unwrap_or_default();
+
+    std::iter::from_fn(move || {
+        if rest.len() < 12 {
+            return None;
+        }
+        let data_len = BigEndian::read_u32(&rest[..4]) as usize;
+        let chunk_len = data_len.checked_add(12)?;
+        if rest.len() < chunk_len {
+            return None;
+        }
+        let (chunk, tail) = rest.split_at(chunk_len);
+        rest = tail;
+
+        Some((chunk[4..8].try_into().ok()?, chunk))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::png_chunk;
+
+    #[test]
+    fn should_collect_and_insert_exif_chunks() {
+        let png = std::fs::read("tests/images/Base.png").unwrap();
+        assert!(PngAncillaryChunks::from_png_bytes(&png).is_empty());
+
+        let exif = png_chunk(b"eXIf", b"MM\0*\0\0\0\x08\0\0");
+        let with_exif = PngAncillaryChunks {
+            chunks: exif.clone(),
+        }
+        .insert_into(png.clone());
+        assert_eq!(with_exif.len(), png.len() + exif.len());
+
+        let chunks = PngAncillaryChunks::from_png_bytes(&with_exif);
+        assert_eq!(chunks.chunks, exif);
+        image::load_from_memory(&with_exif).expect("PNG with eXIf chunk should stay valid");
+    }
+
+    #[test]
+    fn should_ignore_non_png_data() {
+        assert!(PngAncillaryChunks::from_png_bytes(b"no png at all").is_empty());
+        assert_eq!(
+            PngAncillaryChunks::default().insert_into(b"no png".to_vec()),
+            b"no png"
+        );
+    }
+}
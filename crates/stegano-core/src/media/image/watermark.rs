@@ -0,0 +1,294 @@
+//! Burns a visible text into an image, with a tiny built-in 5x7 bitmap font.
+//!
+//! The font knows the latin letters, digits, some punctuation and `©`, lowercase letters are
+//! drawn as uppercase ones and unknown characters as `?`.
+
+use image::{ImageBuffer, Pixel};
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+/// the glyph and one column of space
+const GLYPH_ADVANCE: u32 = GLYPH_WIDTH + 1;
+
+/// The corner of the image a watermark is placed in
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+/// Draws `text` in white with a black shadow into the corner of the image. The glyphs grow with
+/// the image, one font pixel is a square of `scale` pixels. What does not fit is cut off.
+pub(crate) fn draw_watermark<P: Pixel<Subpixel = u8>>(
+    image: &mut ImageBuffer<P, Vec<u8>>,
+    text: &str,
+    corner: Corner,
+) {
+    let (width, height) = image.dimensions();
+    let scale = (width.min(height) / 128).max(1);
+    let margin = 2 * scale;
+    let glyphs: Vec<[u8; 7]> = text.chars().map(glyph).collect();
+    let text_width = (glyphs.len() as u32 * GLYPH_ADVANCE).saturating_sub(1) * scale;
+    let text_height = GLYPH_HEIGHT * scale;
+
+    let left = match corner {
+        Corner::TopLeft | Corner::BottomLeft => margin as i64,
+        Corner::TopRight | Corner::BottomRight => {
+            width as i64 - (margin + text_width + scale) as i64
+        }
+    };
+    let top = match corner {
+        Corner::TopLeft | Corner::TopRight => margin as i64,
+        Corner::BottomLeft | Corner::BottomRight => {
+            height as i64 - (margin + text_height + scale) as i64
+        }
+    };
+
+    // the shadow first, one font pixel to the bottom right, then the text on top of it
+    for (offset, value) in [(scale as i64, u8::MIN), (0, u8::MAX)] {
+        for (i, rows) in glyphs.iter().enumerate() {
+            let glyph_left = left + offset + (i as u32 * GLYPH_ADVANCE * scale) as i64;
+            for (row, bits) in rows.iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                        continue;
+                    }
+                    let x = glyph_left + (column * scale) as i64;
+                    let y = top + offset + (row as u32 * scale) as i64;
+                    fill_square(image, x, y, scale, value);
+                }
+            }
+        }
+    }
+}
+
+/// sets the colors of the square to `value`, it becomes opaque
+fn fill_square<P: Pixel<Subpixel = u8>>(
+    image: &mut ImageBuffer<P, Vec<u8>>,
+    x: i64,
+    y: i64,
+    size: u32,
+    value: u8,
+) {
+    // luma alpha and rgba pixels have an even channel count, the last channel is alpha
+    let color_channels = match P::CHANNEL_COUNT {
+        2 | 4 => P::CHANNEL_COUNT as usize - 1,
+        n => n as usize,
+    };
+    for y in y..y + size as i64 {
+        for x in x..x + size as i64 {
+            let (Ok(x), Ok(y)) = (u32::try_from(x), u32::try_from(y)) else {
+                continue;
+            };
+            let Some(pixel) = image.get_pixel_mut_checked(x, y) else {
+                continue;
+            };
+            for (i, channel) in pixel.channels_mut().iter_mut().enumerate() {
+                *channel = if i < color_channels { value } else { u8::MAX };
+            }
+        }
+    }
+}
+
+/// the rows of the glyph, the upper bit is the left column
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        ' ' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+        'A' => [
+            0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+        'B' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110,
+        ],
+        'C' => [
+            0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110,
+        ],
+        'D' => [
+            0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110,
+        ],
+        'E' => [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111,
+        ],
+        'F' => [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+        'G' => [
+            0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111,
+        ],
+        'H' => [
+            0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+        'I' => [
+            0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        'J' => [
+            0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100,
+        ],
+        'K' => [
+            0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001,
+        ],
+        'L' => [
+            0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111,
+        ],
+        'M' => [
+            0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001,
+        ],
+        'N' => [
+            0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001,
+        ],
+        'O' => [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'P' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+        'Q' => [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101,
+        ],
+        'R' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001,
+        ],
+        'S' => [
+            0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110,
+        ],
+        'T' => [
+            0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+        'U' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'V' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+        ],
+        'W' => [
+            0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010,
+        ],
+        'X' => [
+            0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001,
+        ],
+        'Y' => [
+            0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100,
+        ],
+        'Z' => [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111,
+        ],
+        '0' => [
+            0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110,
+        ],
+        '1' => [
+            0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        '2' => [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111,
+        ],
+        '3' => [
+            0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110,
+        ],
+        '4' => [
+            0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010,
+        ],
+        '5' => [
+            0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110,
+        ],
+        '6' => [
+            0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110,
+        ],
+        '7' => [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000,
+        ],
+        '8' => [
+            0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110,
+        ],
+        '9' => [
+            0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100,
+        ],
+        '.' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100,
+        ],
+        ',' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000,
+        ],
+        '-' => [
+            0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000,
+        ],
+        '_' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111,
+        ],
+        ':' => [
+            0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000,
+        ],
+        '!' => [
+            0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100,
+        ],
+        '?' => [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100,
+        ],
+        '/' => [
+            0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000,
+        ],
+        '@' => [
+            0b01110, 0b10001, 0b10111, 0b10101, 0b10111, 0b10000, 0b01110,
+        ],
+        '&' => [
+            0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101,
+        ],
+        '(' => [
+            0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010,
+        ],
+        ')' => [
+            0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000,
+        ],
+        '\'' => [
+            0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+        '©' => [
+            0b01110, 0b10001, 0b10111, 0b11001, 0b10111, 0b10001, 0b01110,
+        ],
+        _ => glyph('?'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_draw_into_the_chosen_corner_only() {
+        let black = image::Rgba([0, 0, 0, 255]);
+        for corner in [
+            Corner::TopLeft,
+            Corner::TopRight,
+            Corner::BottomLeft,
+            Corner::BottomRight,
+        ] {
+            let mut image = image::RgbaImage::from_pixel(128, 48, black);
+            draw_watermark(&mut image, "© 2024", corner);
+
+            let white: Vec<(u32, u32)> = image
+                .enumerate_pixels()
+                .filter(|(_, _, p)| p.0 == [255, 255, 255, 255])
+                .map(|(x, y, _)| (x, y))
+                .collect();
+            assert!(!white.is_empty());
+            let left = matches!(corner, Corner::TopLeft | Corner::BottomLeft);
+            let top = matches!(corner, Corner::TopLeft | Corner::TopRight);
+            assert!(white
+                .iter()
+                .all(|(x, y)| (*x < 64) == left && (*y < 24) == top));
+        }
+    }
+
+    #[test]
+    fn should_cut_off_text_that_does_not_fit() {
+        let mut image = image::GrayImage::new(10, 10);
+        draw_watermark(&mut image, "TOO LONG FOR THIS", Corner::TopRight);
+        draw_watermark(&mut image, "TOO LONG FOR THIS", Corner::BottomLeft);
+
+        assert!(image.pixels().any(|p| p.0 == [255]));
+    }
+}
@@ -1,14 +1,41 @@
 pub mod audio;
+mod carrier_size;
+mod chi_square;
+mod fec;
 pub mod image;
+mod obfuscation;
 pub mod payload;
 mod primitives;
+mod redundancy;
+mod stego_codec;
 mod types;
 
 use std::path::Path;
 
+pub use carrier_size::CarrierSize;
+pub use fec::FecConfig;
+pub use obfuscation::HEADER_KEYSTREAM;
 pub use primitives::*;
+pub use stego_codec::{LsbStegoCodec, StegoCodec};
 pub use types::*;
 
 pub trait Persist {
     fn save_as(&mut self, _: &Path) -> crate::Result<()>;
 }
+
+/// Runs `f` and logs how long it took, e.g. a codec loop. This needs the `bench-timing` feature,
+/// without it `f` just runs.
+#[cfg(feature = "bench-timing")]
+pub(crate) fn timed<T>(label: &str, f: impl FnOnce() -> T) -> T {
+    let start = std::time::Instant::now();
+    let result = f();
+    log::debug!("{label} took {:?}", start.elapsed());
+
+    result
+}
+
+#[cfg(not(feature = "bench-timing"))]
+#[inline(always)]
+pub(crate) fn timed<T>(_label: &str, f: impl FnOnce() -> T) -> T {
+    f()
+}
@@ -0,0 +1,52 @@
+//! Hides the recognizable header of an unencrypted payload, see [`crate::CodecOptions::obfuscate_header`].
+//!
+//! The version byte and the length header are XORed with [`HEADER_KEYSTREAM`], a constant that
+//! is published right here. Anyone who knows it restores the header, this is no encryption.
+//! It only keeps the header from standing out, e.g. in a `strings` dump of the LSBs.
+
+use std::io::{Cursor, Read};
+
+/// The ASCII bytes of `"SteganoRs"`, it covers the version byte and the long length header
+pub const HEADER_KEYSTREAM: [u8; 9] = *b"SteganoRs";
+
+/// XORs the first bytes of `data` with the keystream, applied twice it restores the data
+pub(crate) fn xor_header(data: &mut [u8]) {
+    data.iter_mut()
+        .zip(HEADER_KEYSTREAM)
+        .for_each(|(b, k)| *b ^= k);
+}
+
+/// Restores the header of the data read from `raw`, the rest is passed on untouched
+pub(crate) fn decoder<'a>(mut raw: Box<dyn Read + 'a>) -> Box<dyn Read + 'a> {
+    let mut header = Vec::with_capacity(HEADER_KEYSTREAM.len());
+    if raw
+        .by_ref()
+        .take(HEADER_KEYSTREAM.len() as u64)
+        .read_to_end(&mut header)
+        .is_err()
+    {
+        return raw;
+    }
+    xor_header(&mut header);
+
+    Box::new(Cursor::new(header).chain(raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_restore_the_header() {
+        let mut data = vec![4, 0, 0, 0, 42, 0xff, 1, 2, 3, 4, 5];
+        xor_header(&mut data);
+        assert_ne!(&data[..5], &[4, 0, 0, 0, 42]);
+        assert_eq!(&data[9..], &[4, 5], "only the header is touched");
+
+        let mut restored = Vec::new();
+        decoder(Box::new(Cursor::new(data)))
+            .read_to_end(&mut restored)
+            .unwrap();
+        assert_eq!(restored, vec![4, 0, 0, 0, 42, 0xff, 1, 2, 3, 4, 5]);
+    }
+}
@@ -1,6 +1,7 @@
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use std::io::Read;
+use std::io::{Read, Write};
 
+use crate::error::SteganoError;
 use crate::result::Result;
 
 pub(crate) const TEXT_ONLY: u8 = 1 << 0;
@@ -9,6 +10,11 @@ pub(crate) const TEXT_AND_DOCUMENTS: u8 = 1 << 2;
 pub(crate) const LENGTH_HEADER: u8 = 1 << 3;
 pub(crate) const AES_CRYPTO: u8 = 1 << 4;
 pub(crate) const CHA_CRYPTO: u8 = 1 << 5;
+/// the length header has 8 instead of 4 bytes, only set for payloads beyond `u32::MAX`.
+/// The FEC flag sets this bit too, but always along with the upper bit that no version uses.
+pub(crate) const LONG_LENGTH_HEADER: u8 = 1 << 6;
+/// the extra bytes of a long length header
+pub(crate) const LONG_LENGTH_HEADER_EXTRA: usize = 4;
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum PayloadCodecFeatures {
@@ -18,6 +24,7 @@ pub enum PayloadCodecFeatures {
     LengthHeader,
     AesCrypto,
     ChaCrypto,
+    LongLengthHeader,
     MixedFeatures(u8),
 }
 
@@ -40,6 +47,7 @@ impl From<PayloadCodecFeatures> for u8 {
             PayloadCodecFeatures::LengthHeader => LENGTH_HEADER,
             PayloadCodecFeatures::AesCrypto => AES_CRYPTO,
             PayloadCodecFeatures::ChaCrypto => CHA_CRYPTO,
+            PayloadCodecFeatures::LongLengthHeader => LONG_LENGTH_HEADER,
             PayloadCodecFeatures::MixedFeatures(other) => other,
         }
     }
@@ -87,18 +95,54 @@ where
     }
 }
 
+/// Writes the version byte and the length header of a payload with `len` bytes, payloads beyond
+/// `u32::MAX` get the 8 byte header and `LongLengthHeader` is added to the version
+pub(crate) fn write_length_header(out: &mut dyn Write, version: u8, len: u64) -> Result<()> {
+    match u32::try_from(len) {
+        Ok(len) => {
+            out.write_u8(version & !LONG_LENGTH_HEADER)?;
+            out.write_u32::<BigEndian>(len)?;
+        }
+        Err(_) => {
+            out.write_u8(version | LONG_LENGTH_HEADER)?;
+            out.write_u64::<BigEndian>(len)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the length header that follows the version byte, `long` if the version has `LongLengthHeader`
+pub(crate) fn read_length_header(content: &mut dyn Read, long: bool) -> Result<u64> {
+    Ok(if long {
+        content.read_u64::<BigEndian>()?
+    } else {
+        content.read_u32::<BigEndian>()?.into()
+    })
+}
+
 #[derive(Debug, Default)]
-pub struct PayloadDecoderWithLengthHeader;
+pub struct PayloadDecoderWithLengthHeader {
+    long: bool,
+}
+
+impl PayloadDecoderWithLengthHeader {
+    /// the decoder that reads the length header of the given version
+    pub fn new(features: PayloadCodecFeatures) -> Self {
+        Self {
+            long: features.has_feature(PayloadCodecFeatures::LongLengthHeader),
+        }
+    }
+}
+
 impl PayloadDecoder for PayloadDecoderWithLengthHeader {
     fn decode(&self, content: &mut dyn Read) -> Result<Vec<u8>> {
-        let len = content.read_u32::<BigEndian>()? as usize;
+        // more than is addressable cannot be there, it is reported as truncated
+        let len = usize::try_from(read_length_header(content, self.long)?).unwrap_or(usize::MAX);
         let mut buffer = Vec::new();
         content.read_to_end(&mut buffer)?;
         if len > buffer.len() {
-            panic!(
-                "read len value cannot be bigger `{len}` than the actual buffer `{}`",
-                buffer.len()
-            );
+            return Err(SteganoError::TruncatedRawData(len, buffer.len()));
         }
         buffer.truncate(len);
 
@@ -127,8 +171,7 @@ impl PayloadEncoder for PayloadEncoderWithLengthHeader {
         content.read_to_end(&mut src)?;
 
         let mut buffer = Vec::with_capacity(src.len() + 6);
-        buffer.write_u8(self.version().into())?;
-        buffer.write_u32::<BigEndian>(src.len() as u32)?;
+        write_length_header(&mut buffer, self.version().into(), src.len() as u64)?;
         buffer.extend_from_slice(&src[..]);
         buffer.write_u8(0xff)?;
 
@@ -188,4 +231,30 @@ mod tests {
         assert!(f.has_feature(PayloadCodecFeatures::LengthHeader));
         assert!(f.has_feature(PayloadCodecFeatures::ChaCrypto));
     }
+
+    #[test]
+    fn should_widen_the_length_header_beyond_4_gib() {
+        let version = u8::from(
+            PayloadCodecFeatures::TextAndDocuments.add_feature(PayloadCodecFeatures::LengthHeader),
+        );
+        let huge = 5 * 1024 * 1024 * 1024_u64;
+
+        let mut header = Vec::new();
+        write_length_header(&mut header, version, huge).unwrap();
+        assert_eq!(header.len(), 1 + 8);
+        let written = PayloadCodecFeatures::MixedFeatures(header[0]);
+        assert!(written.has_feature(PayloadCodecFeatures::LongLengthHeader));
+        assert_eq!(
+            crate::PayloadVersion::try_from(header[0]).unwrap(),
+            crate::PayloadVersion::V76
+        );
+        let long = written.has_feature(PayloadCodecFeatures::LongLengthHeader);
+        assert_eq!(read_length_header(&mut &header[1..], long).unwrap(), huge);
+
+        // small payloads keep the 4 byte header that older versions can read
+        let mut header = Vec::new();
+        write_length_header(&mut header, version, 42).unwrap();
+        assert_eq!(header, [version, 0, 0, 0, 42]);
+        assert_eq!(read_length_header(&mut &header[1..], false).unwrap(), 42);
+    }
 }
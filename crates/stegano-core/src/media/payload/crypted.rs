@@ -1,7 +1,11 @@
 use std::io::Read;
 
-use stegano_seasmoke::decrypt_data;
-use stegano_seasmoke::encrypt_data;
+use stegano_seasmoke::{
+    decrypt_data, decrypt_data_once, decrypt_data_with_key, decrypt_legacy_data,
+};
+use stegano_seasmoke::{encrypt_data, encrypt_data_with_key, SeasmokeError};
+use stegano_seasmoke::{generate_key, Key};
+use zeroize::Zeroizing;
 
 use super::FabA;
 use super::PayloadCodecFactory;
@@ -11,15 +15,55 @@ use super::{PayloadCodec, PayloadDecoder};
 use crate::result::Result;
 use crate::SteganoError;
 
-#[derive(Debug, PartialEq, Eq)]
+/// Marks an encrypted payload, it is not secret and allows to detect encryption without the password
+pub(crate) const CRYPT_MAGIC: [u8; 4] = *b"StgC";
+/// Version of the encrypted payload header, follows directly after `CRYPT_MAGIC`
+pub(crate) const CRYPT_HEADER_VERSION: u8 = 1;
+/// Version of the header of a payload encrypted for several passwords. The header is followed by
+/// the number of wrapped keys and the data key encrypted with each password, then the ciphertext.
+pub(crate) const CRYPT_HEADER_VERSION_MULTI: u8 = 2;
+const CRYPT_HEADER_LEN: usize = CRYPT_MAGIC.len() + 1;
+/// The most passwords a payload is encrypted for, unveiling tries every wrapped key with argon2
+pub const MAX_PASSWORDS: usize = 16;
+/// a data key encrypted with one of the passwords
+const WRAPPED_KEY_LEN: usize = std::mem::size_of::<Key>() + stegano_seasmoke::PASSWORD_OVERHEAD;
+/// the bytes a password encryption adds to a payload
+pub(crate) const PASSWORD_CRYPT_OVERHEAD: usize =
+    CRYPT_HEADER_LEN + stegano_seasmoke::PASSWORD_OVERHEAD;
+/// the bytes an encryption with a raw key adds to a payload
+pub(crate) const KEY_CRYPT_OVERHEAD: usize = CRYPT_HEADER_LEN + stegano_seasmoke::KEY_OVERHEAD;
+
+/// the bytes an encryption for `passwords` passwords adds to a payload
+pub(crate) const fn multi_password_crypt_overhead(passwords: usize) -> usize {
+    CRYPT_HEADER_LEN + 1 + passwords * WRAPPED_KEY_LEN + stegano_seasmoke::KEY_OVERHEAD
+}
+
+/// Checks if the payload starts with the header of an encrypted payload
+pub(crate) fn has_crypt_header(payload: &[u8]) -> bool {
+    crypt_header_version(payload).is_some()
+}
+
+fn crypt_header_version(payload: &[u8]) -> Option<u8> {
+    if payload.len() < CRYPT_HEADER_LEN || payload[..CRYPT_MAGIC.len()] != CRYPT_MAGIC {
+        return None;
+    }
+
+    match payload[CRYPT_MAGIC.len()] {
+        v @ (CRYPT_HEADER_VERSION | CRYPT_HEADER_VERSION_MULTI) => Some(v),
+        _ => None,
+    }
+}
+
+/// Factory for codecs that encrypt with a password, the password is scrubbed from memory on drop
+#[derive(Debug)]
 pub struct FabS {
-    pub password: String,
+    pub password: Zeroizing<String>,
 }
 
 impl FabS {
     pub fn new<I: Into<String>>(password: I) -> Self {
         FabS {
-            password: password.into(),
+            password: Zeroizing::new(password.into()),
         }
     }
 }
@@ -33,21 +77,113 @@ impl PayloadCodecFactory for FabS {
 
         Ok(Box::new(CryptedPayloadCodec::new(
             codec,
-            self.password.clone(),
+            self.password.to_string(),
         )))
     }
+
+    fn crypt_overhead(&self) -> usize {
+        PASSWORD_CRYPT_OVERHEAD
+    }
+}
+
+/// Factory for codecs that encrypt with a raw 256-bit key instead of a password
+#[derive(Debug)]
+pub struct FabK {
+    pub key: Zeroizing<Key>,
+}
+
+impl FabK {
+    pub fn new(key: Key) -> Self {
+        FabK {
+            key: Zeroizing::new(key),
+        }
+    }
+}
+
+impl PayloadCodecFactory for FabK {
+    fn create_codec(&self, features: PayloadCodecFeatures) -> Result<Box<dyn PayloadCodec>> {
+        let features = features
+            .add_feature(PayloadCodecFeatures::ChaCrypto)
+            .add_feature(PayloadCodecFeatures::LengthHeader);
+        let codec = FabA.create_codec(features)?;
+
+        Ok(Box::new(CryptedPayloadCodec::with_key(codec, *self.key)))
+    }
+
+    fn crypt_overhead(&self) -> usize {
+        KEY_CRYPT_OVERHEAD
+    }
+}
+
+/// Factory for codecs that encrypt for several passwords, any one of them decrypts the payload
+#[derive(Debug)]
+pub struct FabM {
+    pub passwords: Vec<Zeroizing<String>>,
+}
+
+impl FabM {
+    pub fn new<I: Into<String>>(passwords: impl IntoIterator<Item = I>) -> Self {
+        FabM {
+            passwords: passwords
+                .into_iter()
+                .map(|p| Zeroizing::new(p.into()))
+                .collect(),
+        }
+    }
+}
+
+impl PayloadCodecFactory for FabM {
+    fn create_codec(&self, features: PayloadCodecFeatures) -> Result<Box<dyn PayloadCodec>> {
+        let features = features
+            .add_feature(PayloadCodecFeatures::ChaCrypto)
+            .add_feature(PayloadCodecFeatures::LengthHeader);
+        let codec = FabA.create_codec(features)?;
+
+        Ok(Box::new(CryptedPayloadCodec {
+            inner_encoder: codec,
+            secret: Secret::Passwords(self.passwords.clone()),
+        }))
+    }
+
+    fn crypt_overhead(&self) -> usize {
+        multi_password_crypt_overhead(self.passwords.len())
+    }
+}
+
+/// The secret a `CryptedPayloadCodec` en-/decrypts with, it is scrubbed from memory on drop
+enum Secret {
+    Password(Zeroizing<String>),
+    Passwords(Vec<Zeroizing<String>>),
+    Key(Zeroizing<Key>),
+}
+
+impl Secret {
+    fn passwords(&self) -> Vec<&str> {
+        match self {
+            Secret::Password(password) => vec![password.as_str()],
+            Secret::Passwords(passwords) => passwords.iter().map(|p| p.as_str()).collect(),
+            Secret::Key(_) => Vec::new(),
+        }
+    }
 }
 
 pub struct CryptedPayloadCodec {
     inner_encoder: Box<dyn PayloadCodec>,
-    password: String,
+    secret: Secret,
 }
 
 impl CryptedPayloadCodec {
     pub fn new(inner_encoder: Box<dyn PayloadCodec>, password: String) -> Self {
         Self {
             inner_encoder,
-            password,
+            secret: Secret::Password(Zeroizing::new(password)),
+        }
+    }
+
+    pub fn with_key(inner_encoder: Box<dyn PayloadCodec>, key: Key) -> Self {
+        Self {
+            inner_encoder,
+            secret: Secret::Key(Zeroizing::new(key)),
         }
     }
 }
@@ -58,12 +194,25 @@ impl PayloadEncoder for CryptedPayloadCodec {
     }
 
     fn encode(&self, content: &mut dyn Read) -> Result<Vec<u8>> {
-        let mut data = Vec::new();
-        // let's collect all data first
+        // let's collect all data first, the plaintext is scrubbed once it is encrypted
+        let mut data = Zeroizing::new(Vec::new());
         content.read_to_end(&mut data)?;
 
         // now we encrypt the data
-        let data = encrypt_data(&self.password, &data).expect("todo");
+        let (version, cipher_data) = match &self.secret {
+            Secret::Password(password) => (CRYPT_HEADER_VERSION, encrypt_data(password, &data)?),
+            Secret::Passwords(passwords) => (
+                CRYPT_HEADER_VERSION_MULTI,
+                encrypt_for_passwords(passwords, &data)?,
+            ),
+            Secret::Key(key) => (CRYPT_HEADER_VERSION, encrypt_data_with_key(key, &data)?),
+        };
+
+        // the header goes in front of the ciphertext, so that encryption is detectable
+        let mut data = Vec::with_capacity(CRYPT_HEADER_LEN + cipher_data.len());
+        data.extend_from_slice(&CRYPT_MAGIC);
+        data.push(version);
+        data.extend_from_slice(&cipher_data);
 
         // now we encode the encrypted data with the inner encoder
         let mut cursor = std::io::Cursor::new(data);
@@ -75,8 +224,40 @@ impl PayloadDecoder for CryptedPayloadCodec {
     fn decode(&self, content: &mut dyn Read) -> Result<Vec<u8>> {
         // let's collect all data first, but from the decoder that is smarter than us
         let data = self.inner_encoder.decode(content)?;
-        let decrypted_data =
-            decrypt_data(&self.password, &data).map_err(SteganoError::DecryptionError)?;
+        // payloads written before the header was introduced have no header
+        let version = crypt_header_version(&data);
+        let (cipher_data, decrypt): (_, fn(&str, &[u8]) -> _) = if version.is_some() {
+            (&data[CRYPT_HEADER_LEN..], decrypt_data)
+        } else {
+            // so its salt is never mistaken for a mode byte
+            (&data[..], decrypt_legacy_data)
+        };
+        let decrypted_data = match (&self.secret, version) {
+            // a key that does not fit is no wrong password, so it stays a decryption error
+            (Secret::Key(_), Some(CRYPT_HEADER_VERSION_MULTI)) => {
+                return Err(SteganoError::DecryptionError(
+                    SeasmokeError::PasswordRequired,
+                ));
+            }
+            (Secret::Key(key), _) => {
+                return decrypt_data_with_key(key, cipher_data)
+                    .map_err(SteganoError::DecryptionError);
+            }
+            (_, Some(CRYPT_HEADER_VERSION_MULTI)) => {
+                return decrypt_for_any_password(&self.secret.passwords(), cipher_data);
+            }
+            (Secret::Password(password), _) => decrypt(password, cipher_data),
+            (Secret::Passwords(passwords), _) => {
+                let mut decrypted = Err(SeasmokeError::PasswordRequired);
+                for password in passwords {
+                    decrypted = decrypt(password, cipher_data);
+                    if decrypted.is_ok() {
+                        break;
+                    }
+                }
+                decrypted
+            }
+        }?;
 
         Ok(decrypted_data)
     }
@@ -84,9 +265,56 @@ impl PayloadDecoder for CryptedPayloadCodec {
 
 impl PayloadCodec for CryptedPayloadCodec {}
 
+/// Encrypts the data with a random data key, that key is wrapped once for every password
+fn encrypt_for_passwords(passwords: &[Zeroizing<String>], data: &[u8]) -> Result<Vec<u8>> {
+    if !(1..=MAX_PASSWORDS).contains(&passwords.len()) {
+        return Err(SteganoError::InvalidPasswordCount(passwords.len()));
+    }
+    let data_key = generate_key();
+
+    let mut cipher_data = vec![passwords.len() as u8];
+    for password in passwords {
+        cipher_data.extend(encrypt_data(password, data_key.as_ref())?);
+    }
+    cipher_data.extend(encrypt_data_with_key(&data_key, data)?);
+
+    Ok(cipher_data)
+}
+
+/// Unwraps the data key with the first password that fits one of the wrapped keys
+fn decrypt_for_any_password(passwords: &[&str], cipher_data: &[u8]) -> Result<Vec<u8>> {
+    let Some((&count, rest)) = cipher_data
+        .split_first()
+        .filter(|(&count, _)| (1..=MAX_PASSWORDS).contains(&(count as usize)))
+    else {
+        return Err(SeasmokeError::MalformedCiphertext.into());
+    };
+    let wrapped_len = count as usize * WRAPPED_KEY_LEN;
+    if rest.len() < wrapped_len {
+        return Err(SeasmokeError::MalformedCiphertext.into());
+    }
+    let (wrapped_keys, cipher_data) = rest.split_at(wrapped_len);
+
+    for password in passwords {
+        for wrapped_key in wrapped_keys.chunks_exact(WRAPPED_KEY_LEN) {
+            let Ok(data_key) = decrypt_data_once(password, wrapped_key).map(Zeroizing::new) else {
+                continue;
+            };
+            let data_key: &Key = data_key
+                .as_slice()
+                .try_into()
+                .map_err(|_| SeasmokeError::MalformedCiphertext)?;
+
+            return Ok(decrypt_data_with_key(data_key, cipher_data)?);
+        }
+    }
+
+    Err(SteganoError::WrongPassword)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{media::payload::HasFeature, Message};
+    use crate::{media::payload::HasFeature, Message, SteganoError};
 
     use super::*;
 
@@ -106,10 +334,92 @@ mod tests {
         assert!(features.has_feature(PayloadCodecFeatures::LengthHeader));
         // thats the major part there!
         assert!(features.has_feature(PayloadCodecFeatures::ChaCrypto));
+        // version byte and 4 bytes length header come first
+        assert!(has_crypt_header(&encrypted_data[5..]));
 
         let msg_decrypted =
             Message::from_raw_data(&mut std::io::Cursor::new(encrypted_data), &cipher).unwrap();
 
         assert_eq!(msg_decrypted, msg);
     }
+
+    #[test]
+    fn test_encryption_codec_with_raw_key() {
+        let cipher = FabK::new([42; 32]);
+        let msg = Message::from_files(&["LICENSE"]).unwrap();
+        let encrypted_data = msg.to_raw_data(&cipher).unwrap();
+
+        let features = PayloadCodecFeatures::MixedFeatures(encrypted_data[0]);
+        assert!(features.has_feature(PayloadCodecFeatures::ChaCrypto));
+
+        let msg_decrypted =
+            Message::from_raw_data(&mut std::io::Cursor::new(&encrypted_data), &cipher).unwrap();
+        assert_eq!(msg_decrypted, msg);
+
+        let wrong_cipher = FabS::new("password42");
+        assert!(matches!(
+            Message::from_raw_data(&mut std::io::Cursor::new(&encrypted_data), &wrong_cipher),
+            Err(SteganoError::DecryptionError(SeasmokeError::KeyRequired))
+        ));
+        let wrong_key = FabK::new([43; 32]);
+        assert!(matches!(
+            Message::from_raw_data(&mut std::io::Cursor::new(&encrypted_data), &wrong_key),
+            Err(SteganoError::DecryptionError(
+                SeasmokeError::DecryptionError(_)
+            ))
+        ));
+        let password_data = msg.to_raw_data(&FabS::new("password42")).unwrap();
+        assert!(matches!(
+            Message::from_raw_data(&mut std::io::Cursor::new(&password_data), &cipher),
+            Err(SteganoError::DecryptionError(
+                SeasmokeError::PasswordRequired
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_secrets_are_zeroized_on_drop() {
+        fn assert_zeroize_on_drop<T: zeroize::ZeroizeOnDrop>(_: &T) {}
+
+        assert_zeroize_on_drop(&FabS::new("password42").password);
+        for password in &FabM::new(["alice", "bob"]).passwords {
+            assert_zeroize_on_drop(password);
+        }
+        assert_zeroize_on_drop(&FabK::new([42; 32]).key);
+    }
+
+    #[test]
+    fn test_password_count_is_limited() {
+        let passwords: Vec<_> = (0..=MAX_PASSWORDS)
+            .map(|i| Zeroizing::new(i.to_string()))
+            .collect();
+        assert!(matches!(
+            encrypt_for_passwords(&passwords, b"secret"),
+            Err(SteganoError::InvalidPasswordCount(17))
+        ));
+        assert!(matches!(
+            encrypt_for_passwords(&[], b"secret"),
+            Err(SteganoError::InvalidPasswordCount(0))
+        ));
+
+        // an untrusted count is rejected before any wrapped key is tried
+        let mut cipher_data = vec![MAX_PASSWORDS as u8 + 1];
+        cipher_data.resize(1 + 255 * WRAPPED_KEY_LEN, 0);
+        assert!(matches!(
+            decrypt_for_any_password(&["password42"], &cipher_data),
+            Err(SteganoError::DecryptionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_wrong_password_is_reported() {
+        let msg = Message::from_files(&["LICENSE"]).unwrap();
+        let encrypted_data = msg.to_raw_data(&FabS::new("password42")).unwrap();
+
+        let result = Message::from_raw_data(
+            &mut std::io::Cursor::new(encrypted_data),
+            &FabS::new("password43"),
+        );
+        assert!(matches!(result, Err(SteganoError::WrongPassword)));
+    }
 }
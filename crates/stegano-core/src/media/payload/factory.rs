@@ -6,6 +6,36 @@ use crate::result::Result;
 
 pub trait PayloadCodecFactory {
     fn create_codec(&self, features: PayloadCodecFeatures) -> Result<Box<dyn PayloadCodec>>;
+
+    /// the bytes the encryption of the codecs adds to a payload, none without encryption
+    fn crypt_overhead(&self) -> usize {
+        0
+    }
+
+    /// true if the codecs need a password or key, to encrypt and to decrypt
+    fn has_secret(&self) -> bool {
+        self.crypt_overhead() > 0
+    }
+}
+
+/// Creates the codec that decodes a payload of the given version. An encrypted payload cannot be
+/// decoded without a secret, a secret for a plain payload is not needed and ignored.
+pub(crate) fn create_decoder(
+    factory: &dyn PayloadCodecFactory,
+    version: u8,
+) -> Result<Box<dyn PayloadCodec>> {
+    let features = PayloadCodecFeatures::MixedFeatures(version);
+    match (
+        factory.has_secret(),
+        version.has_feature(PayloadCodecFeatures::ChaCrypto),
+    ) {
+        (false, true) => Err(SteganoError::PasswordRequired),
+        (true, false) => {
+            log::warn!("The data is not encrypted, the password is ignored");
+            FabA.create_codec(features)
+        }
+        _ => factory.create_codec(features),
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -23,13 +53,13 @@ impl PayloadCodecFactory for FabA {
                     PayloadCodecFeatures::TextAndDocuments
                         .add_feature(PayloadCodecFeatures::LengthHeader),
                 ),
-                PayloadDecoderWithLengthHeader,
+                PayloadDecoderWithLengthHeader::default(),
             ))),
             version if version.has_feature(PayloadCodecFeatures::LengthHeader) => {
                 let codec: Box<dyn PayloadCodec> = Box::new(PayloadFlexCodec::new(
                     // here we explicitly keep the specific version as is
                     PayloadEncoderWithLengthHeader::new(features),
-                    PayloadDecoderWithLengthHeader,
+                    PayloadDecoderWithLengthHeader::new(features),
                 ));
                 Ok(codec)
             }
@@ -37,3 +67,29 @@ impl PayloadCodecFactory for FabA {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_require_a_secret_exactly_for_encrypted_payloads() {
+        let plain: u8 = PayloadCodecFeatures::TextAndDocuments
+            .add_feature(PayloadCodecFeatures::LengthHeader)
+            .into();
+        let encrypted = plain | u8::from(PayloadCodecFeatures::ChaCrypto);
+
+        assert!(matches!(
+            create_decoder(&FabA, encrypted),
+            Err(SteganoError::PasswordRequired)
+        ));
+        assert!(matches!(
+            create_decoder(&FabK::new([42; 32]), encrypted),
+            Ok(codec) if codec.has_feature(PayloadCodecFeatures::ChaCrypto)
+        ));
+        assert!(matches!(
+            create_decoder(&FabS::new("secret"), plain),
+            Ok(codec) if !codec.has_feature(PayloadCodecFeatures::ChaCrypto)
+        ));
+    }
+}
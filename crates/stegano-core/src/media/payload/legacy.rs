@@ -58,8 +58,29 @@ pub mod v1 {
 pub mod v2 {
     use std::io::Read;
 
+    use byteorder::WriteBytesExt;
+
     use super::*;
 
+    /// Legacy encoder, only used to produce images for tools that do not know the length header.
+    /// Note: the decoder expects the remaining capacity of the carrier to be filled with zeros.
+    #[derive(Debug, Default)]
+    pub struct PayloadEncoderWithTerminator;
+    impl PayloadEncoder for PayloadEncoderWithTerminator {
+        fn version(&self) -> PayloadCodecFeatures {
+            PayloadCodecFeatures::TextAndDocumentsTerminated
+        }
+
+        fn encode(&self, content: &mut dyn Read) -> Result<Vec<u8>> {
+            let mut buffer = vec![self.version().into()];
+            content.read_to_end(&mut buffer)?;
+            buffer.write_u8(0xff)?;
+            buffer.write_u8(0xff)?;
+
+            Ok(buffer)
+        }
+    }
+
     #[derive(Debug, Default)]
     pub struct PayloadDecoderWithTerminator;
     impl PayloadDecoder for PayloadDecoderWithTerminator {
@@ -2,7 +2,9 @@ mod codec;
 mod crypted;
 mod factory;
 pub mod legacy;
+mod version;
 
 pub use codec::*;
 pub use crypted::*;
 pub use factory::*;
+pub use version::*;
@@ -0,0 +1,155 @@
+use super::*;
+use crate::error::SteganoError;
+use crate::result::Result;
+
+/// The payload format versions that are known, derived from the first byte of a payload
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PayloadVersion {
+    /// text only, terminated by `0xff`, written by stegano for windows up to v2.1
+    V1,
+    /// text and documents as zip, terminated by `0xff 0xff`, written by stegano for windows up to v2.1
+    V2,
+    /// text and documents as zip with a length header
+    V4,
+    /// text and documents as zip with a length header, encrypted
+    V4Encrypted,
+    /// text only with a length header
+    V9,
+    /// text only with an 8 byte length header, for payloads beyond 4 GiB
+    V73,
+    /// text and documents as zip with an 8 byte length header, for payloads beyond 4 GiB
+    V76,
+    /// text and documents as zip with an 8 byte length header, encrypted
+    V76Encrypted,
+}
+
+impl TryFrom<u8> for PayloadVersion {
+    type Error = SteganoError;
+
+    fn try_from(version: u8) -> Result<Self> {
+        let length_header = u8::from(PayloadCodecFeatures::LengthHeader);
+        let long_length_header = length_header | LONG_LENGTH_HEADER;
+        match version {
+            TEXT_ONLY => Ok(Self::V1),
+            TEXT_AND_DOCUMENTS_TERMINATED => Ok(Self::V2),
+            v if v == TEXT_AND_DOCUMENTS || v == TEXT_AND_DOCUMENTS | length_header => Ok(Self::V4),
+            v if v == TEXT_ONLY | length_header => Ok(Self::V9),
+            v if v == TEXT_ONLY | long_length_header => Ok(Self::V73),
+            v if v == TEXT_AND_DOCUMENTS | long_length_header => Ok(Self::V76),
+            v if v.has_feature(PayloadCodecFeatures::ChaCrypto)
+                && v.has_feature(PayloadCodecFeatures::TextAndDocuments)
+                && v.has_feature(PayloadCodecFeatures::LongLengthHeader) =>
+            {
+                Ok(Self::V76Encrypted)
+            }
+            v if v.has_feature(PayloadCodecFeatures::ChaCrypto)
+                && v.has_feature(PayloadCodecFeatures::TextAndDocuments) =>
+            {
+                Ok(Self::V4Encrypted)
+            }
+            v => Err(SteganoError::UnsupportedMessageFormat(v)),
+        }
+    }
+}
+
+impl From<PayloadVersion> for PayloadCodecFeatures {
+    fn from(version: PayloadVersion) -> Self {
+        match version {
+            PayloadVersion::V1 => PayloadCodecFeatures::TextOnly,
+            PayloadVersion::V2 => PayloadCodecFeatures::TextAndDocumentsTerminated,
+            PayloadVersion::V4 => PayloadCodecFeatures::TextAndDocuments
+                .add_feature(PayloadCodecFeatures::LengthHeader),
+            PayloadVersion::V4Encrypted => PayloadCodecFeatures::TextAndDocuments
+                .add_feature(PayloadCodecFeatures::LengthHeader)
+                .add_feature(PayloadCodecFeatures::ChaCrypto),
+            PayloadVersion::V9 => {
+                PayloadCodecFeatures::TextOnly.add_feature(PayloadCodecFeatures::LengthHeader)
+            }
+            PayloadVersion::V73 => PayloadCodecFeatures::TextOnly
+                .add_feature(PayloadCodecFeatures::LengthHeader)
+                .add_feature(PayloadCodecFeatures::LongLengthHeader),
+            PayloadVersion::V76 => PayloadCodecFeatures::TextAndDocuments
+                .add_feature(PayloadCodecFeatures::LengthHeader)
+                .add_feature(PayloadCodecFeatures::LongLengthHeader),
+            PayloadVersion::V76Encrypted => PayloadCodecFeatures::TextAndDocuments
+                .add_feature(PayloadCodecFeatures::LengthHeader)
+                .add_feature(PayloadCodecFeatures::ChaCrypto)
+                .add_feature(PayloadCodecFeatures::LongLengthHeader),
+        }
+    }
+}
+
+/// Factory that creates codecs which always encode in one specific version,
+/// for example to produce images that are readable by legacy tools
+#[derive(Debug, PartialEq, Eq)]
+pub struct FabV {
+    version: PayloadVersion,
+}
+
+impl FabV {
+    /// Only versions that can carry documents and are not encrypted can be produced
+    pub fn new(version: PayloadVersion) -> Result<Self> {
+        match version {
+            PayloadVersion::V2 | PayloadVersion::V4 => Ok(Self { version }),
+            v => Err(SteganoError::UnsupportedMessageFormat(
+                PayloadCodecFeatures::from(v).into(),
+            )),
+        }
+    }
+}
+
+impl PayloadCodecFactory for FabV {
+    fn create_codec(&self, _features: PayloadCodecFeatures) -> Result<Box<dyn PayloadCodec>> {
+        match self.version {
+            PayloadVersion::V2 => Ok(Box::new(PayloadFlexCodec::new(
+                legacy::v2::PayloadEncoderWithTerminator,
+                legacy::v2::PayloadDecoderWithTerminator,
+            ))),
+            version => FabA.create_codec(version.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_map_version_bytes() {
+        assert_eq!(PayloadVersion::try_from(1).unwrap(), PayloadVersion::V1);
+        assert_eq!(PayloadVersion::try_from(2).unwrap(), PayloadVersion::V2);
+        assert_eq!(PayloadVersion::try_from(4).unwrap(), PayloadVersion::V4);
+        assert_eq!(PayloadVersion::try_from(9).unwrap(), PayloadVersion::V9);
+        assert_eq!(PayloadVersion::try_from(12).unwrap(), PayloadVersion::V4);
+        assert_eq!(
+            PayloadVersion::try_from(44).unwrap(),
+            PayloadVersion::V4Encrypted
+        );
+        assert!(matches!(
+            PayloadVersion::try_from(0b11000000),
+            Err(SteganoError::UnsupportedMessageFormat(0b11000000))
+        ));
+
+        for version in [
+            PayloadVersion::V1,
+            PayloadVersion::V2,
+            PayloadVersion::V4,
+            PayloadVersion::V4Encrypted,
+            PayloadVersion::V9,
+            PayloadVersion::V73,
+            PayloadVersion::V76,
+            PayloadVersion::V76Encrypted,
+        ] {
+            let byte: u8 = PayloadCodecFeatures::from(version).into();
+            assert_eq!(PayloadVersion::try_from(byte).unwrap(), version);
+        }
+    }
+
+    #[test]
+    fn should_only_produce_unencrypted_document_versions() {
+        assert!(FabV::new(PayloadVersion::V2).is_ok());
+        assert!(FabV::new(PayloadVersion::V4).is_ok());
+        assert!(FabV::new(PayloadVersion::V1).is_err());
+        assert!(FabV::new(PayloadVersion::V4Encrypted).is_err());
+    }
+}
@@ -1,8 +1,10 @@
 /// wrap the low level data types that carries information
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub enum MediaPrimitive {
     ImageColorChannel(u8),
-    AudioSample(i16),
+    AudioSample(i32),
+    /// a 32 bit float sample, the data goes into the mantissa of its bit pattern
+    AudioFloatSample(f32),
 }
 
 impl From<u8> for MediaPrimitive {
@@ -12,8 +14,47 @@ impl From<u8> for MediaPrimitive {
 }
 
 /// mutable primitive for storing stegano data
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub enum MediaPrimitiveMut<'a> {
     ImageColorChannel(&'a mut u8),
-    AudioSample(&'a mut i16),
+    AudioSample(&'a mut i32),
+    AudioFloatSample(&'a mut f32),
+}
+
+/// an audio sample type that data can be hidden in, integer samples or 32 bit float samples
+pub trait WavSample: Copy {
+    fn primitive(self) -> MediaPrimitive;
+    fn primitive_mut(&mut self) -> MediaPrimitiveMut<'_>;
+
+    /// false if the sample is skipped for hiding and unveiling, flipping a bit must not change
+    /// what kind of value it is
+    fn can_carry(&self) -> bool {
+        true
+    }
+}
+
+impl WavSample for i32 {
+    fn primitive(self) -> MediaPrimitive {
+        MediaPrimitive::AudioSample(self)
+    }
+
+    fn primitive_mut(&mut self) -> MediaPrimitiveMut<'_> {
+        MediaPrimitiveMut::AudioSample(self)
+    }
+}
+
+impl WavSample for f32 {
+    fn primitive(self) -> MediaPrimitive {
+        MediaPrimitive::AudioFloatSample(self)
+    }
+
+    fn primitive_mut(&mut self) -> MediaPrimitiveMut<'_> {
+        MediaPrimitiveMut::AudioFloatSample(self)
+    }
+
+    /// a flipped mantissa bit would turn infinity into NaN or a NaN into infinity, finite
+    /// samples stay finite
+    fn can_carry(&self) -> bool {
+        self.is_finite()
+    }
 }
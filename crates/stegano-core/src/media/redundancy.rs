@@ -0,0 +1,148 @@
+//! Redundant copies of the hidden bits for tamper detection, see [`crate::CodecOptions::redundancy`].
+//!
+//! Unlike the forward error correction nothing is corrected, the copies are compared instead.
+//! A carrier that was modified where one of the copies lies makes them disagree.
+//!
+//! The frame is the copies one after the other, each of them starts with the same header:
+//! - a flag byte, the upper bit is set and the lower six bits hold the number of copies.
+//!   No payload version uses the upper bit and the FEC flag sets the two upper bits.
+//! - the length of the payload as big endian `u32`
+
+use byteorder::{BigEndian, ByteOrder};
+use std::io::{Cursor, Read};
+
+use crate::error::SteganoError;
+use crate::result::Result;
+
+const REDUNDANCY_FLAG: u8 = 0b1000_0000;
+const COPIES_MASK: u8 = 0b0011_1111;
+const HEADER_LEN: usize = 1 + 4;
+/// the most copies the flag byte can hold
+pub(crate) const MAX_COPIES: u8 = COPIES_MASK;
+
+/// the number of copies a flag byte announces, `None` if it is no flag
+fn copies_of(flag: u8) -> Option<u8> {
+    let copies = flag & COPIES_MASK;
+    (flag & !COPIES_MASK == REDUNDANCY_FLAG && copies >= 2).then_some(copies)
+}
+
+/// The number of payload bytes that fit into `capacity` raw bytes with `copies` copies
+pub(crate) fn payload_capacity(copies: u8, capacity: usize) -> usize {
+    match copies.min(MAX_COPIES) {
+        0 | 1 => capacity,
+        copies => (capacity / copies as usize).saturating_sub(HEADER_LEN),
+    }
+}
+
+/// Hides the payload `copies` times in a row, less than 2 copies leave it as it is
+pub(crate) fn encode(copies: u8, payload: &[u8]) -> Vec<u8> {
+    let copies = copies.min(MAX_COPIES);
+    if copies < 2 {
+        return payload.to_vec();
+    }
+    let mut header = [REDUNDANCY_FLAG | copies, 0, 0, 0, 0];
+    BigEndian::write_u32(&mut header[1..], payload.len() as u32);
+
+    let mut frame = Vec::with_capacity((HEADER_LEN + payload.len()) * copies as usize);
+    for _ in 0..copies {
+        frame.extend_from_slice(&header);
+        frame.extend_from_slice(payload);
+    }
+
+    frame
+}
+
+/// Reads the whole frame at the start of `raw` and returns the number of copies, the length
+/// of the payload and the frame. Noise hardly ever announces a frame that fits into the
+/// carrier, so an incomplete one is no frame, then the bytes read so far are returned as error.
+fn read_frame(raw: &mut dyn Read) -> std::result::Result<(u8, usize, Vec<u8>), Vec<u8>> {
+    let mut frame = Vec::with_capacity(HEADER_LEN);
+    if raw.take(HEADER_LEN as u64).read_to_end(&mut frame).is_err() || frame.len() < HEADER_LEN {
+        return Err(frame);
+    }
+    let Some(copies) = copies_of(frame[0]) else {
+        return Err(frame);
+    };
+    let len = BigEndian::read_u32(&frame[1..]) as usize;
+
+    // a length that overflows is noise
+    let total = len
+        .checked_add(HEADER_LEN)
+        .and_then(|copy| copy.checked_mul(copies as usize));
+    let Some(total) = total else {
+        return Err(frame);
+    };
+    let complete = raw
+        .take((total - HEADER_LEN) as u64)
+        .read_to_end(&mut frame)
+        .is_ok_and(|_| frame.len() == total);
+    if !complete {
+        return Err(frame);
+    }
+
+    Ok((copies, len, frame))
+}
+
+/// Errors with `SteganoError::CopiesDisagree` if the copies at the start of `raw` differ,
+/// data without copies passes
+pub(crate) fn check(raw: &mut dyn Read) -> Result<()> {
+    let Ok((copies, len, frame)) = read_frame(raw) else {
+        return Ok(());
+    };
+    let mut frames = frame.chunks(HEADER_LEN + len);
+    let first = frames.next().unwrap_or_default();
+    if frames.any(|copy| copy != first) {
+        return Err(SteganoError::CopiesDisagree { copies });
+    }
+
+    Ok(())
+}
+
+/// Passes on the first copy of the frame, any other data is passed on untouched
+pub(crate) fn decoder<'a>(mut raw: Box<dyn Read + 'a>) -> Box<dyn Read + 'a> {
+    match read_frame(&mut raw) {
+        Ok((_, len, mut frame)) => {
+            frame.truncate(HEADER_LEN + len);
+            Box::new(Cursor::new(frame.split_off(HEADER_LEN)))
+        }
+        Err(read) => Box::new(Cursor::new(read).chain(raw)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_detect_a_modified_copy() {
+        let frame = encode(3, b"tamper evident");
+        assert_eq!(frame.len(), 3 * (HEADER_LEN + 14));
+        assert!(check(&mut frame.as_slice()).is_ok());
+
+        let mut payload = Vec::new();
+        decoder(Box::new(Cursor::new(frame.clone())))
+            .read_to_end(&mut payload)
+            .unwrap();
+        assert_eq!(payload, b"tamper evident");
+
+        let mut tampered = frame;
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            check(&mut tampered.as_slice()),
+            Err(SteganoError::CopiesDisagree { copies: 3 })
+        ));
+
+        // noise that looks like a flag announces more than there is
+        let noise = [REDUNDANCY_FLAG | 2, 0xff, 0xff, 0xff, 0xff, 1, 2, 3];
+        assert!(check(&mut &noise[..]).is_ok());
+        let mut passed = Vec::new();
+        decoder(Box::new(Cursor::new(noise)))
+            .read_to_end(&mut passed)
+            .unwrap();
+        assert_eq!(passed, noise);
+
+        // a single copy is no frame at all
+        assert_eq!(encode(1, b"plain"), b"plain");
+        assert!(check(&mut &b"plain"[..]).is_ok());
+    }
+}
@@ -0,0 +1,41 @@
+use std::io::Read;
+
+use crate::media::image::CodecOptions;
+use crate::media::Media;
+use crate::result::Result;
+
+/// Places the bits of the payload in a media and finds them again.
+/// The built-in scheme is [`LsbStegoCodec`], other schemes can be plugged in with
+/// `SteganoEncoder::with_codec` and `UnveilApi::with_codec`.
+pub trait StegoCodec {
+    /// Hides `data` in the media, it errors if the media cannot hold all of it
+    fn embed(&self, media: &mut Media, data: &[u8], opts: &CodecOptions) -> Result<()>;
+
+    /// Returns the hidden bytes, they may be followed by whatever else the media holds
+    fn extract(&self, media: &Media, opts: &CodecOptions) -> Result<Vec<u8>>;
+}
+
+impl std::fmt::Debug for dyn StegoCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("StegoCodec")
+    }
+}
+
+/// The least significant bits of the colors of an image or the samples of an audio
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LsbStegoCodec;
+
+impl StegoCodec for LsbStegoCodec {
+    fn embed(&self, media: &mut Media, data: &[u8], opts: &CodecOptions) -> Result<()> {
+        media.hide_data(data.to_vec(), opts)?;
+
+        Ok(())
+    }
+
+    fn extract(&self, media: &Media, opts: &CodecOptions) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        media.decoder(opts).read_to_end(&mut data)?;
+
+        Ok(data)
+    }
+}
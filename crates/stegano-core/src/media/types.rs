@@ -1,115 +1,1199 @@
+use std::io::{Cursor, Read, Seek, Write};
 use std::path::Path;
 
-pub use hound::{WavReader, WavSpec, WavWriter};
-pub use image::RgbaImage;
+pub use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use image::{DynamicImage, ImageBuffer, Pixel};
+pub use image::{GrayImage, RgbaImage};
 use log::error;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
 
 use crate::error::SteganoError;
-use crate::media::image::CodecOptions;
+use crate::media::image::decoder::ImageRgbaColor;
+use crate::media::image::{
+    draw_watermark, looks_transcoded_from_jpeg, read_placement_header, traversed_area,
+    write_placement_header, CodecOptions, Corner, EmbedDirection,
+};
+use crate::media::{CarrierSize, MediaPrimitive, WavSample};
 use crate::result::Result;
 
 use super::Persist;
 
-pub type WavAudio = (WavSpec, Vec<i16>);
+/// the original spec and all samples, widened to `i32` so that any integer bit depth fits
+pub type WavAudio = (WavSpec, Vec<i32>);
+
+/// the original spec and all samples of a 32 bit float WAV
+pub type FloatWavAudio = (WavSpec, Vec<f32>);
+
+/// the encoded format of media bytes, used when there is no file extension to look at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaFormat {
+    Png,
+    Bmp,
+    Wav,
+    /// lossy, it can be detected but not be used as carrier
+    Jpeg,
+    /// can be detected but not be used as carrier
+    Gif,
+}
+
+impl MediaFormat {
+    /// Identifies the format by the magic numbers at the start of the data
+    pub fn detect_from_bytes(data: &[u8]) -> Option<Self> {
+        match data {
+            [0x89, b'P', b'N', b'G', ..] => Some(Self::Png),
+            [0xff, 0xd8, 0xff, ..] => Some(Self::Jpeg),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some(Self::Wav),
+            [b'B', b'M', ..] => Some(Self::Bmp),
+            [b'G', b'I', b'F', b'8', ..] => Some(Self::Gif),
+            _ => None,
+        }
+    }
+
+    /// Identifies the format of a file by its magic numbers, or by its extension if they are unknown
+    pub fn of_file(f: &Path) -> Option<Self> {
+        let mut header = [0; 12];
+        let read = std::fs::File::open(f)
+            .and_then(|mut file| file.read(&mut header))
+            .unwrap_or_default();
+
+        Self::detect_from_bytes(&header[..read]).or_else(|| {
+            f.extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(|ext| Self::from_extension(&ext.to_lowercase()))
+        })
+    }
+
+    /// The formats that can be used as carrier, e.g. to offer them in a file picker
+    pub fn supported_carrier_formats() -> &'static [MediaFormat] {
+        &[Self::Png, Self::Bmp, Self::Wav]
+    }
+
+    /// The formats a media with hidden data can be written in
+    pub fn supported_output_formats() -> &'static [MediaFormat] {
+        &[Self::Png, Self::Bmp, Self::Wav]
+    }
+
+    /// Identifies the format by a lower case file extension
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "png" => Some(Self::Png),
+            "bmp" => Some(Self::Bmp),
+            "wav" => Some(Self::Wav),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "gif" => Some(Self::Gif),
+            _ => None,
+        }
+    }
+}
+
+/// the steganography algorithm, it has to fit the carrier format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    /// least significant bit, for lossless formats like PNG, BMP and WAV
+    Lsb,
+    /// F5 in the DCT coefficients, for JPEG only
+    F5,
+}
+
+impl Algorithm {
+    /// The algorithm that fits the carrier format, used when none is chosen explicitly
+    pub fn for_format(format: MediaFormat) -> Self {
+        match format {
+            MediaFormat::Jpeg => Self::F5,
+            _ => Self::Lsb,
+        }
+    }
+
+    /// Ensures the algorithm can work with the carrier format, e.g. F5 needs a JPEG,
+    /// and that this build implements it
+    pub fn ensure_fits(self, format: MediaFormat) -> Result<()> {
+        match (self, format) {
+            (Self::Lsb, MediaFormat::Png | MediaFormat::Bmp | MediaFormat::Wav) => Ok(()),
+            // there is no F5 encoder yet
+            (Self::F5, MediaFormat::Jpeg) => Err(SteganoError::AlgorithmNotSupported(self)),
+            (algorithm, format) => Err(SteganoError::AlgorithmMismatch { algorithm, format }),
+        }
+    }
+}
 
 /// a media container for steganography
+#[derive(Clone, Debug)]
 pub enum Media {
     Image(RgbaImage),
+    /// an 8 bit grayscale image, it stays grayscale so that the carrier keeps its color type
+    GrayImage(GrayImage),
     Audio(WavAudio),
+    /// a 32 bit float WAV, the data goes into the least significant mantissa bit of the samples
+    FloatAudio(FloatWavAudio),
 }
 
 impl Media {
+    /// Loads the media, the format is detected from the content and must not contradict
+    /// the file extension. Without a known extension the detected format is used.
     pub fn from_file(f: &Path) -> Result<Self> {
-        if let Some(ext) = f.extension() {
-            let ext = ext.to_str().unwrap().to_lowercase();
-            match ext.as_str() {
-                "png" => Ok(Self::Image(
-                    image::open(f)
-                        .map_err(|_e| SteganoError::InvalidImageMedia)?
-                        .to_rgba8(),
-                )),
-                "wav" => {
-                    let mut reader =
-                        WavReader::open(f).map_err(|_e| SteganoError::InvalidAudioMedia)?;
-                    let spec = reader.spec();
-                    let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
-
-                    Ok(Self::Audio((spec, samples)))
+        let extension = f
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase);
+        let by_extension = extension.as_deref().and_then(MediaFormat::from_extension);
+        let data = std::fs::read(f).map_err(|source| SteganoError::Io {
+            path: f.to_path_buf(),
+            source,
+        })?;
+
+        let format = match (by_extension, MediaFormat::detect_from_bytes(&data)) {
+            (Some(by_extension), Some(detected)) if by_extension != detected => {
+                return Err(SteganoError::FormatMismatch {
+                    extension: extension.unwrap_or_default(),
+                    detected,
+                })
+            }
+            (Some(format), _) | (None, Some(format)) => format,
+            (None, None) => return Err(SteganoError::UnsupportedMedia),
+        };
+
+        Self::from_bytes(&data, format)
+    }
+
+    /// Decodes media from an in-memory buffer, for cases where there is no file at hand
+    pub fn from_bytes(data: &[u8], format: MediaFormat) -> Result<Self> {
+        match format {
+            MediaFormat::Png | MediaFormat::Bmp => {
+                let image_format = if format == MediaFormat::Png {
+                    image::ImageFormat::Png
+                } else {
+                    image::ImageFormat::Bmp
+                };
+                Ok(Self::from_image(
+                    image::load_from_memory_with_format(data, image_format)
+                        .map_err(|_e| SteganoError::InvalidImageMedia)?,
+                ))
+            }
+            MediaFormat::Wav => Self::from_wav_reader(
+                WavReader::new(Cursor::new(data)).map_err(|_e| SteganoError::InvalidAudioMedia)?,
+            ),
+
+            MediaFormat::Jpeg | MediaFormat::Gif => Err(SteganoError::UnsupportedMedia),
+        }
+    }
+
+    /// 8 bit grayscale images are kept as they are, all others are converted to RGBA
+    fn from_image(image: DynamicImage) -> Self {
+        match image {
+            DynamicImage::ImageLuma8(gray) => Self::GrayImage(gray),
+            image => Self::Image(image.to_rgba8()),
+        }
+    }
+
+    fn from_wav_reader<R: Read>(mut reader: WavReader<R>) -> Result<Self> {
+        let spec = reader.spec();
+        match (spec.sample_format, spec.bits_per_sample) {
+            (SampleFormat::Int, _) => Ok(Self::Audio((spec, read_samples(&mut reader)?))),
+            (SampleFormat::Float, 32) => Ok(Self::FloatAudio((spec, read_samples(&mut reader)?))),
+            _ => Err(SteganoError::InvalidAudioMedia),
+        }
+    }
+
+    pub fn hide_data(&mut self, mut msg_data: Vec<u8>, opts: &CodecOptions) -> Result<&mut Self> {
+        opts.check_bit_plane()?;
+        let needed = msg_data.len();
+        if opts.obfuscate_header {
+            super::obfuscation::xor_header(&mut msg_data);
+        }
+        let msg_data = super::redundancy::encode(opts.redundancy, &msg_data);
+        let msg_data = match opts.fec {
+            Some(fec) => fec.encode(&msg_data),
+            None => msg_data,
+        };
+        let msg_data = if opts.record_carrier_size {
+            super::carrier_size::encode(self.carrier_size(), &msg_data)
+        } else {
+            msg_data
+        };
+        if let Some(fraction) = opts.max_fill_fraction {
+            // the framing counts in, as it takes the carrier bits too
+            if msg_data.len() > fill_limit(self.raw_capacity_bytes(opts), fraction) {
+                return Err(SteganoError::CapacityExceeded {
+                    needed,
+                    available: self.capacity_bytes(opts),
+                });
+            }
+        }
+        super::timed("hiding", || -> Result<()> {
+            match self {
+                Media::Image(i) => hide_in_image(i, &msg_data, opts)?,
+                Media::GrayImage(i) => hide_in_image(i, &msg_data, opts)?,
+                Media::Audio((spec, samples)) => hide_in_audio(spec, samples, &msg_data, opts)?,
+                Media::FloatAudio((spec, samples)) => {
+                    hide_in_audio(spec, samples, &msg_data, opts)?
                 }
-                _ => Err(SteganoError::UnsupportedMedia),
             }
+
+            Ok(())
+        })?;
+
+        Ok(self)
+    }
+}
+
+impl Media {
+    /// Overwrites the hiding bits of every color channel or sample that could carry data with
+    /// noise from `rng`, so that no hidden payload survives, no matter in which order it was hidden
+    pub(crate) fn sanitize(&mut self, opts: &CodecOptions, rng: &mut impl RngCore) -> Result<()> {
+        let opts = CodecOptions {
+            color_channel_step_increment: 1,
+            skip_last_row_and_column: false,
+            permutation_seed: None,
+            direction: EmbedDirection::Forward,
+            adaptive_threshold: None,
+            fec: None,
+            ..opts.clone()
+        };
+        // one byte more than fits, so that the channels of a trailing partial byte are covered too
+        let mut noise = vec![0; self.capacity_bytes(&opts) + 1];
+        rng.fill_bytes(&mut noise);
+
+        let mut encoder = match self {
+            Media::Image(i) => super::image::LsbCodec::encoder(i, &opts),
+            Media::GrayImage(i) => super::image::LsbCodec::encoder(i, &opts),
+            Media::Audio((spec, samples)) => audio_encoder(spec, samples, &opts),
+            Media::FloatAudio((spec, samples)) => audio_encoder(spec, samples, &opts),
+        };
+        // the encoder writes until the carrier is exhausted
+        while encoder.write(&noise)? > 0 {}
+
+        Ok(())
+    }
+}
+
+fn color_values(colors: ImageRgbaColor<'_>) -> impl Iterator<Item = i64> + '_ {
+    colors.filter_map(|color| match color {
+        MediaPrimitive::ImageColorChannel(value) => Some(value as i64),
+        MediaPrimitive::AudioSample(_) | MediaPrimitive::AudioFloatSample(_) => None,
+    })
+}
+
+fn read_samples<S: hound::Sample, R: Read>(reader: &mut WavReader<R>) -> Result<Vec<S>> {
+    reader
+        .samples::<S>()
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|_e| SteganoError::InvalidAudioMedia)
+}
+
+/// writes all samples and finalizes the WAV, so that its chunk lengths are right
+fn write_samples<W: Write + Seek, S: hound::Sample + Copy>(
+    mut writer: WavWriter<W>,
+    samples: &[S],
+) -> std::result::Result<(), hound::Error> {
+    for sample in samples {
+        writer.write_sample(*sample)?;
+    }
+
+    writer.finalize()
+}
+
+fn audio_encoder<'i, S: WavSample>(
+    spec: &WavSpec,
+    samples: &'i mut [S],
+    opts: &CodecOptions,
+) -> Box<dyn Write + 'i> {
+    super::audio::LsbCodec::encoder(
+        samples,
+        spec.channels,
+        opts.audio_channels,
+        opts.audio_sample_step,
+    )
+}
+
+fn hide_in_audio<S: WavSample>(
+    spec: &WavSpec,
+    samples: &mut [S],
+    msg_data: &[u8],
+    opts: &CodecOptions,
+) -> Result<()> {
+    let mut encoder = audio_encoder(spec, samples, opts);
+    encoder
+        .write_all(msg_data)
+        .map_err(|_e| SteganoError::AudioEncodingError)?;
+    if opts.randomize_unused {
+        fill_with_noise(&mut encoder).map_err(|_e| SteganoError::AudioEncodingError)?;
+    }
+
+    Ok(())
+}
+
+/// writes random bits until the carrier is exhausted, see `CodecOptions::randomize_unused`
+fn fill_with_noise(encoder: &mut impl Write) -> std::io::Result<()> {
+    let mut rng = StdRng::from_entropy();
+    let mut noise = [0; 1024];
+    loop {
+        rng.fill_bytes(&mut noise);
+        // the last bits of the carrier are taken, even if they make no whole byte
+        if encoder.write(&noise)? == 0 {
+            return Ok(());
+        }
+    }
+}
+
+fn hide_in_image<P: Pixel<Subpixel = u8>>(
+    image: &mut ImageBuffer<P, Vec<u8>>,
+    msg_data: &[u8],
+    opts: &CodecOptions,
+) -> Result<()> {
+    let (width, height) = image.dimensions();
+    let capacity = image_capacity(image, opts);
+    let mut encoder = super::image::LsbCodec::encoder(image, opts);
+
+    encoder.write_all(msg_data).map_err(|e| {
+        error!("Error encoding image: {e}, kind {}", e.kind());
+
+        match e.kind() {
+            std::io::ErrorKind::WriteZero => {
+                // the capacity grows with the area, so both sides grow by the square root
+                let scale = (msg_data.len() as f64 / capacity.max(1) as f64).sqrt();
+                let w = (scale * width as f64).ceil();
+                let h = (scale * height as f64).ceil();
+
+                SteganoError::ImageCapacityError(width as _, height as _, w as _, h as _)
+            }
+            _ => SteganoError::ImageEncodingError,
+        }
+    })?;
+    if opts.randomize_unused {
+        fill_with_noise(&mut encoder).map_err(|_e| SteganoError::ImageEncodingError)?;
+    }
+    drop(encoder);
+    // the unveiling reads back where the bits went, see `CodecOptions::skip_last_row_and_column`
+    write_placement_header(image, opts);
+
+    Ok(())
+}
+
+impl Media {
+    /// The width and height of an image, `None` for audio
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        match self {
+            Media::Image(i) => Some(i.dimensions()),
+            Media::GrayImage(i) => Some(i.dimensions()),
+            Media::Audio(_) | Media::FloatAudio(_) => None,
+        }
+    }
+
+    /// The number of samples of audio, over all channels, `None` for images
+    pub fn sample_count(&self) -> Option<usize> {
+        match self {
+            Media::Audio((_, samples)) => Some(samples.len()),
+            Media::FloatAudio((_, samples)) => Some(samples.len()),
+            Media::Image(_) | Media::GrayImage(_) => None,
+        }
+    }
+
+    /// The number of bytes that can be hidden in the media with the given options,
+    /// the header and the payload of a message count in
+    pub fn capacity_bytes(&self, opts: &CodecOptions) -> usize {
+        let capacity = self.raw_capacity_bytes(opts);
+        let capacity = match opts.max_fill_fraction {
+            Some(fraction) => fill_limit(capacity, fraction),
+            None => capacity,
+        };
+
+        let capacity = if opts.record_carrier_size {
+            capacity.saturating_sub(super::carrier_size::HEADER_LEN)
         } else {
-            Err(SteganoError::UnsupportedMedia)
+            capacity
+        };
+        let capacity = match opts.fec {
+            Some(fec) => fec.payload_capacity(capacity),
+            None => capacity,
+        };
+        super::redundancy::payload_capacity(opts.redundancy, capacity)
+    }
+
+    /// The number of bytes the codec writes until the carrier is exhausted
+    fn raw_capacity_bytes(&self, opts: &CodecOptions) -> usize {
+        match self {
+            Media::Image(i) => image_capacity(i, opts),
+            Media::GrayImage(i) => image_capacity(i, opts),
+            Media::Audio((spec, samples)) => audio_capacity(spec, samples, opts),
+            Media::FloatAudio((spec, samples)) => audio_capacity(spec, samples, opts),
         }
     }
 
-    pub fn hide_data(&mut self, msg_data: Vec<u8>, opts: &CodecOptions) -> Result<&mut Self> {
+    /// builds the LSB decoder that fits the media, it skips the recorded carrier size, corrects
+    /// payloads hidden with FEC, takes the first of redundant copies and restores an obfuscated header.
+    pub(crate) fn decoder(&self, opts: &CodecOptions) -> Box<dyn Read + '_> {
+        let decoder = super::redundancy::decoder(self.framed_decoder(opts));
+        if opts.obfuscate_header {
+            super::obfuscation::decoder(decoder)
+        } else {
+            decoder
+        }
+    }
+
+    /// the bits of the media without the recorded carrier size and corrected by FEC
+    fn framed_decoder(&self, opts: &CodecOptions) -> Box<dyn Read + '_> {
+        super::fec::decoder(super::carrier_size::decoder(self.lsb_decoder(opts)))
+    }
+
+    /// the plain bits of the media, the placement header of an image overrides the placement
+    /// options, see `write_placement_header`
+    fn lsb_decoder(&self, opts: &CodecOptions) -> Box<dyn Read + '_> {
         match self {
-            Media::Image(i) => {
-                let (width, height) = i.dimensions();
-                let mut encoder = super::image::LsbCodec::encoder(i, opts);
-
-                encoder.write_all(msg_data.as_ref()).map_err(|e| {
-                    error!("Error encoding image: {e}, kind {}", e.kind());
-
-                    match e.kind() {
-                        std::io::ErrorKind::WriteZero => {
-                            let capacity = width * height;
-                            // let ratio = width as f64 / height as f64;
-                            let estimated_needed_dimensions = msg_data.len() * 8 / 3;
-                            let scale = estimated_needed_dimensions as f64 / capacity as f64;
-                            let w = scale * width as f64;
-                            let h = scale * height as f64;
-
-                            SteganoError::ImageCapacityError(
-                                width as _,
-                                height as _,
-                                w as _,
-                                h as _,
-                            )
-                        }
-                        _ => SteganoError::ImageEncodingError,
-                    }
-                })?
+            Media::Image(i) => super::image::LsbCodec::decoder(
+                i,
+                &read_placement_header(i, opts).unwrap_or_else(|| opts.clone()),
+            ),
+            Media::GrayImage(i) => super::image::LsbCodec::decoder(
+                i,
+                &read_placement_header(i, opts).unwrap_or_else(|| opts.clone()),
+            ),
+            Media::Audio((spec, samples)) => audio_decoder(spec, samples, opts),
+            Media::FloatAudio((spec, samples)) => audio_decoder(spec, samples, opts),
+        }
+    }
+
+    /// the size of the media, as `CodecOptions::record_carrier_size` records it
+    pub(crate) fn carrier_size(&self) -> CarrierSize {
+        match self {
+            Media::Image(i) => CarrierSize::Pixels {
+                width: i.width(),
+                height: i.height(),
+            },
+            Media::GrayImage(i) => CarrierSize::Pixels {
+                width: i.width(),
+                height: i.height(),
+            },
+            Media::Audio((_, samples)) => CarrierSize::Samples(samples.len() as u64),
+            Media::FloatAudio((_, samples)) => CarrierSize::Samples(samples.len() as u64),
+        }
+    }
+
+    /// Errors with `SteganoError::CopiesDisagree` if the redundant copies of the hidden data
+    /// differ, e.g. because the media was modified since. Without copies it passes.
+    pub(crate) fn ensure_copies_agree(&self, opts: &CodecOptions) -> Result<()> {
+        super::redundancy::check(&mut self.framed_decoder(opts))
+    }
+
+    /// Errors with `SteganoError::CarrierAltered` if the media recorded another size when the
+    /// data was hidden, e.g. because it was cropped since. Without a recorded size it passes.
+    pub(crate) fn ensure_unaltered(&self, opts: &CodecOptions) -> Result<()> {
+        let actual = self.carrier_size();
+        match super::carrier_size::recorded_size(&mut self.lsb_decoder(opts), actual) {
+            Some(expected) if expected != actual => {
+                Err(SteganoError::CarrierAltered { expected, actual })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The peak signal-to-noise ratio in dB between this (stego) media and the `original`,
+    /// the higher the less the media was distorted. Identical media result in infinity.
+    pub fn psnr(&self, original: &Media) -> Result<f64> {
+        let (squared_error, count, peak) = match (self, original) {
+            (Media::Image(a), Media::Image(b)) if a.dimensions() == b.dimensions() => {
+                // alpha is left out, it is not a color that is seen
+                let (e, n) = squared_error(
+                    a.pixels().flat_map(|p| &p.0[..3]).copied(),
+                    b.pixels().flat_map(|p| &p.0[..3]).copied(),
+                );
+                (e, n, u8::MAX as f64)
+            }
+            (Media::GrayImage(a), Media::GrayImage(b)) if a.dimensions() == b.dimensions() => {
+                let (e, n) = squared_error(a.iter().copied(), b.iter().copied());
+                (e, n, u8::MAX as f64)
             }
-            Media::Audio((_spec, samples)) => {
-                let mut encoder = super::audio::LsbCodec::encoder(samples);
+            (Media::Audio((spec_a, a)), Media::Audio((spec_b, b)))
+                if spec_a == spec_b && a.len() == b.len() =>
+            {
+                let (e, n) = squared_error(a.iter().copied(), b.iter().copied());
+                (e, n, (1u64 << (spec_a.bits_per_sample - 1)) as f64)
+            }
+            // float samples are in `-1.0..=1.0`
+            (Media::FloatAudio((spec_a, a)), Media::FloatAudio((spec_b, b)))
+                if spec_a == spec_b && a.len() == b.len() =>
+            {
+                let (e, n) = squared_error(a.iter().copied(), b.iter().copied());
+                (e, n, 1.0)
+            }
+            _ => return Err(SteganoError::IncomparableMedia),
+        };
+        if squared_error == 0.0 || count == 0 {
+            return Ok(f64::INFINITY);
+        }
+        let mse = squared_error / count as f64;
+
+        Ok(10.0 * (peak * peak / mse).log10())
+    }
 
-                encoder
-                    .write_all(msg_data.as_ref())
-                    .map_err(|_e| SteganoError::AudioEncodingError)?
+    /// A rough steganalysis score, the chi-square attack on the LSB pairs of the color channels
+    /// or samples the options would use. It is the probability in `0.0..=1.0` that the LSBs carry
+    /// data, a clean carrier scores low. Note that only a payload that fills most of the carrier
+    /// is detected reliably.
+    pub fn chi_square_score(&self, opts: &CodecOptions) -> f64 {
+        let values: Box<dyn Iterator<Item = i64>> = match self {
+            Media::Image(i) => Box::new(color_values(ImageRgbaColor::new_with_options(i, opts))),
+            Media::GrayImage(i) => {
+                Box::new(color_values(ImageRgbaColor::new_with_options(i, opts)))
             }
+            Media::Audio((spec, samples)) => Box::new(
+                super::audio::lsb_codec::selected(
+                    samples.iter().copied(),
+                    spec.channels,
+                    opts.audio_channels,
+                    opts.audio_sample_step,
+                )
+                .map(i64::from),
+            ),
+            Media::FloatAudio((spec, samples)) => Box::new(
+                super::audio::lsb_codec::carrying::<_, f32>(
+                    samples.iter().copied(),
+                    spec.channels,
+                    opts.audio_channels,
+                    opts.audio_sample_step,
+                )
+                .map(|sample| i64::from(sample.to_bits())),
+            ),
+        };
+
+        super::chi_square::embedding_probability(values)
+    }
+
+    /// true if the image shows the 8x8 block grid of a JPEG compression, like a PNG that was
+    /// converted from a JPEG. Hiding works, but such artifacts make a carrier suspicious.
+    pub fn looks_transcoded_from_jpeg(&self) -> bool {
+        match self {
+            Media::Image(i) => looks_transcoded_from_jpeg(i.width(), i.height(), |x, y| {
+                let [r, g, b, _] = i.get_pixel(x, y).0;
+                0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64
+            }),
+            Media::GrayImage(i) => looks_transcoded_from_jpeg(i.width(), i.height(), |x, y| {
+                i.get_pixel(x, y).0[0] as f64
+            }),
+            Media::Audio(_) | Media::FloatAudio(_) => false,
         }
+    }
 
-        Ok(self)
+    /// Burns the text visibly into a corner of an image, see `SteganoEncoder::with_watermark`
+    pub(crate) fn draw_watermark(&mut self, text: &str, corner: Corner) -> Result<()> {
+        match self {
+            Media::Image(i) => draw_watermark(i, text, corner),
+            Media::GrayImage(i) => draw_watermark(i, text, corner),
+            Media::Audio(_) | Media::FloatAudio(_) => return Err(SteganoError::UnsupportedMedia),
+        }
+
+        Ok(())
+    }
+
+    /// true for all image variants
+    pub(crate) fn is_image(&self) -> bool {
+        matches!(self, Media::Image(_) | Media::GrayImage(_))
+    }
+
+    /// Errors with `SteganoError::CarrierTooSmall` if less than `needed` bytes fit into the media
+    pub(crate) fn ensure_capacity(&self, needed: usize, opts: &CodecOptions) -> Result<()> {
+        let available = self.capacity_bytes(opts);
+        if available < needed {
+            return Err(SteganoError::CarrierTooSmall { needed, available });
+        }
+
+        Ok(())
+    }
+
+    /// Encodes the media into its file format (PNG for images, WAV for audio) and returns the bytes
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        match self {
+            Media::Image(_) | Media::GrayImage(_) => self.to_vec_as(MediaFormat::Png),
+            Media::Audio(_) | Media::FloatAudio(_) => self.to_vec_as(MediaFormat::Wav),
+        }
+    }
+
+    /// Encodes the media into the given format and returns the bytes,
+    /// images can be encoded as PNG or BMP and audio as WAV
+    pub fn to_vec_as(&self, format: MediaFormat) -> Result<Vec<u8>> {
+        // only used for the image variants
+        let image_format = if format == MediaFormat::Bmp {
+            image::ImageFormat::Bmp
+        } else {
+            image::ImageFormat::Png
+        };
+        let mut buf = Cursor::new(Vec::new());
+        match (self, format) {
+            (Media::Image(i), MediaFormat::Png | MediaFormat::Bmp) => {
+                i.write_to(&mut buf, image_format).map_err(|e| {
+                    error!("Error encoding image to memory: {e}");
+                    SteganoError::ImageEncodingError
+                })?
+            }
+            (Media::GrayImage(i), MediaFormat::Png | MediaFormat::Bmp) => {
+                i.write_to(&mut buf, image_format).map_err(|e| {
+                    error!("Error encoding image to memory: {e}");
+                    SteganoError::ImageEncodingError
+                })?
+            }
+            (Media::Audio((spec, samples)), MediaFormat::Wav) => write_samples(
+                WavWriter::new(&mut buf, *spec).map_err(|_| SteganoError::AudioCreationError)?,
+                samples,
+            )
+            .map_err(|_| SteganoError::AudioEncodingError)?,
+            (Media::FloatAudio((spec, samples)), MediaFormat::Wav) => write_samples(
+                WavWriter::new(&mut buf, *spec).map_err(|_| SteganoError::AudioCreationError)?,
+                samples,
+            )
+            .map_err(|_| SteganoError::AudioEncodingError)?,
+            (_, format) => return Err(SteganoError::UnsupportedOutputFormat(format)),
+        }
+
+        Ok(buf.into_inner())
     }
 }
 
+/// sums up the squared differences and counts the compared values
+fn squared_error<T: Into<f64>>(
+    a: impl Iterator<Item = T>,
+    b: impl Iterator<Item = T>,
+) -> (f64, usize) {
+    a.zip(b).fold((0.0, 0), |(sum, n), (a, b)| {
+        let d = a.into() - b.into();
+        (sum + d * d, n + 1)
+    })
+}
+
+fn image_capacity<P: Pixel<Subpixel = u8>>(
+    image: &ImageBuffer<P, Vec<u8>>,
+    opts: &CodecOptions,
+) -> usize {
+    let pixels = match opts.usable_pixels(image, opts.skip_last_row_and_column) {
+        // only the pixels in busy regions can carry data
+        Some(usable) => usable.iter().filter(|usable| **usable).count(),
+        None => {
+            let (width, height) = image.dimensions();
+            let (width, height) = traversed_area(width, height, opts.skip_last_row_and_column);
+            width as usize * height as usize
+        }
+    };
+    let color_channels = pixels * opts.channels_per_pixel::<P>();
+
+    color_channels.div_ceil(opts.color_channel_step_increment) / 8
+}
+
+/// the bytes of the capacity that `CodecOptions::max_fill_fraction` allows to use
+fn fill_limit(capacity: usize, fraction: f32) -> usize {
+    (capacity as f64 * fraction.clamp(0.0, 1.0) as f64) as usize
+}
+
+fn audio_capacity<S: WavSample>(spec: &WavSpec, samples: &[S], opts: &CodecOptions) -> usize {
+    super::audio::lsb_codec::carrying::<_, S>(
+        samples.iter(),
+        spec.channels,
+        opts.audio_channels,
+        opts.audio_sample_step,
+    )
+    .count()
+        / 8
+}
+
+fn audio_decoder<'i, S: WavSample>(
+    spec: &WavSpec,
+    samples: &'i [S],
+    opts: &CodecOptions,
+) -> Box<dyn Read + 'i> {
+    super::audio::LsbCodec::decoder(
+        samples,
+        spec.channels,
+        opts.audio_channels,
+        opts.audio_sample_step,
+    )
+}
+
 impl Persist for Media {
     fn save_as(&mut self, file: &Path) -> Result<()> {
+        let io_error = |source| SteganoError::Io {
+            path: file.to_path_buf(),
+            source,
+        };
+        let image_error = |e: image::ImageError| {
+            error!("Error saving image to file: {:?}: {e}", file);
+            match e {
+                image::ImageError::IoError(source) => io_error(source),
+                _ => SteganoError::ImageEncodingError,
+            }
+        };
+        let audio_error = |e: hound::Error, otherwise: SteganoError| match e {
+            hound::Error::IoError(source) => io_error(source),
+            _ => otherwise,
+        };
+
         match self {
-            Media::Image(i) => i.save(file).map_err(|e| {
-                error!("Error saving image to file: {:?}: {e}", file);
-                SteganoError::ImageEncodingError
-            }),
-            Media::Audio((spec, samples)) => {
-                let mut writer =
-                    WavWriter::create(file, *spec).map_err(|_| SteganoError::AudioCreationError)?;
-                if let Some(error) = samples
-                    .iter()
-                    .map(|s| {
-                        writer
-                            .write_sample(*s)
-                            .map_err(|_| SteganoError::AudioEncodingError)
-                    })
-                    .filter_map(Result::err)
-                    .next()
-                {
-                    return Err(error);
-                }
+            Media::Image(i) => i.save(file).map_err(image_error),
+            Media::GrayImage(i) => i.save(file).map_err(image_error),
+            Media::Audio((spec, samples)) => write_samples(
+                WavWriter::create(file, *spec)
+                    .map_err(|e| audio_error(e, SteganoError::AudioCreationError))?,
+                samples,
+            )
+            .map_err(|e| audio_error(e, SteganoError::AudioEncodingError)),
+            Media::FloatAudio((spec, samples)) => write_samples(
+                WavWriter::create(file, *spec)
+                    .map_err(|e| audio_error(e, SteganoError::AudioCreationError))?,
+                samples,
+            )
+            .map_err(|e| audio_error(e, SteganoError::AudioEncodingError)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::payload::FabA;
+    use crate::media::HEADER_KEYSTREAM;
+    use crate::message::MESSAGE_HEADER_LEN;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
 
-                Ok(())
+    #[test]
+    fn should_decode_png_bytes() {
+        let data = std::fs::read("tests/images/plain/carrier-image.png").unwrap();
+        let expected = image::open("tests/images/plain/carrier-image.png")
+            .unwrap()
+            .to_rgba8();
+
+        let Media::Image(image) = Media::from_bytes(&data, MediaFormat::Png).unwrap() else {
+            panic!("PNG bytes were not decoded as image");
+        };
+        assert_eq!(image, expected);
+        assert!(matches!(
+            Media::from_bytes(&data, MediaFormat::Wav),
+            Err(SteganoError::InvalidAudioMedia)
+        ));
+    }
+
+    #[test]
+    fn should_decode_wav_bytes() {
+        let file = Path::new("tests/audio/plain/carrier-audio.wav");
+        let data = std::fs::read(file).unwrap();
+        let Media::Audio(expected) = Media::from_file(file).unwrap() else {
+            panic!("WAV file was not loaded as audio");
+        };
+
+        let Media::Audio(audio) = Media::from_bytes(&data, MediaFormat::Wav).unwrap() else {
+            panic!("WAV bytes were not decoded as audio");
+        };
+        assert_eq!(audio, expected);
+        assert!(matches!(
+            Media::from_bytes(&data, MediaFormat::Png),
+            Err(SteganoError::InvalidImageMedia)
+        ));
+    }
+
+    #[test]
+    fn should_decode_bmp_bytes() {
+        let image = RgbaImage::from_pixel(3, 2, image::Rgba([1, 2, 3, 255]));
+        let mut bmp = Cursor::new(Vec::new());
+        image.write_to(&mut bmp, image::ImageFormat::Bmp).unwrap();
+
+        let Media::Image(decoded) = Media::from_bytes(bmp.get_ref(), MediaFormat::Bmp).unwrap()
+        else {
+            panic!("BMP bytes were not decoded as image");
+        };
+        assert_eq!(decoded, image);
+    }
+
+    #[test]
+    fn should_accept_exactly_the_capacity_in_bytes() {
+        let media = Media::from_file(Path::new("tests/images/plain/carrier-image.png")).unwrap();
+        let opts = CodecOptions::default();
+        let (width, height) = media.dimensions().unwrap();
+        assert_eq!(media.sample_count(), None);
+
+        // 3 color channels of every pixel but the last row and column, one bit each
+        let capacity = media.capacity_bytes(&opts);
+        assert_eq!(capacity, ((width - 1) * (height - 1) * 3 / 8) as usize);
+        assert!(media.clone().hide_data(vec![0xaa; capacity], &opts).is_ok());
+        assert!(media
+            .clone()
+            .hide_data(vec![0xaa; capacity + 1], &opts)
+            .is_err());
+
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let audio = Media::Audio((spec, vec![0; 800]));
+        assert_eq!(audio.dimensions(), None);
+        assert_eq!(audio.sample_count(), Some(800));
+        assert_eq!(audio.capacity_bytes(&opts), 100);
+    }
+
+    #[test]
+    fn should_report_the_capacity_the_codec_actually_writes() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let image = RgbaImage::from_fn(7, 5, |_, _| image::Rgba(rng.gen()));
+        let gray = GrayImage::from_fn(7, 5, |x, y| image::Luma([(x * 30 + y * 7) as u8]));
+        // the naive `width * height * 3 / 8` over-promises once the last row and column are skipped
+        assert_eq!(7 * 5 * 3 / 8, 13);
+
+        let all_options = [
+            CodecOptions::default(),
+            CodecOptions {
+                skip_last_row_and_column: false,
+                ..Default::default()
+            },
+            CodecOptions {
+                color_channel_step_increment: 3,
+                ..Default::default()
+            },
+            CodecOptions {
+                skip_alpha_channel: false,
+                color_channel_step_increment: 2,
+                ..Default::default()
+            },
+            CodecOptions {
+                region: Some(crate::Rect::new(1, 1, 4, 3)),
+                ..Default::default()
+            },
+        ];
+        for opts in all_options {
+            let mut image = image.clone();
+            let capacity = Media::Image(image.clone()).capacity_bytes(&opts);
+            let written = super::super::image::LsbCodec::encoder(&mut image, &opts)
+                .write(&[0xaa; 64])
+                .unwrap();
+            assert_eq!(capacity, written, "{opts:?}");
+
+            let mut gray = gray.clone();
+            let capacity = Media::GrayImage(gray.clone()).capacity_bytes(&opts);
+            let written = super::super::image::LsbCodec::encoder(&mut gray, &opts)
+                .write(&[0xaa; 64])
+                .unwrap();
+            assert_eq!(capacity, written, "{opts:?}");
+        }
+        assert_eq!(
+            Media::Image(image).capacity_bytes(&CodecOptions::default()),
+            6 * 4 * 3 / 8
+        );
+    }
+
+    #[test]
+    fn should_obfuscate_the_header_and_restore_it() {
+        let carrier = Media::from_file(Path::new("tests/images/plain/carrier-image.png")).unwrap();
+        let opts = CodecOptions {
+            obfuscate_header: true,
+            ..Default::default()
+        };
+        let mut builder = crate::Message::builder();
+        builder
+            .add_file_data("secret.txt", b"Hello World!".to_vec())
+            .unwrap();
+        let secret = builder.build();
+        let data = secret.to_raw_data(&FabA).unwrap();
+
+        let mut media = carrier.clone();
+        media.hide_data(data.clone(), &opts).unwrap();
+
+        // the plain LSBs no longer start with the version byte and length header
+        let mut lsb = vec![0; data.len()];
+        media
+            .decoder(&CodecOptions::default())
+            .read_exact(&mut lsb)
+            .unwrap();
+        assert_ne!(lsb[0], data[0]);
+        assert_ne!(&lsb[..MESSAGE_HEADER_LEN], &data[..MESSAGE_HEADER_LEN]);
+        assert_eq!(
+            &lsb[HEADER_KEYSTREAM.len()..],
+            &data[HEADER_KEYSTREAM.len()..]
+        );
+
+        let unveiled =
+            crate::Message::from_raw_data_with_options(&mut media.decoder(&opts), &FabA, &opts)
+                .unwrap();
+        assert_eq!(unveiled.files, secret.files);
+    }
+
+    #[test]
+    fn should_fill_the_unused_capacity_with_noise() {
+        // a flat carrier, all its LSBs are zero
+        let carrier = Media::Image(RgbaImage::from_pixel(40, 30, image::Rgba([128; 4])));
+        let opts = CodecOptions {
+            randomize_unused: true,
+            ..Default::default()
+        };
+        let mut builder = crate::Message::builder();
+        builder
+            .add_file_data("secret.txt", b"Hello World!".to_vec())
+            .unwrap();
+        let secret = builder.build();
+        let data = secret.to_raw_data(&FabA).unwrap();
+
+        let mut media = carrier.clone();
+        media.hide_data(data.clone(), &opts).unwrap();
+
+        let mut lsb = Vec::new();
+        media
+            .decoder(&CodecOptions::default())
+            .read_to_end(&mut lsb)
+            .unwrap();
+        let tail = &lsb[data.len()..media.capacity_bytes(&opts)];
+        assert!(
+            tail.iter().any(|b| *b != 0),
+            "the tail is no longer original"
+        );
+        let ones: u32 = tail.iter().map(|b| b.count_ones()).sum();
+        let ratio = ones as f64 / (tail.len() * 8) as f64;
+        assert!((0.4..0.6).contains(&ratio), "the tail is noise, {ratio}");
+
+        let unveiled =
+            crate::Message::from_raw_data_with_options(&mut media.decoder(&opts), &FabA, &opts)
+                .unwrap();
+        assert_eq!(unveiled.files, secret.files);
+    }
+
+    #[test]
+    fn should_use_at_most_the_fill_fraction_of_the_capacity() {
+        let carrier = Media::Image(RgbaImage::from_pixel(40, 30, image::Rgba([128; 4])));
+        let opts = CodecOptions {
+            max_fill_fraction: Some(0.25),
+            ..Default::default()
+        };
+        let full = carrier.capacity_bytes(&CodecOptions::default());
+        let reduced = carrier.capacity_bytes(&opts);
+        assert_eq!(reduced, full / 4);
+
+        // fits the full carrier, but not a quarter of it
+        let data = vec![42; full / 2];
+        carrier
+            .clone()
+            .hide_data(data.clone(), &CodecOptions::default())
+            .unwrap();
+        let result = carrier.clone().hide_data(data, &opts).err();
+        assert!(
+            matches!(
+                result,
+                Some(SteganoError::CapacityExceeded { needed, available })
+                    if needed == full / 2 && available == reduced
+            ),
+            "{result:?}"
+        );
+
+        let mut media = carrier.clone();
+        media.hide_data(vec![42; reduced], &opts).unwrap();
+        let mut lsb = Vec::new();
+        media.decoder(&opts).read_to_end(&mut lsb).unwrap();
+        assert_eq!(&lsb[..reduced], vec![42; reduced]);
+        assert!(lsb[reduced..].iter().all(|b| *b == 0), "nothing beyond");
+    }
+
+    #[test]
+    fn should_measure_more_distortion_for_more_data() {
+        let original = Media::from_file(Path::new("tests/images/plain/carrier-image.png")).unwrap();
+        let opts = CodecOptions::default();
+        assert_eq!(original.psnr(&original).unwrap(), f64::INFINITY);
+
+        let mut small = original.clone();
+        small.hide_data(b"Hello World!".to_vec(), &opts).unwrap();
+        let small_psnr = small.psnr(&original).unwrap();
+
+        // alternating bits, so that about every second LSB has to flip
+        let mut full = original.clone();
+        let capacity = full.capacity_bytes(&opts);
+        full.hide_data(vec![0b1010_1010; capacity], &opts).unwrap();
+        let full_psnr = full.psnr(&original).unwrap();
+
+        assert!(small_psnr > 70.0, "small payload psnr was {small_psnr}");
+        assert!(full_psnr > 45.0, "full payload psnr was {full_psnr}");
+        assert!(small_psnr - full_psnr > 10.0);
+
+        assert!(matches!(
+            small.psnr(&Media::Image(RgbaImage::new(2, 2))),
+            Err(SteganoError::IncomparableMedia)
+        ));
+    }
+
+    #[test]
+    fn should_score_a_fully_embedded_image_higher_than_a_clean_one() {
+        // a smooth gradient of even values, like a photo its LSB pairs are far from balanced
+        let clean = Media::Image(RgbaImage::from_fn(256, 256, |x, y| {
+            image::Rgba([(x as u8) & !1, (y as u8) & !1, ((x + y) as u8) & !1, 255])
+        }));
+        let opts = CodecOptions::default();
+        let clean_score = clean.chi_square_score(&opts);
+
+        let mut embedded = clean.clone();
+        let capacity = embedded.capacity_bytes(&opts);
+        let mut rng = StdRng::seed_from_u64(1);
+        let data = (0..capacity).map(|_| rng.gen()).collect();
+        embedded.hide_data(data, &opts).unwrap();
+        let embedded_score = embedded.chi_square_score(&opts);
+
+        assert!(clean_score < 0.05, "clean score was {clean_score}");
+        assert!(embedded_score > 0.95, "embedded score was {embedded_score}");
+    }
+
+    #[test]
+    fn should_detect_formats_by_magic_numbers() {
+        let png = std::fs::read("tests/images/plain/carrier-image.png").unwrap();
+        let wav = std::fs::read("tests/audio/plain/carrier-audio.wav").unwrap();
+        let jpeg = std::fs::read("tests/images/NoSecrets.jpg").unwrap();
+        assert_eq!(MediaFormat::detect_from_bytes(&png), Some(MediaFormat::Png));
+        assert_eq!(MediaFormat::detect_from_bytes(&wav), Some(MediaFormat::Wav));
+        assert_eq!(
+            MediaFormat::detect_from_bytes(&jpeg),
+            Some(MediaFormat::Jpeg)
+        );
+        assert_eq!(
+            MediaFormat::detect_from_bytes(b"BM\0\0"),
+            Some(MediaFormat::Bmp)
+        );
+        assert_eq!(
+            MediaFormat::detect_from_bytes(b"GIF89a"),
+            Some(MediaFormat::Gif)
+        );
+        assert_eq!(MediaFormat::detect_from_bytes(b"RIFF\0\0\0\0AVI "), None);
+        assert_eq!(MediaFormat::detect_from_bytes(b""), None);
+    }
+
+    #[test]
+    fn should_list_the_formats_this_build_supports() {
+        let carriers = MediaFormat::supported_carrier_formats();
+        let outputs = MediaFormat::supported_output_formats();
+        for format in [MediaFormat::Png, MediaFormat::Wav] {
+            assert!(carriers.contains(&format));
+            assert!(outputs.contains(&format));
+        }
+        // detected, but not usable
+        for format in [MediaFormat::Jpeg, MediaFormat::Gif] {
+            assert!(!carriers.contains(&format));
+            assert!(!outputs.contains(&format));
+        }
+
+        let image = Media::Image(RgbaImage::new(4, 4));
+        let audio = Media::from_file(Path::new("tests/audio/plain/carrier-audio.wav")).unwrap();
+        for format in outputs {
+            let media = if *format == MediaFormat::Wav {
+                &audio
+            } else {
+                &image
+            };
+            let data = media.to_vec_as(*format).unwrap();
+            assert_eq!(MediaFormat::detect_from_bytes(&data), Some(*format));
+            if carriers.contains(format) {
+                Media::from_bytes(&data, *format).unwrap();
             }
         }
     }
+
+    #[test]
+    fn should_refuse_a_png_named_wav_and_load_it_without_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let png = std::fs::read("tests/images/plain/carrier-image.png").unwrap();
+        let as_wav = dir.path().join("carrier.wav");
+        let without_extension = dir.path().join("carrier");
+        std::fs::write(&as_wav, &png).unwrap();
+        std::fs::write(&without_extension, &png).unwrap();
+
+        assert!(matches!(
+            Media::from_file(&as_wav),
+            Err(SteganoError::FormatMismatch { extension, detected: MediaFormat::Png }) if extension == "wav"
+        ));
+        assert!(matches!(
+            Media::from_file(&without_extension),
+            Ok(Media::Image(_))
+        ));
+    }
+
+    #[test]
+    fn should_finalize_the_chunk_lengths_of_a_saved_wav() {
+        let dir = tempfile::tempdir().unwrap();
+        let stego = dir.path().join("stego.wav");
+        let mut media = Media::from_file(Path::new("tests/audio/plain/carrier-audio.wav")).unwrap();
+        let Media::Audio((_, samples)) = &media else {
+            panic!("WAV file was not loaded as audio");
+        };
+        let sample_count = samples.len();
+
+        media
+            .hide_data(b"finalized".to_vec(), &CodecOptions::default())
+            .unwrap()
+            .save_as(&stego)
+            .unwrap();
+
+        let reader = WavReader::open(&stego).unwrap();
+        assert_eq!(reader.len() as usize, sample_count);
+        assert_eq!(reader.into_samples::<i32>().count(), sample_count);
+
+        let bytes = std::fs::read(&stego).unwrap();
+        let riff_len = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+        assert_eq!(riff_len + 8, bytes.len(), "the RIFF chunk spans the file");
+    }
+
+    #[test]
+    fn should_hide_in_the_mantissa_of_a_float_wav() {
+        let dir = tempfile::tempdir().unwrap();
+        let carrier = dir.path().join("float.wav");
+        let stego = dir.path().join("stego.wav");
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        let mut original: Vec<f32> = (0..4000).map(|i| (i as f32 * 0.01).sin() * 0.8).collect();
+        original[3] = f32::INFINITY;
+        original[10] = f32::NEG_INFINITY;
+        original[17] = f32::NAN;
+        write_samples(WavWriter::create(&carrier, spec).unwrap(), &original).unwrap();
+
+        let mut media = Media::from_file(&carrier).unwrap();
+        assert!(matches!(media, Media::FloatAudio(_)));
+        assert_eq!(media.capacity_bytes(&CodecOptions::default()), 3997 / 8);
+        media
+            .hide_data(b"floating".to_vec(), &CodecOptions::default())
+            .unwrap()
+            .save_as(&stego)
+            .unwrap();
+
+        let media = Media::from_file(&stego).unwrap();
+        let Media::FloatAudio((stego_spec, samples)) = &media else {
+            panic!("float WAV was not loaded as float audio");
+        };
+        assert_eq!(*stego_spec, spec);
+        assert_eq!(samples[3], f32::INFINITY);
+        assert_eq!(samples[10], f32::NEG_INFINITY);
+        assert!(samples[17].is_nan());
+        for (sample, original) in samples.iter().zip(&original).filter(|(_, o)| o.is_finite()) {
+            assert!((sample - original).abs() <= f32::EPSILON);
+        }
+        assert_ne!(samples, &original);
+
+        let mut buf = vec![0; 8];
+        media
+            .decoder(&CodecOptions::default())
+            .read_exact(&mut buf)
+            .unwrap();
+        assert_eq!(buf, b"floating");
+    }
+
+    #[test]
+    fn should_only_accept_algorithms_that_fit_the_format() {
+        let jpeg = MediaFormat::of_file(Path::new("tests/images/NoSecrets.jpg")).unwrap();
+        let png = MediaFormat::of_file(Path::new("tests/images/plain/carrier-image.png")).unwrap();
+        assert_eq!(Algorithm::for_format(jpeg), Algorithm::F5);
+        assert_eq!(Algorithm::for_format(png), Algorithm::Lsb);
+
+        assert!(matches!(
+            Algorithm::F5.ensure_fits(jpeg),
+            Err(SteganoError::AlgorithmNotSupported(Algorithm::F5))
+        ));
+        assert!(Algorithm::Lsb.ensure_fits(MediaFormat::Wav).is_ok());
+        assert!(matches!(
+            Algorithm::F5.ensure_fits(png),
+            Err(SteganoError::AlgorithmMismatch {
+                algorithm: Algorithm::F5,
+                format: MediaFormat::Png
+            })
+        ));
+        assert!(Algorithm::Lsb.ensure_fits(jpeg).is_err());
+    }
 }
@@ -1,19 +1,157 @@
-use crate::media::payload::{PayloadCodec, PayloadCodecFactory, PayloadCodecFeatures};
+use crate::media::payload::{
+    create_decoder, multi_password_crypt_overhead, PayloadCodec, PayloadCodecFactory,
+    PayloadCodecFeatures, KEY_CRYPT_OVERHEAD, LONG_LENGTH_HEADER_EXTRA, PASSWORD_CRYPT_OVERHEAD,
+};
 use crate::result::Result;
-use crate::SteganoError;
+use crate::{CodecOptions, SteganoError};
 
 use byteorder::ReadBytesExt;
 use image::EncodableLayout;
+use log::warn;
+use std::collections::BTreeMap;
 use std::default::Default;
 use std::fs::File;
 use std::io::{Cursor, Read};
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroize;
+use zip::write::{ExtendedFileOptions, FileOptions};
 use zip::{ZipArchive, ZipWriter};
 
-#[derive(Debug, PartialEq, Eq)]
+/// the version byte and the 4 byte length header, a carrier smaller than this cannot hold any message
+pub(crate) const MESSAGE_HEADER_LEN: usize = 5;
+
+/// the payload is closed by a `0xff` byte after the length header and data
+const PAYLOAD_TRAILER_LEN: usize = 1;
+/// a zip without any file, it is just the end of central directory record
+const EMPTY_ZIP_LEN: usize = 22;
+
+/// header id of the private zip extra field that carries the `FileMetadata`,
+/// zip readers skip unknown extra fields, so older versions just ignore it
+const METADATA_EXTRA_FIELD: u16 = 0x5453;
+/// flag of the metadata extra field, the 8 byte little-endian modification time follows
+const METADATA_MODIFIED: u8 = 0b01;
+/// flag of the metadata extra field, the length prefixed MIME type follows
+const METADATA_MIME: u8 = 0b10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Message {
+    /// the files in the order they were added, they are hidden and unveiled in that order
     pub files: Vec<(String, Vec<u8>)>,
     pub text: Option<String>,
+    /// details of the files by file name, files without any details have no entry
+    pub metadata: BTreeMap<String, FileMetadata>,
+    /// names of the files that were damaged and skipped, see `CodecOptions::best_effort`
+    pub damaged_files: Vec<String>,
+}
+
+/// Optional details of a hidden file, so that it can be restored like it was
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileMetadata {
+    /// the MIME type, guessed from the file extension
+    pub mime: Option<String>,
+    /// the modification time in seconds since the unix epoch
+    pub modified: Option<u64>,
+}
+
+impl FileMetadata {
+    /// Collects the details of a file on the filesystem
+    fn of_file(file: &Path) -> Self {
+        let modified = std::fs::metadata(file)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|since_epoch| since_epoch.as_secs());
+
+        Self {
+            mime: guess_mime(file).map(str::to_owned),
+            modified,
+        }
+    }
+
+    /// the modification time as `SystemTime`, e.g. to restore it on an unveiled file
+    pub fn modified_time(&self) -> Option<SystemTime> {
+        self.modified
+            .map(|secs| UNIX_EPOCH + std::time::Duration::from_secs(secs))
+    }
+
+    fn to_extra_field(&self) -> Vec<u8> {
+        let mut field = vec![0];
+        if let Some(modified) = self.modified {
+            field[0] |= METADATA_MODIFIED;
+            field.extend_from_slice(&modified.to_le_bytes());
+        }
+        if let Some(mime) = self
+            .mime
+            .as_ref()
+            .filter(|mime| mime.len() <= u8::MAX as usize)
+        {
+            field[0] |= METADATA_MIME;
+            field.push(mime.len() as u8);
+            field.extend_from_slice(mime.as_bytes());
+        }
+
+        field
+    }
+
+    /// finds the metadata field among the raw extra fields of a zip entry,
+    /// `None` if there is none or it is broken
+    fn from_extra_fields(mut extra: &[u8]) -> Option<Self> {
+        while let [id_lo, id_hi, len_lo, len_hi, rest @ ..] = extra {
+            let len = u16::from_le_bytes([*len_lo, *len_hi]) as usize;
+            let field = rest.get(..len)?;
+            if u16::from_le_bytes([*id_lo, *id_hi]) == METADATA_EXTRA_FIELD {
+                return Self::from_extra_field(field);
+            }
+            extra = &rest[len..];
+        }
+
+        None
+    }
+
+    fn from_extra_field(field: &[u8]) -> Option<Self> {
+        let (&flags, mut field) = field.split_first()?;
+        let mut metadata = Self::default();
+        if flags & METADATA_MODIFIED != 0 {
+            let (modified, rest) = field.split_first_chunk::<8>()?;
+            metadata.modified = Some(u64::from_le_bytes(*modified));
+            field = rest;
+        }
+        if flags & METADATA_MIME != 0 {
+            let (&len, rest) = field.split_first()?;
+            let mime = rest.get(..len as usize)?;
+            metadata.mime = Some(String::from_utf8_lossy(mime).to_string());
+        }
+
+        Some(metadata)
+    }
+}
+
+/// guesses the MIME type of the common file types by their extension
+fn guess_mime(file: &Path) -> Option<&'static str> {
+    let extension = file.extension()?.to_str()?.to_lowercase();
+    let mime = match extension.as_str() {
+        "txt" | "md" => "text/plain",
+        "html" | "htm" => "text/html",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        "wav" => "audio/wav",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        "bin" => "application/octet-stream",
+        _ => return None,
+    };
+
+    Some(mime)
 }
 
 impl Message {
@@ -21,12 +159,21 @@ impl Message {
     pub fn from_raw_data(
         dec: &mut dyn Read,
         codec_factory: &dyn PayloadCodecFactory,
+    ) -> Result<Self> {
+        Self::from_raw_data_with_options(dec, codec_factory, &CodecOptions::default())
+    }
+
+    /// Like `from_raw_data`, but honors `CodecOptions::max_extract_bytes` and
+    /// `CodecOptions::best_effort`
+    pub(crate) fn from_raw_data_with_options(
+        dec: &mut dyn Read,
+        codec_factory: &dyn PayloadCodecFactory,
+        options: &CodecOptions,
     ) -> Result<Self> {
         let version = dec.read_u8()?;
-        let codec: Box<dyn PayloadCodec> =
-            codec_factory.create_codec(PayloadCodecFeatures::MixedFeatures(version))?;
+        let codec: Box<dyn PayloadCodec> = create_decoder(codec_factory, version)?;
 
-        let message = decode_message(&*codec, dec)?;
+        let message = decode_message(&*codec, dec, options)?;
 
         Ok(message)
     }
@@ -38,6 +185,8 @@ impl Message {
         Ok(Self {
             files: Default::default(),
             text: Some(text),
+            metadata: Default::default(),
+            damaged_files: Default::default(),
         })
     }
 
@@ -54,11 +203,22 @@ impl Message {
     }
 
     pub fn add_file<P: AsRef<Path> + ?Sized>(&mut self, file: &P) -> Result<&mut Self> {
-        let mut fd = File::open(file)?;
-        let mut fb: Vec<u8> = Vec::new();
-
-        fd.read_to_end(&mut fb)?;
+        let fb = read_data_file(file.as_ref())?;
         self.add_file_data(file, fb)?;
+        self.set_metadata_of(file.as_ref(), FileMetadata::of_file(file.as_ref()));
+
+        Ok(self)
+    }
+
+    /// Adds the content of `file` but stores it under `stored_name`, useful to disambiguate files with equal names
+    pub fn add_file_as<P: AsRef<Path> + ?Sized>(
+        &mut self,
+        file: &P,
+        stored_name: &str,
+    ) -> Result<&mut Self> {
+        let fb = read_data_file(file.as_ref())?;
+        self.add_file_data(stored_name, fb)?;
+        self.set_metadata_of(Path::new(stored_name), FileMetadata::of_file(file.as_ref()));
 
         Ok(self)
     }
@@ -71,15 +231,28 @@ impl Message {
         let file = file
             .as_ref()
             .file_name()
-            .ok_or_else(|| SteganoError::InvalidFileName)?
+            .ok_or(SteganoError::InvalidFileName)?
             .to_str()
-            .ok_or_else(|| SteganoError::InvalidFileName)?;
+            .ok_or(SteganoError::InvalidFileName)?;
+
+        if self.files.iter().any(|(name, _)| name == file) {
+            return Err(SteganoError::DuplicateFileName {
+                name: file.to_owned(),
+            });
+        }
 
         self.files.push((file.to_owned(), data));
 
         Ok(self)
     }
 
+    /// stores the metadata for the file that was just added under the file name of `file`
+    fn set_metadata_of(&mut self, file: &Path, metadata: FileMetadata) {
+        if let Some(name) = file.file_name().and_then(|name| name.to_str()) {
+            self.metadata.insert(name.to_owned(), metadata);
+        }
+    }
+
     pub fn features(&self) -> PayloadCodecFeatures {
         if self.files.is_empty() {
             PayloadCodecFeatures::TextOnly
@@ -92,44 +265,250 @@ impl Message {
         Self::new()
     }
 
+    /// Starts a message that keeps track of its size while files are added
+    pub fn builder() -> MessageBuilder {
+        MessageBuilder::default()
+    }
+
     fn new() -> Self {
         Message {
             files: Vec::new(),
             text: None,
+            metadata: BTreeMap::new(),
+            damaged_files: Vec::new(),
         }
     }
 
-    fn from_documents_data(buf: Vec<u8>) -> Result<Message> {
+    fn from_documents_data(buf: Vec<u8>, options: &CodecOptions) -> Result<Message> {
         // todo: thinking about refactoring that, so that the this whole logic is actually ankered in the codec, or at least in the codec factory
         let mut buf = Cursor::new(buf);
         let mut m = Message::new();
 
         let mut zip = ZipArchive::new(&mut buf)?;
+        // the text counts towards the extraction limit as well
+        let mut extracted = zip.comment().len();
+        if let Some(limit) = options.max_extract_bytes.filter(|limit| extracted > *limit) {
+            return Err(SteganoError::ExtractionLimitExceeded {
+                claimed: extracted,
+                limit,
+            });
+        }
         if !zip.comment().is_empty() {
             m.text = Some(String::from_utf8_lossy(zip.comment().as_bytes()).to_string())
         }
 
         for i in 0..zip.len() {
-            let mut file = zip.by_index(i)?;
+            // the central directory knows where each file starts, so a damaged one is skipped
+            // without losing the ones after it
+            let entry_name = zip.name_for_index(i).unwrap_or_default().to_string();
+            let mut file = match zip.by_index(i) {
+                Ok(file) => file,
+                Err(e) if options.best_effort => {
+                    warn!("Skipping the damaged file {entry_name}: {e}");
+                    m.damaged_files.push(entry_name);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
             let mut writer = Vec::new();
-            file.read_to_end(&mut writer)?;
-
-            m.files.push((
-                file.mangled_name()
-                    .to_str()
-                    .unwrap_or("--no-file-name--")
-                    .to_string(),
-                writer,
-            ));
+            let read = match options.max_extract_bytes {
+                Some(limit) => {
+                    // the declared size is checked before anything is allocated, and as it
+                    // may lie, the extraction stops right after the limit too
+                    let claimed = extracted.saturating_add(file.size() as usize);
+                    if claimed > limit {
+                        return Err(SteganoError::ExtractionLimitExceeded { claimed, limit });
+                    }
+                    (&mut file)
+                        .take((limit - extracted) as u64 + 1)
+                        .read_to_end(&mut writer)
+                }
+                None => file.read_to_end(&mut writer),
+            };
+            match read {
+                Ok(_) => {}
+                Err(e) if options.best_effort => {
+                    warn!("Skipping the damaged file {entry_name}: {e}");
+                    m.damaged_files.push(entry_name);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            }
+            if let Some(limit) = options.max_extract_bytes {
+                extracted += writer.len();
+                if extracted > limit {
+                    return Err(SteganoError::ExtractionLimitExceeded {
+                        claimed: extracted,
+                        limit,
+                    });
+                }
+            }
+
+            let name = file
+                .mangled_name()
+                .to_str()
+                .unwrap_or("--no-file-name--")
+                .to_string();
+            if let Some(metadata) = file.extra_data().and_then(FileMetadata::from_extra_fields) {
+                m.metadata.insert(name.clone(), metadata);
+            }
+            m.files.push((name, writer));
         }
 
         Ok(m)
     }
 
+    /// The number of bytes `to_raw_data` returns, with the payload header, the zip entries and
+    /// the encryption. Only one file at a time is compressed, the payload is never allocated.
+    pub fn serialized_size(&self, codec_factory: &dyn PayloadCodecFactory) -> Result<usize> {
+        let codec = codec_factory.create_codec(self.features())?;
+        if !codec.has_feature(PayloadCodecFeatures::LengthHeader) {
+            // a legacy payload is only used without files, it is small enough to be encoded
+            return Ok(encode_message(&*codec, self)?.len());
+        }
+        let files_size = self
+            .files
+            .iter()
+            .map(|file| Ok(zip_files([file], &self.metadata)?.len() - EMPTY_ZIP_LEN))
+            .sum::<Result<usize>>()?;
+
+        Ok(serialized_size(files_size, codec_factory.crypt_overhead()))
+    }
+
     pub fn to_raw_data(&self, codec_factory: &dyn PayloadCodecFactory) -> Result<Vec<u8>> {
         let codec = codec_factory.create_codec(self.features())?;
         encode_message(&*codec, self)
     }
+
+    /// Overwrites the content of the files and the text with zeros, for a message that was
+    /// only decrypted in memory
+    pub(crate) fn zeroize(&mut self) {
+        for (_, data) in self.files.iter_mut() {
+            data.zeroize();
+        }
+        self.text.zeroize();
+    }
+}
+
+/// Builds a `Message` file by file and keeps a running total of the bytes it occupies in a
+/// carrier, so that adding files can stop before the carrier overflows.
+///
+/// Every file is compressed once when it is added, like it will be when hiding.
+#[derive(Debug, Clone)]
+pub struct MessageBuilder {
+    message: Message,
+    /// the zip entries of all files so far
+    files_size: usize,
+    encryption_overhead: usize,
+}
+
+impl Default for MessageBuilder {
+    fn default() -> Self {
+        Self {
+            message: Message::new(),
+            files_size: 0,
+            encryption_overhead: 0,
+        }
+    }
+}
+
+impl MessageBuilder {
+    /// Accounts for the encryption with a password, like `SteganoEncoder::with_encryption`
+    pub fn encrypted(&mut self, encrypted: bool) -> &mut Self {
+        self.encryption_overhead = if encrypted {
+            PASSWORD_CRYPT_OVERHEAD
+        } else {
+            0
+        };
+        self
+    }
+
+    /// Accounts for the encryption with a raw key, like `SteganoEncoder::with_key_file`
+    pub(crate) fn key_encrypted(&mut self) -> &mut Self {
+        self.encryption_overhead = KEY_CRYPT_OVERHEAD;
+        self
+    }
+
+    /// Accounts for the encryption for several passwords, like `SteganoEncoder::with_encryption_multi`
+    pub(crate) fn encrypted_for_passwords(&mut self, passwords: usize) -> &mut Self {
+        self.encryption_overhead = multi_password_crypt_overhead(passwords);
+        self
+    }
+
+    /// see `Message::add_file`
+    pub fn add_file<P: AsRef<Path> + ?Sized>(&mut self, file: &P) -> Result<&mut Self> {
+        self.message.add_file(file)?;
+        self.measure_last_file()?;
+
+        Ok(self)
+    }
+
+    /// see `Message::add_file_as`
+    pub fn add_file_as<P: AsRef<Path> + ?Sized>(
+        &mut self,
+        file: &P,
+        stored_name: &str,
+    ) -> Result<&mut Self> {
+        self.message.add_file_as(file, stored_name)?;
+        self.measure_last_file()?;
+
+        Ok(self)
+    }
+
+    /// see `Message::add_file_data`
+    pub fn add_file_data<P: AsRef<Path> + ?Sized>(
+        &mut self,
+        file: &P,
+        data: Vec<u8>,
+    ) -> Result<&mut Self> {
+        self.message.add_file_data(file, data)?;
+        self.measure_last_file()?;
+
+        Ok(self)
+    }
+
+    /// Replaces the files by the ones of `message`, the encryption is kept
+    pub(crate) fn with_message(&mut self, message: Message) -> Result<&mut Self> {
+        self.files_size = zip_files(&message.files, &message.metadata)?.len() - EMPTY_ZIP_LEN;
+        self.message = message;
+
+        Ok(self)
+    }
+
+    /// Removes all files, the encryption is kept
+    pub fn clear(&mut self) -> &mut Self {
+        self.message = Message::new();
+        self.files_size = 0;
+        self
+    }
+
+    /// The number of bytes the message occupies in a carrier, with the payload header and the
+    /// encryption. Exact for the current payload version, legacy versions need a bit less.
+    pub fn current_size(&self) -> usize {
+        serialized_size(self.files_size, self.encryption_overhead)
+    }
+
+    /// true if the message fits into a carrier with `capacity` bytes
+    pub fn would_fit(&self, capacity: usize) -> bool {
+        self.current_size() <= capacity
+    }
+
+    /// The message that is built so far
+    pub fn message(&self) -> &Message {
+        &self.message
+    }
+
+    pub fn build(self) -> Message {
+        self.message
+    }
+
+    fn measure_last_file(&mut self) -> Result<()> {
+        if let Some(file) = self.message.files.last() {
+            self.files_size += zip_files([file], &self.message.metadata)?.len() - EMPTY_ZIP_LEN;
+        }
+
+        Ok(())
+    }
 }
 
 // impl TryFrom<&mut Vec<u8>> for Message {
@@ -150,17 +529,48 @@ impl Message {
 //     }
 // }
 
+/// reads a data file that should be hidden, any failure is reported with the path of the file
+fn read_data_file(file: &Path) -> Result<Vec<u8>> {
+    let unreadable = |source| SteganoError::Io {
+        path: file.to_path_buf(),
+        source,
+    };
+    let mut fd = File::open(file).map_err(unreadable)?;
+    let mut fb: Vec<u8> = Vec::new();
+    fd.read_to_end(&mut fb).map_err(unreadable)?;
+
+    Ok(fb)
+}
+
 pub(crate) fn encode_message(encoder: &dyn PayloadCodec, msg: &Message) -> Result<Vec<u8>> {
+    let buf = zip_files(&msg.files, &msg.metadata)?;
+
+    encoder.encode(&mut Cursor::new(buf))
+}
+
+/// zips the files, each file entry adds the same number of bytes no matter what other files are zipped
+fn zip_files<'a>(
+    files: impl IntoIterator<Item = &'a (String, Vec<u8>)>,
+    metadata: &BTreeMap<String, FileMetadata>,
+) -> Result<Vec<u8>> {
     let mut buf = Vec::new();
 
     {
         let w = Cursor::new(&mut buf);
         let mut zip = ZipWriter::new(w);
 
-        let options = zip::write::SimpleFileOptions::default()
+        let options = FileOptions::<ExtendedFileOptions>::default()
             .compression_method(zip::CompressionMethod::Deflated);
 
-        for (name, buf) in (msg.files).iter().map(|(name, buf)| (name, buf)) {
+        for (name, buf) in files {
+            let mut options = options.clone();
+            if let Some(metadata) = metadata.get(name) {
+                options.add_extra_data(
+                    METADATA_EXTRA_FIELD,
+                    metadata.to_extra_field().into_boxed_slice(),
+                    false,
+                )?;
+            }
             zip.start_file(name, options)?;
 
             let mut r = Cursor::new(buf);
@@ -170,16 +580,107 @@ pub(crate) fn encode_message(encoder: &dyn PayloadCodec, msg: &Message) -> Resul
         zip.finish()?;
     }
 
-    encoder.encode(&mut Cursor::new(buf))
+    Ok(buf)
 }
 
-pub(crate) fn decode_message(decoder: &dyn PayloadCodec, data: &mut dyn Read) -> Result<Message> {
-    let content = decoder.decode(data)?;
+/// the bytes a payload occupies in a carrier whose zip entries take `files_size` bytes
+fn serialized_size(files_size: usize, encryption_overhead: usize) -> usize {
+    let payload = encryption_overhead + EMPTY_ZIP_LEN + files_size;
+    // payloads beyond 4 GiB need the long length header
+    let long_header = if payload > u32::MAX as usize {
+        LONG_LENGTH_HEADER_EXTRA
+    } else {
+        0
+    };
+
+    MESSAGE_HEADER_LEN + long_header + PAYLOAD_TRAILER_LEN + payload
+}
+
+/// keeps a copy of everything that is read, to look into a payload that turned out to be truncated
+struct RecordingReader<'a> {
+    inner: &'a mut dyn Read,
+    recorded: Vec<u8>,
+}
+
+impl Read for RecordingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.recorded.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Walks the local headers at the start of a truncated zip and returns
+/// `SteganoError::TruncatedPayload` for the first file whose data is cut off
+fn truncated_zip_entry(zip: &[u8]) -> Option<SteganoError> {
+    const LOCAL_HEADER_LEN: usize = 30;
+    let u16_at =
+        |at: usize| Some(u16::from_le_bytes(zip.get(at..at + 2)?.try_into().ok()?) as usize);
+    let u32_at =
+        |at: usize| Some(u32::from_le_bytes(zip.get(at..at + 4)?.try_into().ok()?) as usize);
+
+    let mut at = 0;
+    while zip.get(at..at + 4)? == b"PK\x03\x04" {
+        let expected = u32_at(at + 18)?;
+        let name_len = u16_at(at + 26)?;
+        let extra_len = u16_at(at + 28)?;
+        let name = zip.get(at + LOCAL_HEADER_LEN..at + LOCAL_HEADER_LEN + name_len)?;
+        let data_start = at + LOCAL_HEADER_LEN + name_len + extra_len;
+        let got = zip.len().saturating_sub(data_start);
+        if got < expected {
+            return Some(SteganoError::TruncatedPayload {
+                file_name: String::from_utf8_lossy(name).to_string(),
+                expected,
+                got,
+            });
+        }
+        at = data_start + expected;
+    }
+
+    None
+}
+
+pub(crate) fn decode_message(
+    decoder: &dyn PayloadCodec,
+    data: &mut dyn Read,
+    options: &CodecOptions,
+) -> Result<Message> {
+    let mut recorded = RecordingReader {
+        inner: data,
+        recorded: Vec::new(),
+    };
+    let content = match decoder.decode(&mut recorded) {
+        // a plain zip tells which of its files got cut off, an encrypted one cannot
+        Err(SteganoError::TruncatedRawData(expected, got))
+            if decoder.has_feature(PayloadCodecFeatures::TextAndDocuments)
+                && !decoder.has_feature(PayloadCodecFeatures::ChaCrypto) =>
+        {
+            let header_len = if decoder.has_feature(PayloadCodecFeatures::LongLengthHeader) {
+                8
+            } else {
+                4
+            };
+            let zip = recorded.recorded.get(header_len..).unwrap_or_default();
+            return Err(
+                truncated_zip_entry(zip).unwrap_or(SteganoError::TruncatedRawData(expected, got))
+            );
+        }
+        content => content?,
+    };
 
     if decoder.has_feature(PayloadCodecFeatures::TextOnly) {
+        if let Some(limit) = options
+            .max_extract_bytes
+            .filter(|limit| content.len() > *limit)
+        {
+            return Err(SteganoError::ExtractionLimitExceeded {
+                claimed: content.len(),
+                limit,
+            });
+        }
         Message::from_utf8(content)
     } else if decoder.has_feature(PayloadCodecFeatures::TextAndDocuments) {
-        Message::from_documents_data(content)
+        Message::from_documents_data(content, options)
     } else {
         Err(SteganoError::UnsupportedMessageFormat(
             decoder.version().into(),
@@ -191,7 +692,7 @@ pub(crate) fn decode_message(decoder: &dyn PayloadCodec, data: &mut dyn Read) ->
 mod tests {
     use coverage_helper::test;
 
-    use crate::media::payload::{legacy, FabA, HasFeature, TEXT_ONLY};
+    use crate::media::payload::{legacy, FabA, FabK, FabM, FabS, HasFeature, TEXT_ONLY};
 
     use super::*;
     use std::io::{copy, BufReader};
@@ -269,6 +770,274 @@ mod tests {
         assert_eq!(m.files.len(), 0, "Message.files were not empty.");
     }
 
+    #[test]
+    fn should_reject_duplicate_file_names() {
+        let mut m = Message::empty();
+        m.add_file("tests/images/secrets/Blah.txt").unwrap();
+
+        match m.add_file("tests/images/secrets/Blah.txt").err() {
+            Some(SteganoError::DuplicateFileName { name }) => assert_eq!(name, "Blah.txt"),
+            err => panic!("Error was not of type DuplicateFileName, but was of {err:?}"),
+        }
+
+        m.add_file_as("tests/images/secrets/Blah.txt", "Blah-copy.txt")
+            .unwrap();
+        assert_eq!(m.files.len(), 2);
+        assert_eq!(m.files[1].0, "Blah-copy.txt");
+        assert_eq!(m.files[0].1, m.files[1].1);
+    }
+
+    #[test]
+    fn should_error_on_unreadable_data_files() {
+        let mut m = Message::empty();
+
+        assert!(matches!(
+            m.add_file("tests/images/secrets/missing.txt"),
+            Err(SteganoError::Io { .. })
+        ));
+        assert!(matches!(
+            m.add_file_as("tests/images/secrets", "secrets"),
+            Err(SteganoError::Io { path, .. }) if path == Path::new("tests/images/secrets")
+        ));
+        assert!(m.files.is_empty());
+    }
+
+    #[test]
+    fn should_round_trip_the_file_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.txt");
+        std::fs::write(&file, b"some notes").unwrap();
+        let modified = UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000);
+        File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let mut m = Message::empty();
+        m.add_file(&file).unwrap();
+        m.add_file_data("no-metadata.bin", vec![1, 2, 3]).unwrap();
+        let b = m.to_raw_data(&FabA).unwrap();
+        let m = Message::from_raw_data(&mut Cursor::new(b), &FabA).unwrap();
+
+        assert_eq!(
+            m.metadata.get("notes.txt"),
+            Some(&FileMetadata {
+                mime: Some("text/plain".to_owned()),
+                modified: Some(1_600_000_000),
+            })
+        );
+        assert_eq!(m.metadata["notes.txt"].modified_time(), Some(modified));
+        assert!(!m.metadata.contains_key("no-metadata.bin"));
+    }
+
+    #[test]
+    fn should_compute_the_serialized_size_without_serializing() {
+        let mut m = Message::empty();
+        for fab in [&FabA as &dyn PayloadCodecFactory, &FabS::new("secret")] {
+            assert_eq!(
+                m.serialized_size(fab).unwrap(),
+                m.to_raw_data(fab).unwrap().len()
+            );
+        }
+
+        m.add_file("tests/images/secrets/Blah.txt").unwrap();
+        m.add_file_data("lorem.txt", b"lorem ipsum ".repeat(100))
+            .unwrap();
+        let fabs: [&dyn PayloadCodecFactory; 4] = [
+            &FabA,
+            &FabS::new("secret"),
+            &FabK::new([7; 32]),
+            &FabM::new(["secret", "another secret"]),
+        ];
+        for fab in fabs {
+            assert_eq!(
+                m.serialized_size(fab).unwrap(),
+                m.to_raw_data(fab).unwrap().len()
+            );
+        }
+    }
+
+    #[test]
+    fn should_track_the_size_until_the_files_do_not_fit_anymore() {
+        let opts = crate::CodecOptions::default();
+        let mut carrier = crate::testing::generate_carrier(256, 256, 3);
+        let capacity = carrier.capacity_bytes(&opts);
+
+        let mut builder = Message::builder();
+        builder.add_file("LICENSE").unwrap();
+        assert_eq!(
+            builder.current_size(),
+            builder.message().to_raw_data(&FabA).unwrap().len()
+        );
+        let mut noise = crate::testing::generate_carrier(16, 16, 9)
+            .to_vec()
+            .unwrap();
+        let mut i = 0;
+        while builder.would_fit(capacity) {
+            noise.rotate_left(1);
+            builder
+                .add_file_data(&format!("noise-{i}.png"), noise.clone())
+                .unwrap();
+            i += 1;
+            // the running total is exact, so the last file that fits leaves no byte unaccounted
+            assert_eq!(
+                builder.current_size(),
+                builder.message().to_raw_data(&FabA).unwrap().len()
+            );
+        }
+        assert!(i > 1);
+
+        let data = builder.message().to_raw_data(&FabA).unwrap();
+        assert!(data.len() > capacity);
+        assert!(carrier.hide_data(data, &opts).is_err());
+
+        builder.key_encrypted();
+        let encrypted = builder.message().to_raw_data(&FabK::new([7; 32])).unwrap();
+        assert_eq!(builder.current_size(), encrypted.len());
+        builder.encrypted(true);
+        let encrypted = builder.message().to_raw_data(&FabS::new("secret")).unwrap();
+        assert_eq!(builder.current_size(), encrypted.len());
+        builder.encrypted_for_passwords(2);
+        let encrypted = builder
+            .message()
+            .to_raw_data(&FabM::new(["secret", "another secret"]))
+            .unwrap();
+        assert_eq!(builder.current_size(), encrypted.len());
+    }
+
+    fn limited(max_extract_bytes: usize) -> CodecOptions {
+        CodecOptions {
+            max_extract_bytes: Some(max_extract_bytes),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_skip_a_damaged_file_with_best_effort() {
+        use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+        let mut secret = Message::empty();
+        for (name, seed) in [("first.bin", 1), ("second.bin", 2), ("third.bin", 3)] {
+            // incompressible, so that the data of each file lies plainly in the zip
+            let mut data = vec![0; 1000];
+            StdRng::seed_from_u64(seed).fill_bytes(&mut data);
+            secret.add_file_data(name, data).unwrap();
+        }
+        let mut raw = secret.to_raw_data(&FabA).unwrap();
+        let second = raw.windows(10).position(|w| w == b"second.bin").unwrap();
+        raw[second + 500] ^= 0xff;
+
+        assert!(Message::from_raw_data(&mut Cursor::new(&raw), &FabA).is_err());
+
+        let options = CodecOptions {
+            best_effort: true,
+            ..Default::default()
+        };
+        let m = Message::from_raw_data_with_options(&mut Cursor::new(&raw), &FabA, &options)
+            .expect("The intact files could not be extracted");
+        let names: Vec<_> = m.files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["first.bin", "third.bin"]);
+        assert_eq!(m.files, [secret.files[0].clone(), secret.files[2].clone()]);
+        assert_eq!(m.damaged_files, ["second.bin"]);
+    }
+
+    #[test]
+    fn should_refuse_files_that_claim_more_than_the_extraction_limit() {
+        let mut secret = Message::empty();
+        secret.add_file_data("bomb.bin", b"tiny".to_vec()).unwrap();
+        let raw = secret.to_raw_data(&FabA).unwrap();
+
+        // the zip entry claims almost 4 GiB, in the local header and in the central directory
+        let mut bomb = raw.clone();
+        let claimed = 0xffff_fff0_u32;
+        for (signature, size_offset) in [(b"PK\x03\x04", 22), (b"PK\x01\x02", 24)] {
+            let at = bomb.windows(4).position(|w| w == signature).unwrap() + size_offset;
+            bomb[at..at + 4].copy_from_slice(&claimed.to_le_bytes());
+        }
+        let result =
+            Message::from_raw_data_with_options(&mut Cursor::new(&bomb), &FabA, &limited(1024));
+        assert!(
+            matches!(
+                result,
+                Err(SteganoError::ExtractionLimitExceeded {
+                    claimed: 0xffff_fff0,
+                    limit: 1024
+                })
+            ),
+            "{result:?}"
+        );
+
+        let result =
+            Message::from_raw_data_with_options(&mut Cursor::new(&raw), &FabA, &limited(3));
+        assert!(matches!(
+            result,
+            Err(SteganoError::ExtractionLimitExceeded {
+                claimed: 4,
+                limit: 3
+            })
+        ));
+        let unveiled =
+            Message::from_raw_data_with_options(&mut Cursor::new(&raw), &FabA, &limited(4))
+                .unwrap();
+        assert_eq!(unveiled.files, secret.files);
+    }
+
+    #[test]
+    fn should_count_the_text_towards_the_extraction_limit() {
+        let mut secret = Message::empty();
+        secret.add_file_data("tiny.bin", b"tiny".to_vec()).unwrap();
+        let mut raw = secret.to_raw_data(&FabA).unwrap();
+        // older versions kept the text as zip comment, it ends the zip right before the terminator
+        let terminator = raw.split_off(raw.len() - 1);
+        raw.truncate(raw.len() - 2);
+        raw.extend_from_slice(&[5, 0]);
+        raw.extend_from_slice(b"hello");
+        raw.extend_from_slice(&terminator);
+        let len = u32::from_be_bytes(raw[1..5].try_into().unwrap()) + 5;
+        raw[1..5].copy_from_slice(&len.to_be_bytes());
+
+        for (limit, claimed) in [(4, 5), (8, 9)] {
+            let result =
+                Message::from_raw_data_with_options(&mut Cursor::new(&raw), &FabA, &limited(limit));
+            assert!(
+                matches!(
+                    result,
+                    Err(SteganoError::ExtractionLimitExceeded { claimed: c, limit: l })
+                        if c == claimed && l == limit
+                ),
+                "{result:?}"
+            );
+        }
+        let unveiled =
+            Message::from_raw_data_with_options(&mut Cursor::new(&raw), &FabA, &limited(9))
+                .unwrap();
+        assert_eq!(unveiled.text.as_deref(), Some("hello"));
+        assert_eq!(unveiled.files, secret.files);
+    }
+
+    #[test]
+    fn should_ignore_broken_or_foreign_extra_fields() {
+        // a foreign field first, then a metadata field that announces a time but ends early
+        let extra = [
+            0x55,
+            0x54,
+            1,
+            0,
+            0xff,
+            0x53,
+            0x54,
+            3,
+            0,
+            METADATA_MODIFIED,
+            1,
+            2,
+        ];
+        assert_eq!(FileMetadata::from_extra_fields(&extra), None);
+        assert_eq!(FileMetadata::from_extra_fields(&extra[..5]), None);
+    }
+
     #[test]
     fn should_create_zip_that_is_windows_compatible() -> std::io::Result<()> {
         let mut file = File::open("tests/images/with_text/hello_world.png")?;
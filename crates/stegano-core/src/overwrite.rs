@@ -0,0 +1,74 @@
+//! Replacing and scrubbing the source carrier, see `SteganoEncoder::overwrite_source`
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+use crate::error::SteganoError;
+use crate::result::Result;
+
+/// true if both paths lead to the same existing file
+pub(crate) fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// a hidden sibling of `target` to write to first, it keeps the extension that tells the format
+pub(crate) fn partial_path(target: &Path) -> PathBuf {
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+
+    target.with_file_name(format!(".stegano-{}-{name}", std::process::id()))
+}
+
+/// Moves the completely written `partial` file over `target` with one rename, so that `target`
+/// is never seen half written. The former content of `target` is scrubbed afterwards.
+pub(crate) fn replace(partial: &Path, target: &Path) -> Result<()> {
+    File::open(partial)
+        .and_then(|file| file.sync_all())
+        .map_err(io_error(partial))?;
+    // the handle keeps the former content reachable after the rename, so that it can be scrubbed
+    let former = OpenOptions::new()
+        .write(true)
+        .open(target)
+        .map_err(io_error(target))?;
+    std::fs::rename(partial, target).map_err(io_error(target))?;
+
+    scrub_file(former).map_err(io_error(target))
+}
+
+/// Overwrites the content of the file with noise and removes it
+pub(crate) fn scrub(path: &Path) -> Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .open(path)
+        .and_then(scrub_file)
+        .and_then(|_| std::fs::remove_file(path))
+        .map_err(io_error(path))
+}
+
+/// note that copy-on-write filesystems and SSDs might keep the old blocks anyway
+fn scrub_file(mut file: File) -> std::io::Result<()> {
+    let mut rng = StdRng::from_entropy();
+    let mut noise = [0; 4096];
+    let mut left = file.metadata()?.len();
+    while left > 0 {
+        let len = left.min(noise.len() as u64) as usize;
+        rng.fill_bytes(&mut noise[..len]);
+        file.write_all(&noise[..len])?;
+        left -= len as u64;
+    }
+
+    file.sync_all()
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> SteganoError + '_ {
+    move |source| SteganoError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
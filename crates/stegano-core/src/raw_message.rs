@@ -1,9 +1,10 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use byteorder::ReadBytesExt;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
-use crate::media::payload::{PayloadCodec, PayloadCodecFactory, PayloadCodecFeatures};
+use crate::media::payload::{create_decoder, PayloadCodec, PayloadCodecFactory};
 use crate::result::Result;
+use crate::SteganoError;
 
 #[derive(Debug, Default)]
 pub struct RawMessage {
@@ -17,13 +18,51 @@ impl AsRef<[u8]> for RawMessage {
 }
 
 impl RawMessage {
+    /// Reads the plain bitstream until the decoder is exhausted, no message header is expected
+    pub fn of(dec: &mut dyn Read) -> Result<Self> {
+        let mut content = Vec::new();
+        dec.read_to_end(&mut content)?;
+
+        Ok(Self { content })
+    }
+
+    /// Writes the content verbatim as plain bitstream, symmetric to `RawMessage::of`
+    pub fn write_to(&self, enc: &mut dyn Write) -> Result<()> {
+        enc.write_all(&self.content)?;
+
+        Ok(())
+    }
+
+    /// Reads a 4 byte big-endian length header and then exactly that many bytes,
+    /// so the padding up to the carrier capacity is not part of the content
+    pub fn of_sized(dec: &mut dyn Read) -> Result<Self> {
+        let len = dec.read_u32::<BigEndian>()? as usize;
+        let mut content = Vec::new();
+        dec.take(len as u64).read_to_end(&mut content)?;
+        if content.len() != len {
+            return Err(SteganoError::TruncatedRawData(len, content.len()));
+        }
+
+        Ok(Self { content })
+    }
+
+    /// Writes a 4 byte big-endian length header followed by the content, symmetric to `RawMessage::of_sized`.
+    /// Content beyond `u32::MAX` bytes fails with `SteganoError::RawDataTooLarge`.
+    pub fn write_sized_to(&self, enc: &mut dyn Write) -> Result<()> {
+        let len = u32::try_from(self.content.len())
+            .map_err(|_| SteganoError::RawDataTooLarge(self.content.len()))?;
+        enc.write_u32::<BigEndian>(len)?;
+        enc.write_all(&self.content)?;
+
+        Ok(())
+    }
+
     pub fn from_raw_data(
         dec: &mut dyn Read,
         codec_factory: &dyn PayloadCodecFactory,
     ) -> Result<Self> {
         let version = dec.read_u8()?;
-        let codec: Box<dyn PayloadCodec> =
-            codec_factory.create_codec(PayloadCodecFeatures::MixedFeatures(version))?;
+        let codec: Box<dyn PayloadCodec> = create_decoder(codec_factory, version)?;
 
         Ok(Self {
             content: codec.decode(dec)?,
@@ -54,4 +93,38 @@ mod raw_message_tests {
             "RawMessage.content should contain `He` ascii bytes"
         );
     }
+
+    #[test]
+    fn should_write_and_read_plain_bitstream() {
+        let raw = RawMessage {
+            content: vec![0xde, 0xad, 0xbe, 0xef],
+        };
+        let mut buf = Vec::new();
+        raw.write_to(&mut buf).expect("Failed to write RawMessage");
+        assert_eq!(buf, raw.content);
+
+        let m = RawMessage::of(&mut &buf[..]).expect("Failed to read RawMessage");
+        assert_eq!(m.content, raw.content);
+    }
+
+    #[test]
+    fn should_write_and_read_sized_bitstream() {
+        let raw = RawMessage {
+            content: vec![0xde, 0xad, 0xbe, 0xef],
+        };
+        let mut buf = Vec::new();
+        raw.write_sized_to(&mut buf)
+            .expect("Failed to write RawMessage");
+        assert_eq!(buf, [0, 0, 0, 4, 0xde, 0xad, 0xbe, 0xef]);
+
+        // some padding that must not end up in the content
+        buf.extend_from_slice(&[0; 16]);
+        let m = RawMessage::of_sized(&mut &buf[..]).expect("Failed to read RawMessage");
+        assert_eq!(m.content, raw.content);
+
+        assert!(matches!(
+            RawMessage::of_sized(&mut &buf[..6]),
+            Err(SteganoError::TruncatedRawData(4, 2))
+        ));
+    }
 }
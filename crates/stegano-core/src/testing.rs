@@ -0,0 +1,147 @@
+//! In-memory helpers for tests and fuzzing, available with the `test-utils` feature.
+
+use image::RgbaImage;
+
+use crate::media::payload::{FabA, FabS, PayloadCodecFactory};
+use crate::media::Media;
+use crate::{CodecOptions, Message, Result};
+
+/// Generates an opaque carrier image full of noise, the same seed always gives the same image.
+/// The noise makes it a busy image, like a photo, without shipping a fixture.
+pub fn generate_carrier(width: u32, height: u32, seed: u64) -> Media {
+    let mut pixels = noise(seed);
+    Media::Image(RgbaImage::from_fn(width, height, |_, _| {
+        image::Rgba([
+            pixels.next().unwrap(),
+            pixels.next().unwrap(),
+            pixels.next().unwrap(),
+            255,
+        ])
+    }))
+}
+
+/// A tiny xorshift, good enough to get reproducible noise for payloads and carriers,
+/// the same seed always gives the same bytes
+pub fn noise(seed: u64) -> impl Iterator<Item = u8> {
+    // xorshift gets stuck on a zero state
+    let mut state = seed.max(1);
+    std::iter::repeat_with(move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> 24) as u8
+    })
+}
+
+/// Hides the message in the carrier and unveils it again, entirely in memory.
+/// Returns all unveiled files, the text of the message is returned as `secret-message.txt`.
+pub fn roundtrip(
+    carrier: Media,
+    message: Message,
+    opts: &CodecOptions,
+    password: Option<&str>,
+) -> Result<Vec<(String, Vec<u8>)>> {
+    let fab: Box<dyn PayloadCodecFactory> = if let Some(password) = password {
+        Box::new(FabS::new(password))
+    } else {
+        Box::new(FabA)
+    };
+
+    let mut media = carrier;
+    media.hide_data(message.to_raw_data(&*fab)?, opts)?;
+
+    let msg = Message::from_raw_data(&mut media.decoder(opts), &*fab)?;
+
+    let mut files = msg.files;
+    if let Some(text) = msg.text {
+        files.push(("secret-message.txt".to_owned(), text.into_bytes()));
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::prepare_5x5_image;
+    use crate::SteganoError;
+
+    fn message_with(payload: Vec<u8>) -> Message {
+        let mut message = Message::empty();
+        message.add_file_data("payload.bin", payload).unwrap();
+        message
+    }
+
+    #[test]
+    fn should_roundtrip_random_payloads_of_varying_sizes() {
+        let carrier = generate_carrier(256, 256, 42);
+
+        for (seed, size) in [0, 1, 17, 255, 1024, 8000].into_iter().enumerate() {
+            let payload: Vec<u8> = noise(seed as u64 + 1).take(size).collect();
+            let files = roundtrip(
+                carrier.clone(),
+                message_with(payload.clone()),
+                &CodecOptions::default(),
+                None,
+            )
+            .unwrap_or_else(|e| panic!("Roundtrip of {size} bytes failed: {e}"));
+
+            assert_eq!(files, vec![("payload.bin".to_string(), payload)]);
+        }
+    }
+
+    #[test]
+    fn should_roundtrip_with_password() {
+        let carrier = RgbaImage::from_pixel(64, 64, image::Rgba([128, 64, 32, 255]));
+        let payload: Vec<u8> = noise(7).take(300).collect();
+
+        let files = roundtrip(
+            Media::Image(carrier),
+            message_with(payload.clone()),
+            &CodecOptions::default(),
+            Some("SuperSecret42"),
+        )
+        .expect("Roundtrip with password failed");
+
+        assert_eq!(files, vec![("payload.bin".to_string(), payload)]);
+    }
+
+    #[test]
+    fn should_fail_cleanly_on_a_too_small_carrier() {
+        let payload: Vec<u8> = noise(3).take(10).collect();
+
+        let result = roundtrip(
+            Media::Image(prepare_5x5_image()),
+            message_with(payload),
+            &CodecOptions::default(),
+            None,
+        );
+
+        assert!(matches!(result, Err(SteganoError::ImageCapacityError(..))));
+    }
+
+    #[test]
+    fn should_generate_a_reproducible_carrier_that_holds_a_payload() {
+        let carrier = generate_carrier(64, 64, 7);
+        let Media::Image(image) = &carrier else {
+            panic!("The generated carrier is no image");
+        };
+        assert_eq!(image.dimensions(), (64, 64));
+        let Media::Image(same) = generate_carrier(64, 64, 7) else {
+            unreachable!()
+        };
+        assert_eq!(image, &same);
+
+        let payload: Vec<u8> = noise(11).take(100).collect();
+        assert!(carrier.capacity_bytes(&CodecOptions::default()) >= payload.len());
+        let files = roundtrip(
+            carrier,
+            message_with(payload.clone()),
+            &CodecOptions::default(),
+            None,
+        )
+        .expect("Roundtrip in a generated carrier failed");
+
+        assert_eq!(files, vec![("payload.bin".to_string(), payload)]);
+    }
+}
@@ -1,12 +1,15 @@
-use bitstream_io::{BitWrite, BitWriter, LittleEndian};
+use bitstream_io::{BitWrite, BitWriter, Endianness, LittleEndian};
 use enum_dispatch::enum_dispatch;
 use std::io::{BufWriter, Read, Result};
+use std::marker::PhantomData;
 
 use crate::media::MediaPrimitive;
 
 #[enum_dispatch]
+#[allow(clippy::enum_variant_names)]
 pub enum UnveilAlgorithms {
     OneBitUnveil,
+    BitPlaneUnveil,
 }
 
 /// generic unveil algorithm
@@ -15,15 +18,17 @@ pub trait UnveilAlgorithm {
     fn decode(&self, carrier: MediaPrimitive) -> bool;
 }
 
-/// generic stegano decoder
-pub struct UniversalDecoder<I, A>
+/// generic stegano decoder, `E` is the order in which the bits are packed into a byte
+pub struct UniversalDecoder<I, A, E = LittleEndian>
 where
     I: Iterator<Item = MediaPrimitive>,
     A: UnveilAlgorithm,
+    E: Endianness,
 {
     pub input: I,
     pub algorithm: A,
     position: usize,
+    bit_order: PhantomData<E>,
 }
 
 /// generic stegano decoder constructor method
@@ -33,24 +38,38 @@ where
     A: UnveilAlgorithm,
 {
     pub fn new(input: I, algorithm: A) -> Self {
+        Self::with_bit_order(input, algorithm)
+    }
+}
+
+impl<I, A, E> UniversalDecoder<I, A, E>
+where
+    I: Iterator<Item = MediaPrimitive>,
+    A: UnveilAlgorithm,
+    E: Endianness,
+{
+    /// like `new`, but the bits are packed into bytes in the order of `E`
+    pub fn with_bit_order(input: I, algorithm: A) -> Self {
         UniversalDecoder {
             input,
             algorithm,
             position: 0,
+            bit_order: PhantomData,
         }
     }
 }
 
-impl<I, A> Read for UniversalDecoder<I, A>
+impl<I, A, E> Read for UniversalDecoder<I, A, E>
 where
     I: Iterator<Item = MediaPrimitive>,
     A: UnveilAlgorithm,
+    E: Endianness,
 {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         // TODO better let the algorithm determine the density of decoding
         let items_to_take = buf.len() << 3; // 8 primitives = 1 byte
         let buf_writer = BufWriter::new(buf);
-        let mut bit_buffer = BitWriter::endian(buf_writer, LittleEndian);
+        let mut bit_buffer = BitWriter::<_, E>::new(buf_writer);
 
         let mut bit_read: usize = 0;
         for carrier in self.input.by_ref().take(items_to_take) {
@@ -78,6 +97,28 @@ impl UnveilAlgorithm for OneBitUnveil {
         match carrier {
             MediaPrimitive::ImageColorChannel(b) => (b & 0x1) > 0,
             MediaPrimitive::AudioSample(b) => (b & 0x1) > 0,
+            MediaPrimitive::AudioFloatSample(f) => (f.to_bits() & 0x1) > 0,
+        }
+    }
+}
+
+/// reads the bit of the given bit plane, plane 0 is the least significant bit,
+/// a plane beyond the primitive reads as 0
+pub struct BitPlaneUnveil(pub u8);
+impl UnveilAlgorithm for BitPlaneUnveil {
+    #[inline]
+    fn decode(&self, carrier: MediaPrimitive) -> bool {
+        match carrier {
+            MediaPrimitive::ImageColorChannel(b) => {
+                b.checked_shr(self.0.into()).is_some_and(|b| b & 0x1 > 0)
+            }
+            MediaPrimitive::AudioSample(b) => {
+                b.checked_shr(self.0.into()).is_some_and(|b| b & 0x1 > 0)
+            }
+            MediaPrimitive::AudioFloatSample(f) => f
+                .to_bits()
+                .checked_shr(self.0.into())
+                .is_some_and(|b| b & 0x1 > 0),
         }
     }
 }
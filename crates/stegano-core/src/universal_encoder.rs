@@ -1,30 +1,35 @@
-use bitstream_io::{BitRead, BitReader, LittleEndian};
+use bitstream_io::{BitRead, BitReader, Endianness, LittleEndian};
 use enum_dispatch::enum_dispatch;
 use std::io::{Cursor, Result, Write};
+use std::marker::PhantomData;
 
 use crate::media::MediaPrimitiveMut;
 
 #[enum_dispatch]
+#[allow(clippy::enum_variant_names)]
 pub enum HideAlgorithms {
     OneBitHide,
     OneBitInLowFrequencyHide,
+    BitPlaneHide,
 }
 
 /// generic hiding algorithm, used for specific ones like LSB
 #[enum_dispatch(HideAlgorithms)]
 pub trait HideAlgorithm {
-    /// encodes one bit onto a carrier T e.g. u8 or i16
+    /// encodes one bit onto a carrier T e.g. u8 or i32
     fn encode(&self, carrier: MediaPrimitiveMut, information: &Result<bool>);
 }
 
-/// generic stegano encoder
-pub struct UniversalEncoder<'c, C, A>
+/// generic stegano encoder, `E` is the order in which the bits of a byte are hidden
+pub struct UniversalEncoder<'c, C, A, E = LittleEndian>
 where
     C: Iterator<Item = MediaPrimitiveMut<'c>>,
     A: HideAlgorithm,
+    E: Endianness,
 {
     pub carrier: C,
     pub algorithm: A,
+    bit_order: PhantomData<E>,
 }
 
 impl<'c, C, A> UniversalEncoder<'c, C, A>
@@ -33,20 +38,37 @@ where
     A: HideAlgorithm,
 {
     pub fn new(carrier: C, algorithm: A) -> Self {
-        UniversalEncoder { carrier, algorithm }
+        Self::with_bit_order(carrier, algorithm)
     }
 }
 
-impl<'c, C, A> Write for UniversalEncoder<'c, C, A>
+impl<'c, C, A, E> UniversalEncoder<'c, C, A, E>
 where
     C: Iterator<Item = MediaPrimitiveMut<'c>>,
     A: HideAlgorithm,
+    E: Endianness,
+{
+    /// like `new`, but the bits of a byte are hidden in the order of `E`
+    pub fn with_bit_order(carrier: C, algorithm: A) -> Self {
+        UniversalEncoder {
+            carrier,
+            algorithm,
+            bit_order: PhantomData,
+        }
+    }
+}
+
+impl<'c, C, A, E> Write for UniversalEncoder<'c, C, A, E>
+where
+    C: Iterator<Item = MediaPrimitiveMut<'c>>,
+    A: HideAlgorithm,
+    E: Endianness,
 {
     #[inline]
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
         // TODO better let the algorithm determine the density of encoding
         let items_to_take = buf.len() << 3; // 1 bit per sample <=> * 8 <=> << 3
-        let mut bit_iter = BitReader::endian(Cursor::new(buf), LittleEndian);
+        let mut bit_iter = BitReader::<_, E>::new(Cursor::new(buf));
         let mut bit_written: usize = 0;
         for s in self.carrier.by_ref().take(items_to_take) {
             self.algorithm.encode(s, &bit_iter.read_bit());
@@ -72,8 +94,41 @@ impl HideAlgorithm for OneBitHide {
                 MediaPrimitiveMut::ImageColorChannel(b) => {
                     *b = ((*b) & (u8::MAX - 1)) | if *bit { 1 } else { 0 }
                 }
+                MediaPrimitiveMut::AudioSample(b) => *b = ((*b) & !1) | if *bit { 1 } else { 0 },
+                MediaPrimitiveMut::AudioFloatSample(f) => {
+                    *f = f32::from_bits((f.to_bits() & !1) | if *bit { 1 } else { 0 })
+                }
+            }
+        }
+    }
+}
+
+/// 1 bit hiding strategy that writes to the given bit plane, plane 0 is the least significant bit,
+/// a plane beyond the primitive is left untouched
+#[derive(Debug)]
+pub struct BitPlaneHide(pub u8);
+impl HideAlgorithm for BitPlaneHide {
+    #[inline(always)]
+    fn encode(&self, carrier: MediaPrimitiveMut, information: &Result<bool>) {
+        if let Ok(bit) = information {
+            match carrier {
+                MediaPrimitiveMut::ImageColorChannel(b) => {
+                    let Some(mask) = 1u8.checked_shl(self.0.into()) else {
+                        return;
+                    };
+                    *b = ((*b) & !mask) | if *bit { mask } else { 0 }
+                }
                 MediaPrimitiveMut::AudioSample(b) => {
-                    *b = ((*b) & (i16::MAX - 1)) | if *bit { 1 } else { 0 }
+                    let Some(mask) = 1i32.checked_shl(self.0.into()) else {
+                        return;
+                    };
+                    *b = ((*b) & !mask) | if *bit { mask } else { 0 }
+                }
+                MediaPrimitiveMut::AudioFloatSample(f) => {
+                    let Some(mask) = 1u32.checked_shl(self.0.into()) else {
+                        return;
+                    };
+                    *f = f32::from_bits((f.to_bits() & !mask) | if *bit { mask } else { 0 })
                 }
             }
         }
@@ -92,7 +147,11 @@ impl HideAlgorithm for OneBitInLowFrequencyHide {
                     *b = ((*b) & 0b11110000) | if *bit { 0b00001111 } else { 0 }
                 }
                 MediaPrimitiveMut::AudioSample(b) => {
-                    *b = ((*b) & (0b11111111 << 8)) | if *bit { 0b000000011111111 } else { 0 }
+                    *b = ((*b) & !0b11111111) | if *bit { 0b11111111 } else { 0 }
+                }
+                MediaPrimitiveMut::AudioFloatSample(f) => {
+                    let bits = (f.to_bits() & !0b11111111) | if *bit { 0b11111111 } else { 0 };
+                    *f = f32::from_bits(bits)
                 }
             }
         }
@@ -159,4 +218,27 @@ mod tests {
         }
         assert_eq!(data, 0b00001110);
     }
+
+    #[test]
+    fn should_keep_the_sign_of_negative_audio_samples() {
+        let encoder = OneBitHide;
+        let mut data = -6;
+        {
+            let mp = MediaPrimitiveMut::AudioSample(&mut data);
+            encoder.encode(mp, &Ok(true));
+        }
+        assert_eq!(data, -5);
+    }
+
+    #[test]
+    fn should_encode_one_bit_in_the_mantissa_of_float_samples() {
+        let encoder = OneBitHide;
+        let mut data = -0.5f32;
+        {
+            let mp = MediaPrimitiveMut::AudioFloatSample(&mut data);
+            encoder.encode(mp, &Ok(true));
+        }
+        assert_eq!(data.to_bits(), (-0.5f32).to_bits() | 1);
+        assert!(data < 0.0 && (data + 0.5).abs() < 1e-6);
+    }
 }
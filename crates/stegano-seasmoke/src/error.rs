@@ -15,4 +15,13 @@ pub enum SeasmokeError {
 
     #[error("Encryption error")]
     EncryptionError(Chacha20Poly1305Error),
+
+    #[error("Ciphertext is malformed, it is too short to contain nonce, salt and tag")]
+    MalformedCiphertext,
+
+    #[error("Data was encrypted with a raw key, a key is required to decrypt it")]
+    KeyRequired,
+
+    #[error("Data was encrypted with a password, a password is required to decrypt it")]
+    PasswordRequired,
 }
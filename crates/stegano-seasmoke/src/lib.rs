@@ -1,12 +1,13 @@
 //! # Password Hashing
 //! This little lib explores on
 
+use aes_gcm::Aes256Gcm;
 use argon2::{Argon2, ParamsBuilder};
-use chacha20poly1305::aead::{Aead, AeadCore};
+use chacha20poly1305::aead::{Aead as _, AeadCore};
 use chacha20poly1305::{KeyInit, XChaCha20Poly1305};
 use rand::rngs::OsRng;
 use rand::RngCore;
-use zeroize::Zeroize;
+use zeroize::{Zeroize, Zeroizing};
 
 pub mod error;
 pub mod ffi;
@@ -17,26 +18,184 @@ pub use crate::error::SeasmokeError;
 const NONCE_LEN: usize = 24;
 const SALT_LEN: usize = 32;
 const KEY_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+
+/// fixed salt for the position seed, it must be reproducible from the password alone
+const SEED_SALT: &[u8] = b"stegano-rs/lsb-permutation";
+
+/// trailing mode byte, marks the key as derived from a password via argon2id
+const MODE_PASSWORD: u8 = 0x01;
+/// trailing mode byte, marks the key as provided raw by the caller
+const MODE_KEY: u8 = 0x02;
+/// trailing mode byte, like `MODE_PASSWORD` but followed by the cipher and the nonce length
+const MODE_PASSWORD_CIPHER: u8 = 0x03;
+
+/// the AEAD cipher that encrypts the data, the key is always 256 bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Aead {
+    /// 192-bit nonce, fast in software
+    #[default]
+    XChaCha20Poly1305,
+    /// 96-bit nonce, fast on hardware with AES-NI
+    Aes256Gcm,
+}
+
+impl Aead {
+    /// the length of the random nonce in bytes
+    pub fn nonce_len(self) -> usize {
+        match self {
+            Aead::XChaCha20Poly1305 => NONCE_LEN,
+            Aead::Aes256Gcm => 12,
+        }
+    }
+
+    /// the bytes `encrypt_data_with_cipher` adds to the plaintext, the tag, nonce, salt and header
+    pub fn password_overhead(self) -> usize {
+        TAG_LEN + self.nonce_len() + SALT_LEN + 3
+    }
+
+    fn id(self) -> u8 {
+        match self {
+            Aead::XChaCha20Poly1305 => 0x01,
+            Aead::Aes256Gcm => 0x02,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0x01 => Some(Aead::XChaCha20Poly1305),
+            0x02 => Some(Aead::Aes256Gcm),
+            _ => None,
+        }
+    }
+
+    fn encrypt(self, key: &Key, nonce: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Aead::XChaCha20Poly1305 => {
+                XChaCha20Poly1305::new(key.into()).encrypt(nonce.into(), data)
+            }
+            Aead::Aes256Gcm => Aes256Gcm::new(key.into()).encrypt(nonce.into(), data),
+        }
+        .map_err(SeasmokeError::EncryptionError)
+    }
+
+    fn decrypt(self, key: &Key, nonce: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Aead::XChaCha20Poly1305 => {
+                XChaCha20Poly1305::new(key.into()).decrypt(nonce.into(), data)
+            }
+            Aead::Aes256Gcm => Aes256Gcm::new(key.into()).decrypt(nonce.into(), data),
+        }
+        .map_err(SeasmokeError::DecryptionError)
+    }
+}
+
+/// the bytes `encrypt_data` adds to the plaintext, the tag, nonce, salt and mode byte
+pub const PASSWORD_OVERHEAD: usize = TAG_LEN + NONCE_LEN + SALT_LEN + 1;
+/// the bytes `encrypt_data_with_key` adds to the plaintext, the tag, nonce and mode byte
+pub const KEY_OVERHEAD: usize = TAG_LEN + NONCE_LEN + 1;
 
 pub type Result<T> = std::result::Result<T, SeasmokeError>;
 pub type Key = [u8; KEY_LEN];
 
-/// decrypt data with password, it uses argon2id for key derivation and XChaCha20Poly1305 for encryption
+/// decrypt data with password, it uses argon2id for key derivation and the cipher that is
+/// recorded in the data, XChaCha20Poly1305 if none is recorded
+///
+/// Data that was encrypted before the mode byte was introduced is still supported, unless its
+/// salt happens to end like a mode byte. That layout is only tried if the data is malformed as
+/// the recorded mode, so a wrong password costs a single key derivation. Data that is known to
+/// be in that layout goes to `decrypt_legacy_data` instead.
 pub fn decrypt_data(password: &str, data: &[u8]) -> Result<Vec<u8>> {
-    assert!(data.len() >= SALT_LEN + NONCE_LEN, "data is too short");
+    match data.split_last() {
+        Some((&MODE_PASSWORD, body)) => {
+            or_legacy_layout(decrypt_password_body(password, body), password, data)
+        }
+        Some((&MODE_PASSWORD_CIPHER, body)) => {
+            or_legacy_layout(decrypt_cipher_body(password, body), password, data)
+        }
+        Some((&MODE_KEY, _)) => {
+            decrypt_password_body(password, data).map_err(|_| SeasmokeError::KeyRequired)
+        }
+        _ => decrypt_password_body(password, data),
+    }
+}
+
+/// decrypts the data in the layout before the mode byte, if it is malformed as the recorded mode,
+/// if it does not fit that layout either it stays malformed
+fn or_legacy_layout(decrypted: Result<Vec<u8>>, password: &str, data: &[u8]) -> Result<Vec<u8>> {
+    match decrypted {
+        Err(SeasmokeError::MalformedCiphertext) => {
+            decrypt_password_body(password, data).map_err(|_| SeasmokeError::MalformedCiphertext)
+        }
+        decrypted => decrypted,
+    }
+}
+
+/// decrypt data in the layout before the mode byte was introduced, no matter how its salt ends
+pub fn decrypt_legacy_data(password: &str, data: &[u8]) -> Result<Vec<u8>> {
+    decrypt_password_body(password, data)
+}
+
+/// decrypt data exactly as `encrypt_data` encrypted it, so the key is derived only once,
+/// e.g. for many small ciphertexts of which most do not fit the password
+pub fn decrypt_data_once(password: &str, data: &[u8]) -> Result<Vec<u8>> {
+    match data.split_last() {
+        Some((&MODE_PASSWORD, body)) => decrypt_password_body(password, body),
+        _ => Err(SeasmokeError::MalformedCiphertext),
+    }
+}
+
+/// decrypt data with a raw 256-bit key, it uses XChaCha20Poly1305 without any key derivation
+pub fn decrypt_data_with_key(key: &Key, data: &[u8]) -> Result<Vec<u8>> {
+    let Some((&MODE_KEY, body)) = data.split_last() else {
+        return Err(SeasmokeError::PasswordRequired);
+    };
+    if body.len() < NONCE_LEN + TAG_LEN {
+        return Err(SeasmokeError::MalformedCiphertext);
+    }
+    let nonce = &body[body.len() - NONCE_LEN..];
+
+    let decryptor = XChaCha20Poly1305::new(key.into());
+    let decipher_data = decryptor
+        .decrypt(nonce.into(), &body[0..body.len() - NONCE_LEN])
+        .map_err(SeasmokeError::DecryptionError)?;
+
+    Ok(decipher_data)
+}
+
+fn decrypt_password_body(password: &str, data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < SALT_LEN + NONCE_LEN + TAG_LEN {
+        return Err(SeasmokeError::MalformedCiphertext);
+    }
     let salt = &data[data.len() - SALT_LEN..];
     let nonce = &data[data.len() - SALT_LEN - NONCE_LEN..data.len() - SALT_LEN];
     let key = derive_key(password.as_bytes(), salt)?;
 
-    let decryptor = XChaCha20Poly1305::new(&key.into());
+    let decryptor = XChaCha20Poly1305::new(key.as_ref().into());
     let decipher_data = decryptor
         .decrypt(nonce.into(), &data[0..data.len() - SALT_LEN - NONCE_LEN])
         .map_err(SeasmokeError::DecryptionError)?;
 
-    // todo write tests for enc and decrypt
     Ok(decipher_data)
 }
 
+/// layout: ciphertext, nonce, salt, nonce length, cipher id
+fn decrypt_cipher_body(password: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let [body @ .., nonce_len, cipher] = data else {
+        return Err(SeasmokeError::MalformedCiphertext);
+    };
+    let cipher = Aead::from_id(*cipher).ok_or(SeasmokeError::MalformedCiphertext)?;
+    let nonce_len = *nonce_len as usize;
+    if nonce_len != cipher.nonce_len() || body.len() < SALT_LEN + nonce_len + TAG_LEN {
+        return Err(SeasmokeError::MalformedCiphertext);
+    }
+    let (body, salt) = body.split_at(body.len() - SALT_LEN);
+    let (cipher_data, nonce) = body.split_at(body.len() - nonce_len);
+    let key = derive_key(password.as_bytes(), salt)?;
+
+    cipher.decrypt(&key, nonce, cipher_data)
+}
+
 /// encrypt data with password, it uses argon2id for key derivation and XChaCha20Poly1305 for encryption
 pub fn encrypt_data(password: &str, data: &[u8]) -> Result<Vec<u8>> {
     // https://kerkour.com/rust-file-encryption-chacha20poly1305-argon2
@@ -47,12 +206,13 @@ pub fn encrypt_data(password: &str, data: &[u8]) -> Result<Vec<u8>> {
     let mut nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
     assert!(nonce.len() == NONCE_LEN);
 
-    let encryptor = XChaCha20Poly1305::new(&key.into());
+    let encryptor = XChaCha20Poly1305::new(key.as_ref().into());
     let mut cipher_data = encryptor
         .encrypt(&nonce, data)
         .map_err(SeasmokeError::EncryptionError)?;
     cipher_data.extend_from_slice(&nonce);
     cipher_data.extend_from_slice(&salt);
+    cipher_data.push(MODE_PASSWORD);
 
     nonce.zeroize();
     salt.zeroize();
@@ -60,6 +220,63 @@ pub fn encrypt_data(password: &str, data: &[u8]) -> Result<Vec<u8>> {
     Ok(cipher_data)
 }
 
+/// encrypt data with password, it uses argon2id for key derivation and the given cipher for encryption
+///
+/// The cipher and its nonce length are recorded in the data, `decrypt_data` picks them up.
+pub fn encrypt_data_with_cipher(password: &str, data: &[u8], cipher: Aead) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(password.as_bytes(), &salt)?;
+
+    let mut nonce = Zeroizing::new(vec![0u8; cipher.nonce_len()]);
+    OsRng.fill_bytes(&mut nonce);
+
+    let mut cipher_data = cipher.encrypt(&key, &nonce, data)?;
+    cipher_data.extend_from_slice(&nonce);
+    cipher_data.extend_from_slice(&salt);
+    cipher_data.push(cipher.nonce_len() as u8);
+    cipher_data.push(cipher.id());
+    cipher_data.push(MODE_PASSWORD_CIPHER);
+
+    salt.zeroize();
+
+    Ok(cipher_data)
+}
+
+/// encrypt data with a raw 256-bit key, it uses XChaCha20Poly1305 without any key derivation
+pub fn encrypt_data_with_key(key: &Key, data: &[u8]) -> Result<Vec<u8>> {
+    let mut nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    assert!(nonce.len() == NONCE_LEN);
+
+    let encryptor = XChaCha20Poly1305::new(key.into());
+    let mut cipher_data = encryptor
+        .encrypt(&nonce, data)
+        .map_err(SeasmokeError::EncryptionError)?;
+    cipher_data.extend_from_slice(&nonce);
+    cipher_data.push(MODE_KEY);
+
+    nonce.zeroize();
+
+    Ok(cipher_data)
+}
+
+/// generate a random 256-bit key, e.g. a data key that is then wrapped for several passwords
+pub fn generate_key() -> Zeroizing<Key> {
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    OsRng.fill_bytes(key.as_mut());
+
+    key
+}
+
+/// derive a seed from the password via argon2id, e.g. to shuffle where data is placed
+pub fn derive_seed(password: &str) -> Result<u64> {
+    let key = derive_key(password.as_bytes(), SEED_SALT)?;
+    let mut seed = [0u8; 8];
+    seed.copy_from_slice(&key[..8]);
+
+    Ok(u64::from_le_bytes(seed))
+}
+
 fn default_secure_argon<'key>() -> Result<Argon2<'key>> {
     // increased time costs to make it more secure
     let params = ParamsBuilder::default()
@@ -75,10 +292,11 @@ fn default_secure_argon<'key>() -> Result<Argon2<'key>> {
     ))
 }
 
-fn derive_key(password: &[u8], salt: &[u8]) -> Result<[u8; 32]> {
-    let mut output_key_material = [0u8; 32]; // Can be any desired size
+/// the derived key is scrubbed from memory as soon as it is dropped
+fn derive_key(password: &[u8], salt: &[u8]) -> Result<Zeroizing<Key>> {
+    let mut output_key_material = Zeroizing::new([0u8; KEY_LEN]);
     default_secure_argon()?
-        .hash_password_into(password, salt, &mut output_key_material)
+        .hash_password_into(password, salt, output_key_material.as_mut())
         .map_err(SeasmokeError::KeyDerivationError)?;
 
     Ok(output_key_material)
@@ -142,4 +360,177 @@ mod tests {
         assert_ne!(data, cipher_data.as_slice());
         assert_eq!(data, decipher_data.as_slice());
     }
+
+    #[test]
+    fn test_encryption_round_trip_with_raw_key() {
+        let key: Key = rand::random();
+        let data = b"lorem ipsum dolor sit amet, consectetur adipiscing elit.";
+
+        let cipher_data = encrypt_data_with_key(&key, data).unwrap();
+        assert_eq!(cipher_data.last(), Some(&MODE_KEY));
+        let decipher_data = decrypt_data_with_key(&key, &cipher_data).unwrap();
+        assert_eq!(data, decipher_data.as_slice());
+
+        let other_key: Key = rand::random();
+        assert!(decrypt_data_with_key(&other_key, &cipher_data).is_err());
+        assert!(matches!(
+            decrypt_data("resistance is futile", &cipher_data),
+            Err(SeasmokeError::KeyRequired)
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_malformed_data_errors_cleanly() {
+        let password = "resistance is futile";
+        let key: Key = rand::random();
+        let cipher_data = encrypt_data(password, b"lorem ipsum").unwrap();
+        let key_cipher_data = encrypt_data_with_key(&key, b"lorem ipsum").unwrap();
+
+        assert!(matches!(
+            decrypt_data(password, &[]),
+            Err(SeasmokeError::MalformedCiphertext)
+        ));
+        assert!(matches!(
+            decrypt_data(password, &cipher_data[..SALT_LEN + NONCE_LEN]),
+            Err(SeasmokeError::MalformedCiphertext)
+        ));
+        assert!(matches!(
+            decrypt_data_with_key(&key, &[MODE_KEY]),
+            Err(SeasmokeError::MalformedCiphertext)
+        ));
+        let mut truncated = key_cipher_data[..NONCE_LEN + TAG_LEN - 1].to_vec();
+        truncated.push(MODE_KEY);
+        assert!(matches!(
+            decrypt_data_with_key(&key, &truncated),
+            Err(SeasmokeError::MalformedCiphertext)
+        ));
+
+        // long enough, but not a valid ciphertext
+        assert!(matches!(
+            decrypt_data(password, &cipher_data[1..]),
+            Err(SeasmokeError::DecryptionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_data_once_supports_only_the_current_layout() {
+        let encrypted = encrypt_data("hunter42", b"secret").unwrap();
+        assert_eq!(
+            decrypt_data_once("hunter42", &encrypted).unwrap(),
+            b"secret"
+        );
+        assert!(matches!(
+            decrypt_data_once("hunter42", &encrypted[..encrypted.len() - 1]),
+            Err(SeasmokeError::MalformedCiphertext)
+        ));
+    }
+
+    #[test]
+    fn test_derived_key_is_zeroized_on_drop() {
+        fn assert_zeroize_on_drop<T: zeroize::ZeroizeOnDrop>(_: &T) {}
+
+        let salt = [42u8; SALT_LEN];
+        let key = derive_key(b"hunter42", &salt).unwrap();
+        assert_zeroize_on_drop(&key);
+        assert_eq!(key, derive_key(b"hunter42", &salt).unwrap());
+        assert_ne!(*key, [0u8; KEY_LEN]);
+    }
+
+    #[test]
+    fn test_seed_derivation_is_stable_per_password() {
+        let seed = derive_seed("hunter42").unwrap();
+        assert_eq!(seed, derive_seed("hunter42").unwrap());
+        assert_ne!(seed, derive_seed("hunter43").unwrap());
+    }
+
+    #[test]
+    fn test_decrypt_data_without_mode_byte() {
+        let password = "resistance is futile";
+        let data = b"lorem ipsum dolor sit amet";
+        let cipher_data = encrypt_legacy_data(password, data, [0x2a; SALT_LEN]);
+
+        let decipher_data = decrypt_data(password, &cipher_data).unwrap();
+
+        assert_eq!(data, decipher_data.as_slice());
+    }
+
+    #[test]
+    fn test_decrypt_legacy_data_with_a_salt_that_ends_like_a_mode_byte() {
+        let password = "resistance is futile";
+        let data = b"lorem ipsum dolor sit amet";
+        let mut salt = [0x2a; SALT_LEN];
+        salt[SALT_LEN - 1] = MODE_PASSWORD;
+        let cipher_data = encrypt_legacy_data(password, data, salt);
+
+        let decipher_data = decrypt_legacy_data(password, &cipher_data).unwrap();
+
+        assert_eq!(data, decipher_data.as_slice());
+        assert!(matches!(
+            decrypt_legacy_data("resistance is fertile", &cipher_data),
+            Err(SeasmokeError::DecryptionError(_))
+        ));
+    }
+
+    /// encrypts like `encrypt_data` did before the mode byte, with a fixed salt
+    fn encrypt_legacy_data(password: &str, data: &[u8], salt: [u8; SALT_LEN]) -> Vec<u8> {
+        let key = derive_key(password.as_bytes(), &salt).unwrap();
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut cipher_data = XChaCha20Poly1305::new(key.as_ref().into())
+            .encrypt(&nonce, data)
+            .unwrap();
+        cipher_data.extend_from_slice(&nonce);
+        cipher_data.extend_from_slice(&salt);
+
+        cipher_data
+    }
+
+    #[test]
+    fn test_encryption_round_trip_with_each_cipher() {
+        let password = "resistance is futile";
+        let data = b"lorem ipsum dolor sit amet, consectetur adipiscing elit.";
+
+        for cipher in [Aead::XChaCha20Poly1305, Aead::Aes256Gcm] {
+            let cipher_data = encrypt_data_with_cipher(password, data, cipher).unwrap();
+            assert_eq!(cipher_data.len(), data.len() + cipher.password_overhead());
+            assert_eq!(cipher_data.last(), Some(&MODE_PASSWORD_CIPHER));
+
+            let decipher_data = decrypt_data(password, &cipher_data).unwrap();
+            assert_eq!(data, decipher_data.as_slice(), "{cipher:?}");
+            assert!(decrypt_data("resistance is fertile", &cipher_data).is_err());
+        }
+    }
+
+    #[test]
+    fn test_decrypt_data_picks_the_cipher_from_the_header() {
+        let password = "resistance is futile";
+        let data = b"lorem ipsum dolor sit amet";
+
+        let aes = encrypt_data_with_cipher(password, data, Aead::Aes256Gcm).unwrap();
+        let chacha = encrypt_data_with_cipher(password, data, Aead::XChaCha20Poly1305).unwrap();
+        assert_eq!(aes[aes.len() - 3], 12);
+        assert_eq!(chacha[chacha.len() - 3], NONCE_LEN as u8);
+        assert_eq!(decrypt_data(password, &aes).unwrap(), data);
+        assert_eq!(decrypt_data(password, &chacha).unwrap(), data);
+
+        // recording the other cipher makes the nonce length mismatch
+        let mut swapped = aes.clone();
+        let id = swapped.len() - 2;
+        swapped[id] = Aead::XChaCha20Poly1305.id();
+        assert!(matches!(
+            decrypt_data(password, &swapped),
+            Err(SeasmokeError::MalformedCiphertext)
+        ));
+        // and a consistent header of the other cipher does not authenticate
+        let mut forged = aes[..aes.len() - 3].to_vec();
+        forged.splice(forged.len() - SALT_LEN..forged.len() - SALT_LEN, [0; 12]);
+        forged.extend_from_slice(&[
+            NONCE_LEN as u8,
+            Aead::XChaCha20Poly1305.id(),
+            MODE_PASSWORD_CIPHER,
+        ]);
+        assert!(matches!(
+            decrypt_data(password, &forged),
+            Err(SeasmokeError::DecryptionError(_))
+        ));
+    }
 }